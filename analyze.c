@@ -0,0 +1,1625 @@
+//
+// Offline analysis of raw captures
+//
+// Same input format as 'convert' (mono 32-bit samples at
+// 48kHz), but instead of processing the samples one by one
+// this reads the whole thing into memory and then reports
+// on it.
+//
+//	./analyze stats capture.raw
+//	./analyze silence -60 capture.raw
+//	./analyze thd 1000 capture.raw
+//	./analyze snr 1000 capture.raw
+//	./analyze pitch capture.raw
+//	./analyze hilbert capture.raw
+//	./analyze ir 10 sweep-recording.raw
+//
+// Use '--remove-dc' to subtract the DC offset before doing the
+// analysis, or '--remove-dc=<Hz>' to highpass it away instead.
+//
+// WAV, AIFF and CAF files are recognized by their header, and
+// multichannel ones are mixed down to mono. Their sample rate is
+// used unless '--rate' says otherwise. FLAC, MP3, Ogg, AAC/M4A
+// and compressed AIFC/CAF files get decoded with ffmpeg.
+//
+// Headerless files are 32-bit little-endian by default, but
+// '--format=<fmt>' takes any of the ffmpeg style names for the
+// other raw formats (s16le, s24be, f32le, f64be ..). Or use
+// '--format=auto' to have it guess from the samples themselves.
+//
+// A 'capture.raw.json' file next to a raw capture can say what
+// its rate, format and channels are (see sidecar.h), so that
+// they don't have to be given every time.
+//
+// Interleaved multichannel raw files need '--channels=<n>'. All
+// multichannel input is mixed down to mono unless '--channel=<n>'
+// (counting from zero) picks out just one channel.
+//
+// Instead of a file name, a tcp://, udp:// or http:// URL reads
+// raw samples from the network (see net.h) until the other end
+// closes the connection.
+//
+// Captures at some other sample rate can be analyzed with
+// '--rate=<Hz>', which resamples them to 48kHz first.
+//
+// Only part of a capture can be looked at with '--start=<time>'
+// and '--end=<time>', where the time is in seconds or hh:mm:ss
+// (see timepos.h). Reported times are still from the start of
+// the whole file.
+//
+// A part of a capture can be saved with 'export':
+//
+//	./analyze export --start=1:00 --end=1:10 --output=clip.wav capture.raw
+//
+// and '--normalize=peak|rms|lufs:<dB>' brings the saved part to
+// that peak or RMS level in dBFS, or to that integrated loudness
+// (see loudness.h).
+//
+// Long captures can be kept as zstd archives (say 'capture.raw.zst')
+// and read directly. Exporting to a ".zst" name writes one with a
+// seek table, so that '--start' and '--end' only need to unpack
+// the part that's actually looked at (see seekable.h).
+//
+// Some analyses compare two captures, and take a second file:
+//
+//	./analyze align input.raw loopback.raw
+//
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <unistd.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+#include <ctype.h>
+#include <limits.h>
+#include <sys/stat.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <netdb.h>
+#include <sys/wait.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "util.h"
+#include "measure.h"
+#include "biquad.h"
+#include "weighting.h"
+#include "loudness.h"
+#include "pitch.h"
+#include "fft.h"
+#include "onset.h"
+#include "resample.h"
+#include "correlate.h"
+#include "spectral.h"
+#include "sweep.h"
+#include "timepos.h"
+#include "audiofile.h"
+#include "sidecar.h"
+#include "net.h"
+#include "seekable.h"
+#include "hash.h"
+#include "peaks.h"
+#include "render.h"
+
+// The second capture, for the analyses that compare two
+static float *other;
+static int other_nr;
+
+// What to assume for files without a header
+static struct audio_format raw_format = AUDIO_FORMAT_RAW;
+static int channel = -1, guess_format;
+static int format_given, channels_given;
+
+// What the (first) file turned out to be, for 'probe'
+static struct audio_format input_info;
+static struct bext bext;
+static int have_bext, decoded_externally;
+
+// The optional preprocessing
+static float rate, dc_cutoff;
+static int dc;
+static s64 start, end;
+
+// Where 'export' and 'overview' write to
+static const char *export_name;
+static struct audio_format export_format = AUDIO_FORMAT_RAW;
+static struct render_options render = RENDER_OPTIONS;
+
+// What 'export' normalizes to, if anything
+static int normalize = -1;
+static float normalize_target;
+static const char *const normalize_modes[] = { "peak", "rms", "lufs" };
+
+static unsigned char *read_file(int fd, size_t *lenp)
+{
+	size_t len = 0, alloc = 0;
+	unsigned char *buf = NULL;
+
+	for (;;) {
+		if (len == alloc) {
+			alloc = alloc ? alloc * 2 : 262144;
+			buf = realloc(buf, alloc);
+			if (!buf)
+				return NULL;
+		}
+		ssize_t n = read(fd, buf + len, alloc - len);
+		if (n <= 0)
+			break;
+		len += n;
+	}
+	*lenp = len;
+	return buf;
+}
+
+//
+// Compressed files get decoded by ffmpeg, the same way the
+// Makefile turns the mp3 into input.raw. We already have the
+// whole file in memory (it may well have come from a pipe), so
+// a child process feeds it to ffmpeg while we read back the
+// raw 48kHz mono s32 samples.
+//
+static const char *const decoder[] = {
+	"ffmpeg", "-v", "error", "-i", "pipe:0",
+	"-f", "s32le", "-ac", "1", "-ar", "48000", "pipe:1", NULL
+};
+
+// zstd archives (see seekable.h) are unpacked the same way
+static const char *const unzstd[] = { "zstd", "-d", "-c", "-q", NULL };
+static const char *const zstd[] = { "zstd", "-c", "-q", NULL };
+
+// Anything that isn't a plain WAV, AIFF, CAF or raw file
+static int is_compressed(const unsigned char *buf, size_t len)
+{
+	enum audio_container type = audio_sniff(buf, len);
+	struct audio_format fmt;
+	size_t offset, size;
+
+	// AIFC and CAF files can have compressed samples in them
+	if (type == container_aiff || type == container_caf)
+		return audio_parse(buf, len, &fmt, &offset, &size) < 0;
+	return type > container_caf && type != container_zstd;
+}
+
+// Run 'buf' through the command in 'argv', and return what it outputs
+static unsigned char *run_filter(const char *const argv[], const unsigned char *buf, size_t len,
+	size_t *out_len)
+{
+	int in[2], out[2], status;
+	pid_t dec, feeder;
+	unsigned char *res;
+
+	if (pipe(in) < 0 || pipe(out) < 0)
+		return NULL;
+
+	dec = fork();
+	if (!dec) {
+		dup2(in[0], 0);
+		dup2(out[1], 1);
+		close(in[0]); close(in[1]);
+		close(out[0]); close(out[1]);
+		execvp(argv[0], (char **) argv);
+		perror(argv[0]);
+		_exit(127);
+	}
+	close(in[0]);
+	close(out[1]);
+
+	feeder = fork();
+	if (!feeder) {
+		close(out[0]);
+		while (len) {
+			ssize_t n = write(in[1], buf, len);
+			if (n <= 0)
+				break;
+			buf += n;
+			len -= n;
+		}
+		_exit(0);
+	}
+	close(in[1]);
+
+	res = read_file(out[0], out_len);
+	close(out[0]);
+	waitpid(feeder, NULL, 0);
+	if (waitpid(dec, &status, 0) < 0 || !WIFEXITED(status) || WEXITSTATUS(status)) {
+		fprintf(stderr, "Running %s failed\n", argv[0]);
+		exit(1);
+	}
+	return res;
+}
+
+//
+// With a seek table, a raw capture only needs the frames between
+// '--start' and '--end' unpacked, and *skip_bytes says where in
+// the capture the result starts. Anything else (no table, a WAV
+// header in there, or samples that need resampling or guessing
+// first) gets unpacked as a whole.
+//
+static unsigned char *decode_zstd(const unsigned char *buf, size_t len, const struct audio_format *raw,
+	size_t *out_len, size_t *skip_bytes)
+{
+	struct zstd_frame *frames;
+	unsigned char *res;
+	int nr, first, last;
+
+	*skip_bytes = 0;
+	nr = zstd_seek_table(buf, len, &frames);
+	if (nr > 0 && (start || end) && !guess_format && !rate &&
+	    (!raw->rate || raw->rate == SAMPLES_PER_SEC)) {
+		u64 fb = frame_bytes(raw);
+
+		res = run_filter(unzstd, buf, frames[0].size, out_len);
+		if (res && audio_sniff(res, *out_len) == container_raw &&
+		    !zstd_frames_for(frames, nr, start * fb, end ? end * fb : ~0ull, &first, &last)) {
+			free(res);
+			*skip_bytes = frames[first].start;
+			res = run_filter(unzstd, buf + frames[first].offset,
+				frames[last].offset + frames[last].size - frames[first].offset, out_len);
+			free(frames);
+			return res;
+		}
+		free(res);
+	}
+	free(frames);
+	return run_filter(unzstd, buf, len, out_len);
+}
+
+static void release_file(struct audio_file *file, unsigned char *buf)
+{
+	if (file->map)
+		audio_file_unmap(file);
+	else
+		free(buf);
+}
+
+//
+// Returns the samples as mono floats, and fills in the format
+// they came in (so that we know the rate of WAV files).
+//
+// If the file is mapped and doesn't need resampling, only the
+// part between '--start' and '--end' gets decoded (and touched
+// at all), with the number of frames skipped in *skipped.
+//
+static float *read_samples(int fd, const struct audio_format *raw, int *nrp, struct audio_format *fmt,
+	s64 *skipped)
+{
+	size_t len, offset = 0, size, skip_bytes = 0;
+	struct audio_file file;
+	unsigned char *buf;
+	float *samples;
+
+	*skipped = 0;
+	*nrp = 0;
+
+	// No point in copying a regular file, just map it
+	if (!audio_file_map(&file, fd)) {
+		buf = file.map;
+		len = file.len;
+	} else {
+		buf = read_file(fd, &len);
+	}
+	if (!buf)
+		return NULL;
+
+	// A zstd archive is whatever was put in it
+	if (audio_sniff(buf, len) == container_zstd) {
+		unsigned char *data = decode_zstd(buf, len, raw, &len, &skip_bytes);
+
+		release_file(&file, buf);
+		buf = data;
+		if (!buf)
+			return NULL;
+	}
+
+	if (is_compressed(buf, len)) {
+		unsigned char *raw = run_filter(decoder, buf, len, &len);
+
+		release_file(&file, buf);
+		buf = raw;
+		if (!buf)
+			return NULL;
+		*fmt = (struct audio_format) AUDIO_FORMAT_RAW;
+		fmt->rate = SAMPLES_PER_SEC;
+		decoded_externally = 1;
+		samples = decode_samples(buf, len, fmt, nrp);
+		free(buf);
+		return samples;
+	}
+
+	size = len;
+	if (audio_parse(buf, len, fmt, &offset, &size) < 0) {
+		if (audio_sniff(buf, len) == container_wav) {
+			fprintf(stderr, "Unsupported WAV format\n");
+			exit(1);
+		}
+		*fmt = *raw;
+		if (guess_format) {
+			char name[6];
+
+			if (guess_raw_format(buf, len, fmt) < 0)
+				fprintf(stderr, "Can't guess the sample format, using %s\n", raw_format_name(fmt, name));
+			else
+				fprintf(stderr, "Sample format looks like %s\n", raw_format_name(fmt, name));
+		}
+	}
+
+	if (audio_sniff(buf, len) == container_wav)
+		have_bext = !bext_parse(buf, len, &bext);
+	input_info = *fmt;
+
+	if (channel >= fmt->channels) {
+		fprintf(stderr, "No channel %d (there are %d)\n", channel, fmt->channels);
+		exit(1);
+	}
+
+	if (file.map && !rate && (!fmt->rate || fmt->rate == SAMPLES_PER_SEC)) {
+		size_t frames = size / frame_bytes(fmt);
+		size_t first = (size_t) start < frames ? (size_t) start : frames;
+		size_t last = end && (size_t) end < frames ? (size_t) end : frames;
+
+		audio_file_set_format(&file, fmt, offset, size);
+		audio_file_advise(&file, first, last - first, MADV_SEQUENTIAL);
+		offset += first * frame_bytes(fmt);
+		size = (last - first) * frame_bytes(fmt);
+		*skipped = first;
+	} else if (skip_bytes) {
+		size_t fb = frame_bytes(fmt), pad = (fb - skip_bytes % fb) % fb;
+
+		// The frames don't have to start on a sample boundary
+		offset = pad < size ? pad : size;
+		size -= offset;
+		*skipped = (skip_bytes + offset) / fb;
+	}
+
+	samples = decode_samples(buf + offset, size, fmt, nrp);
+	release_file(&file, buf);
+	if (samples && fmt->channels > 1) {
+		if (channel >= 0)
+			extract_channel(samples, *nrp, fmt->channels, channel, samples);
+		else
+			downmix(samples, *nrp, fmt->channels);
+	}
+	return samples;
+}
+
+//
+// What a raw capture is: a sidecar file (see sidecar.h) says,
+// but anything given on the command line still wins.
+//
+static struct audio_format capture_format(const char *name)
+{
+	struct audio_format raw = AUDIO_FORMAT_RAW;
+
+	if (!name || sidecar_read(name, &raw, NULL, 0) < 0)
+		raw = raw_format;
+	if (format_given) {
+		raw.type = raw_format.type;
+		raw.big_endian = raw_format.big_endian;
+	}
+	if (channels_given)
+		raw.channels = raw_format.channels;
+	return raw;
+}
+
+// Read a capture and do the optional preprocessing
+static float *load_capture(int fd, const char *name, int *nr)
+{
+	struct audio_format fmt, raw = capture_format(name);
+	float *samples, in_rate;
+	s64 skipped;
+
+	samples = read_samples(fd, &raw, nr, &fmt, &skipped);
+	in_rate = rate ? rate : fmt.rate;
+
+	if (!samples) {
+		fprintf(stderr, *nr < 0 ? "Too many samples, use --start/--end\n" : "Out of memory\n");
+		exit(1);
+	}
+
+	if (in_rate && in_rate != SAMPLES_PER_SEC) {
+		if (*nr * (double) SAMPLES_PER_SEC / in_rate >= INT_MAX) {
+			fprintf(stderr, "Too many samples, use --start/--end\n");
+			exit(1);
+		}
+		float *res = resample(samples, *nr, in_rate, SAMPLES_PER_SEC, 2, nr);
+		if (!res) {
+			fprintf(stderr, "Out of memory\n");
+			exit(1);
+		}
+		free(samples);
+		samples = res;
+	}
+
+	if (end && end - skipped < *nr)
+		*nr = (int) (end - skipped);
+	if (start > skipped) {
+		int skip = start - skipped < *nr ? (int) (start - skipped) : *nr;
+
+		*nr -= skip;
+		memmove(samples, samples + skip, *nr * sizeof(float));
+	}
+
+	if (dc)
+		remove_dc(samples, *nr, dc_cutoff);
+	return samples;
+}
+
+// Position in the whole file, for when '--start' skipped some of it
+static s64 file_pos(int pos)
+{
+	return start + pos;
+}
+
+static double timestamp(int pos)
+{
+	return samples_to_secs(file_pos(pos));
+}
+
+static void stats_analyze(float *samples, int nr, float arg[])
+{
+	struct stats st;
+
+	measure_stats(samples, nr, &st);
+	printf("Samples: %d (%.3f s)\n", st.nr, st.nr / SAMPLES_PER_SEC);
+	printf("Min:     %.6f\n", st.min);
+	printf("Max:     %.6f\n", st.max);
+	printf("Peak:    %.6f (%.2f dBFS)\n", st.peak, linear_to_db(st.peak, -200));
+	printf("RMS:     %.6f (%.2f dBFS)\n", st.rms, linear_to_db(st.rms, -200));
+	printf("Crest:   %.2f (%.2f dB)\n", st.crest, linear_to_db(st.crest, -200));
+	printf("DC:      %.6f\n", st.dc);
+	printf("Clipped: %d\n", st.clipped);
+}
+
+//
+// Integrated loudness, and the gain it would take to get to
+// the target loudness (default -23 LUFS, the EBU R128 level;
+// streaming services tend to want -14).
+//
+static void loudness_analyze(float *samples, int nr, float arg[])
+{
+	float target = arg[0] < 0 ? arg[0] : -23;
+	float level = integrated_loudness(samples, nr);
+	float gain = target - level;
+
+	if (level <= LUFS_SILENCE) {
+		printf("Loudness: silent\n");
+		return;
+	}
+	printf("Loudness: %.1f LUFS\n", level);
+	printf("Gain:     %+.1f dB to %.1f LUFS (peak would be %.2f dBFS)\n",
+		gain, target, linear_to_db(peak(samples, nr), -200) + gain);
+}
+
+//
+// Optional arguments: number of bins (default 20), and
+// non-zero for a dBFS scale instead of a linear one
+//
+static void histogram_analyze(float *samples, int nr, float arg[])
+{
+	int bins = arg[0] >= 1 ? (int) arg[0] : 20;
+	float *edges = malloc((bins + 1) * sizeof(float));
+	int *counts = malloc(bins * sizeof(int));
+	int max = 1;
+
+	if (!edges || !counts)
+		goto out;
+
+	measure_histogram(samples, nr, bins, arg[1] != 0, edges, counts);
+	for (int i = 0; i < bins; i++)
+		if (counts[i] > max)
+			max = counts[i];
+
+	for (int i = 0; i < bins; i++) {
+		int len = (int) (50.0 * counts[i] / max);
+		printf("%9.3f .. %9.3f %10d ", edges[i], edges[i+1], counts[i]);
+		while (len--)
+			putchar('#');
+		putchar('\n');
+	}
+out:
+	free(edges);
+	free(counts);
+}
+
+static void print_ranges(struct range *r, int count)
+{
+	for (int i = 0; i < count; i++) {
+		char a[TIMEPOS_LEN], b[TIMEPOS_LEN];
+
+		printf("%s - %s (samples %lld - %lld, %.3f s)\n",
+			format_timepos(file_pos(r[i].start), a),
+			format_timepos(file_pos(r[i].end), b),
+			file_pos(r[i].start), file_pos(r[i].end),
+			samples_to_secs(r[i].end - r[i].start));
+	}
+}
+
+//
+// RMS and peak level envelope, one frame per line.
+//
+// Optional arguments: window size in ms (default 50ms), and the
+// hop size in ms (default a quarter of the window). Overlapping
+// frames use a Hann window, otherwise it's rectangular.
+//
+static void envelope_analyze(float *samples, int nr, float arg[])
+{
+	int window = (int) ((arg[0] > 0 ? arg[0] : 50) * SAMPLES_PER_SEC / 1000);
+	int hop = arg[1] > 0 ? (int) (arg[1] * SAMPLES_PER_SEC / 1000) : window / 4;
+	int frames = windowed_frames(nr, window, hop);
+	float *win = NULL, *level, *peaks;
+
+	if (window < 1 || hop < 1) {
+		fprintf(stderr, "Bad window size\n");
+		exit(1);
+	}
+	level = malloc((frames + 1) * sizeof(float));
+	peaks = malloc((frames + 1) * sizeof(float));
+	if (hop < window)
+		win = malloc(window * sizeof(float));
+	if (!level || !peaks || (hop < window && !win)) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+	if (win)
+		window_hann(win, window);
+
+	windowed_rms(samples, nr, window, hop, win, level, frames);
+	windowed_peak(samples, nr, window, hop, win, peaks, frames);
+
+	printf("# time rms peak (dBFS)\n");
+	for (int f = 0; f < frames; f++)
+		printf("%.3f %.2f %.2f\n", timestamp(f * hop + window / 2),
+			linear_to_db(level[f], -200), linear_to_db(peaks[f], -200));
+
+	free(win);
+	free(level);
+	free(peaks);
+}
+
+//
+// Instantaneous amplitude and frequency from the analytic signal
+// (see fft.h), averaged over each hop. Unlike 'envelope' there's
+// no window to smear things, so it follows tremolo and vibrato
+// on a single tone. With more than one tone it's the beating
+// between them that shows up.
+//
+// Optional argument: hop size in ms (default 10ms)
+//
+static void hilbert_analyze(float *samples, int nr, float arg[])
+{
+	int hop = (int) ((arg[0] > 0 ? arg[0] : 10) * SAMPLES_PER_SEC / 1000);
+	float *amp = malloc((nr + 1) * sizeof(float));
+	float *phase = malloc((nr + 1) * sizeof(float));
+
+	if (hop < 1) {
+		fprintf(stderr, "Bad hop size\n");
+		exit(1);
+	}
+	if (!amp || !phase || analytic_signal(samples, nr, amp, phase) < 0) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+
+	printf("# time amplitude (dBFS) frequency (Hz)\n");
+	for (int i = 0; i < nr; i += hop) {
+		int len = nr - i < hop ? nr - i : hop;
+		double sum = 0, turn = 0;
+
+		for (int j = i; j < i + len; j++) {
+			sum += amp[j];
+			if (j > 0) {
+				double d = phase[j] - phase[j-1];
+				turn += d - 2*M_PI * round(d / (2*M_PI));
+			}
+		}
+		printf("%.3f %.2f %.1f\n", timestamp(i + len / 2),
+			linear_to_db(sum / len, -200), turn * SAMPLES_PER_SEC / (2*M_PI * len));
+	}
+
+	free(amp);
+	free(phase);
+}
+
+//
+// What a waveform display would draw: the min, max, mean and RMS
+// in each of 'columns' (default 80) equal parts of the capture,
+// looked up in a peak pyramid (see peaks.h). With '--cache' the
+// pyramid gets saved next to the capture, and reused if it's
+// still valid. If the capture has only grown since (it's still
+// being recorded), just the new part gets added to it.
+//
+// With '--output=<file>' ending in ".png" or ".svg", it draws an
+// image of that instead (see render.h), '--size=<w>x<h>' pixels
+// and a linear scale, or a dB scale with '--db=<range>'. The
+// colors are '--colors=<background>,<peak>,<rms>' in hex, and
+// with '--hot=<dBFS>' the columns that peak above that are red.
+//
+// Or with '--draw=ascii|blocks|braille' it draws it as text right
+// there, in 'columns' characters and the second argument's rows
+// (default 16) of them.
+//
+// With '--every=<time>' it doesn't read the whole capture first,
+// but prints a line for every bucket of that length as soon as it
+// has the samples for it. That's for raw samples from a pipe or a
+// socket that keeps going, like 'arecord' or a tcp:// URL.
+//
+static int peak_cache;
+static s64 every;
+static const char *capture_name;
+static int draw_style = -1;
+static const char *const draw_styles[] = { "ascii", "blocks", "braille" };
+
+static int has_suffix(const char *name, size_t len, const char *suffix)
+{
+	size_t n = strlen(suffix);
+	return len > n && !strcasecmp(name + len - n, suffix);
+}
+
+static struct render_column *overview_columns(const struct peak *col, int cols, int nr)
+{
+	struct render_column *c = malloc(cols * sizeof(*c));
+
+	if (!c) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+	for (int i = 0; i < cols; i++) {
+		int from = (int) ((s64) nr * i / cols), to = (int) ((s64) nr * (i + 1) / cols);
+
+		c[i] = (struct render_column) { col[i].min, col[i].max, peak_rms(col + i, to - from) };
+	}
+	return c;
+}
+
+static void render_overview(const struct peak *col, int nr)
+{
+	size_t len = strlen(export_name);
+	int svg = has_suffix(export_name, len, ".svg");
+	struct render_column *c;
+	FILE *f;
+	int ret;
+
+	if (!svg && !has_suffix(export_name, len, ".png")) {
+		fprintf(stderr, "'overview' can only write .png or .svg files\n");
+		exit(1);
+	}
+	c = overview_columns(col, render.width, nr);
+
+	f = fopen(export_name, "w");
+	if (!f) {
+		perror(export_name);
+		exit(1);
+	}
+	ret = svg ? render_svg(f, c, &render) : render_png(f, c, &render);
+	if (fclose(f) || ret < 0) {
+		perror(export_name);
+		exit(1);
+	}
+	printf("Wrote a %dx%d overview of %d samples to %s\n", render.width, render.height, nr, export_name);
+	free(c);
+}
+
+static void print_peak(double secs, const struct peak *pk, size_t nr)
+{
+	printf("%.3f %.6f %.6f %.6f %.6f %.2f %.2f\n", secs, pk->min, pk->max,
+		peak_mean(pk, nr), peak_rms(pk, nr), peak_dbfs(pk, -200), peak_rms_dbfs(pk, nr, -200));
+}
+
+static void overview_analyze(float *samples, int nr, float arg[])
+{
+	int cells = arg[0] > 0 ? (int) arg[0] : 80, rows = arg[1] > 0 ? (int) arg[1] : 16;
+	int cols = export_name ? render.width : draw_style >= 0 ? cells * render_dots_x(draw_style) : cells;
+	struct peak *col = malloc(cols * sizeof(*col));
+	struct peak_samples s = peak_floats(samples);
+	int cache = peak_cache && capture_name;
+	struct peak_pyramid p;
+	int cached = -1;
+
+	if (!col) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+	if (cache)
+		cached = peak_cache_load(&p, capture_name, &s, nr);
+	if (cached < 0 && peak_pyramid_build(&p, &s, nr) < 0) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+	if (cache && cached && peak_cache_save(&p, capture_name, peak_fingerprint(&s, nr)) < 0)
+		fprintf(stderr, "Can't write the peak cache for %s\n", capture_name);
+	peak_columns(&p, &s, 0, nr, cols, col);
+	peak_pyramid_free(&p);
+
+	if (export_name) {
+		render_overview(col, nr);
+		free(col);
+		return;
+	}
+	if (draw_style >= 0) {
+		struct render_column *c = overview_columns(col, cols, nr);
+
+		render_text(stdout, c, cells, rows, draw_style, &render);
+		free(c);
+		free(col);
+		return;
+	}
+
+	printf("# time min max mean rms peak(dBFS) rms(dBFS)\n");
+	for (int c = 0; c < cols; c++) {
+		int from = (int) ((s64) nr * c / cols), to = (int) ((s64) nr * (c + 1) / cols);
+
+		if (from != to)
+			print_peak(timestamp(from), col + c, to - from);
+	}
+	free(col);
+}
+
+#define STREAM_FRAMES 4096
+
+static void overview_stream(int fd, const char *name)
+{
+	struct audio_format fmt = capture_format(name);
+	int max = (int) (STREAM_FRAMES / every) + 1, nr;
+	float *buf = malloc(STREAM_FRAMES * fmt.channels * sizeof(float));
+	struct peak *out = malloc(max * sizeof(*out)), last;
+	static struct audio_stream in;
+	struct peak_bucketer b;
+	s64 pos = 0;
+	size_t left;
+
+	if (!buf || !out) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+	if (fmt.rate && fmt.rate != SAMPLES_PER_SEC) {
+		fprintf(stderr, "Can't resample with '--every'\n");
+		exit(1);
+	}
+	if (channel >= fmt.channels) {
+		fprintf(stderr, "No channel %d (there are %d)\n", channel, fmt.channels);
+		exit(1);
+	}
+
+	audio_stream_init(&in, fd, &fmt);
+	peak_bucketer_init(&b, every);
+	printf("# time min max mean rms peak(dBFS) rms(dBFS)\n");
+	while ((nr = audio_stream_read(&in, buf, STREAM_FRAMES)) > 0) {
+		int done;
+
+		if (fmt.channels > 1) {
+			if (channel >= 0)
+				extract_channel(buf, nr, fmt.channels, channel, buf);
+			else
+				downmix(buf, nr, fmt.channels);
+		}
+		done = peak_bucketer_push(&b, buf, nr, out, max);
+		for (int i = 0; i < done; i++, pos += every)
+			print_peak(samples_to_secs(pos), out + i, every);
+		// Whoever reads this wants to see it as it happens
+		fflush(stdout);
+	}
+	if (nr < 0) {
+		perror(name ? name : "read");
+		exit(1);
+	}
+	left = peak_bucketer_flush(&b, &last);
+	if (left)
+		print_peak(samples_to_secs(pos), &last, left);
+	free(buf);
+	free(out);
+}
+
+//
+// Arguments: threshold in dBFS, and optionally the minimum
+// length of silence in ms (default 100ms)
+//
+static void silence_analyze(float *samples, int nr, float arg[])
+{
+	int min_len = (int) ((arg[1] > 0 ? arg[1] : 100) * SAMPLES_PER_SEC / 1000);
+	int max = nr / (min_len + 1) + 1;
+	struct range *r = malloc(max * sizeof(*r));
+
+	if (!r)
+		return;
+	print_ranges(r, detect_silence(samples, nr, arg[0], min_len, r, max));
+	free(r);
+}
+
+//
+// Optional arguments: level in dBFS (default 0, ie full
+// scale), and the minimum run length in samples (default 3)
+//
+static void clipping_analyze(float *samples, int nr, float arg[])
+{
+	int min_len = arg[1] > 0 ? (int) arg[1] : 3;
+	int max = nr / (min_len + 1) + 1;
+	struct range *r = malloc(max * sizeof(*r));
+
+	if (!r)
+		return;
+	print_ranges(r, detect_clipping(samples, nr, arg[0], min_len, r, max));
+	free(r);
+}
+
+static void zcr_analyze(float *samples, int nr, float arg[])
+{
+	printf("Zero crossings: %d (%.1f per second)\n",
+		count_zero_crossings(samples, nr),
+		zero_crossing_rate(samples, nr));
+}
+
+static void thd_analyze(float *samples, int nr, float arg[])
+{
+	struct thd thd;
+	double f = arg[0];
+
+	measure_thd(samples, nr, f, &thd);
+	if (!thd.fundamental) {
+		fprintf(stderr, "No %g Hz signal found\n", f);
+		exit(1);
+	}
+
+	double thd_ratio = thd.harmonics / thd.fundamental;
+	double thdn_ratio = thd.residual / thd.fundamental;
+
+	printf("Fundamental: %g Hz, %.2f dBFS\n", f,
+		linear_to_db(thd.fundamental * M_SQRT2, -200));
+	printf("THD:   %.4f%% (%.1f dB, %d harmonics)\n",
+		100 * thd_ratio, linear_to_db(thd_ratio, -200), thd.nr_harmonics);
+	printf("THD+N: %.4f%% (%.1f dB)\n",
+		100 * thdn_ratio, linear_to_db(thdn_ratio, -200));
+}
+
+static void snr_analyze(float *samples, int nr, float arg[])
+{
+	struct thd thd;
+	double f = arg[0];
+
+	measure_thd(samples, nr, f, &thd);
+	if (!thd.fundamental || !thd.residual) {
+		fprintf(stderr, "No %g Hz signal found\n", f);
+		exit(1);
+	}
+
+	double snr = linear_to_db(thd_snr(&thd), -200);
+	double sinad = linear_to_db(thd_sinad(&thd), -200);
+
+	printf("Fundamental: %g Hz, %.2f dBFS\n", f,
+		linear_to_db(thd.fundamental * M_SQRT2, -200));
+	printf("SNR:   %.1f dB\n", snr);
+	printf("SINAD: %.1f dB (ENOB %.1f bits)\n", sinad, (sinad - 1.76) / 6.02);
+}
+
+//
+// Optional argument: hop size in ms (default 20ms)
+//
+static void pitch_analyze(float *samples, int nr, float arg[])
+{
+	int hop = (int) ((arg[0] > 0 ? arg[0] : 20) * SAMPLES_PER_SEC / 1000);
+
+	if (hop < 1)
+		hop = 1;
+	for (int i = 0; i + PITCH_FRAME <= nr; i += hop) {
+		struct pitch p;
+		int octave;
+		float cents;
+
+		pitch_detect(samples + i, &p);
+		printf("%9.3f s: ", timestamp(i));
+		if (!p.freq) {
+			printf("      -   (%.2f)\n", p.confidence);
+			continue;
+		}
+		const char *note = pitch_note(p.freq, &octave, &cents);
+		printf("%7.2f Hz (%.2f) %s%d %+d cents\n",
+			p.freq, p.confidence, note, octave, (int) lrintf(cents));
+	}
+}
+
+static void onsets_analyze(float *samples, int nr, float arg[])
+{
+	int max = nr / (ONSET_HOP * ONSET_MIN_GAP) + 1;
+	int *onsets = malloc(max * sizeof(int));
+	int count;
+
+	if (!onsets)
+		return;
+	count = detect_onsets(samples, nr, onsets, max);
+	for (int i = 0; i < count; i++)
+		printf("%9.3f s (sample %lld)\n", timestamp(onsets[i]), file_pos(onsets[i]));
+	free(onsets);
+}
+
+static void tempo_analyze(float *samples, int nr, float arg[])
+{
+	int max = (int) (nr / SAMPLES_PER_SEC * TEMPO_MAX_BPM / 60) + 1;
+	int *beats = malloc(max * sizeof(int));
+	int count;
+	float bpm;
+
+	if (!beats)
+		return;
+	count = estimate_tempo(samples, nr, &bpm, beats, max);
+	if (!bpm) {
+		printf("No tempo found\n");
+		free(beats);
+		return;
+	}
+	printf("Tempo: %.1f BPM\n", bpm);
+	for (int i = 0; i < count; i++)
+		printf("%9.3f s (sample %lld)\n", timestamp(beats[i]), file_pos(beats[i]));
+	free(beats);
+}
+
+//
+// Optional argument: maximum lag to look for in ms (default 1s)
+//
+static void align_analyze(float *samples, int nr, float arg[])
+{
+	int max_lag = (int) ((arg[0] > 0 ? arg[0] : 1000) * SAMPLES_PER_SEC / 1000);
+	int lag;
+	float corr;
+
+	if (cross_correlate(samples, nr, other, other_nr, max_lag, &lag, &corr) < 0) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+	printf("Lag: %d samples (%.3f ms), correlation %.3f%s\n",
+		lag, lag * 1000 / SAMPLES_PER_SEC, corr,
+		corr < 0 ? " (inverted)" : "");
+}
+
+//
+// Report the biggest cepstral peaks above 1ms quefrency.
+//
+// Optional argument: number of peaks to show (default 5, at
+// most CEPSTRUM_MAX_PEAKS)
+//
+#define CEPSTRUM_MAX_PEAKS 64
+
+static void cepstrum_analyze(float *samples, int nr, float arg[])
+{
+	int n = fft_size(nr), min = (int) (SAMPLES_PER_SEC / 1000);
+	int peaks = arg[0] >= 1 ? (int) fminf(arg[0], CEPSTRUM_MAX_PEAKS) : 5;
+	float *c;
+
+	if (n > nr)
+		n >>= 1;
+	if (n > ALIGN_MAX_SAMPLES)
+		n = ALIGN_MAX_SAMPLES;
+	if (n <= 2 * min) {
+		fprintf(stderr, "Not enough samples\n");
+		exit(1);
+	}
+
+	c = malloc(n * sizeof(float));
+	if (!c || real_cepstrum(samples, n, c) < 0) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+
+	printf("Cepstrum of the first %d samples:\n", n);
+	int found[peaks], nr_found = 0;
+	while (nr_found < peaks) {
+		int best = 0;
+
+		for (int i = min + 1; i < n/2 - 1; i++) {
+			int near = 0;
+
+			if (c[i] < c[i-1] || c[i] < c[i+1])
+				continue;
+
+			// Skip the wiggles right next to a peak we already reported
+			for (int j = 0; j < nr_found; j++)
+				near |= abs(i - found[j]) < min;
+			if (near)
+				continue;
+
+			if (!best || c[i] > c[best])
+				best = i;
+		}
+		if (!best)
+			break;
+		printf("%9.3f ms (%8.2f Hz): %.4f\n",
+			best * 1000 / SAMPLES_PER_SEC, SAMPLES_PER_SEC / best, c[best]);
+		found[nr_found++] = best;
+	}
+	free(c);
+}
+
+//
+// One line per frame, in columns that are easy to load into
+// numpy (or gnuplot) as aligned arrays
+//
+static void spectral_analyze(float *samples, int nr, float arg[])
+{
+	static float re[SPECTRAL_FFT], im[SPECTRAL_FFT], win[SPECTRAL_FFT];
+	static float prev[SPECTRAL_FFT/2];
+
+	window_hann(win, SPECTRAL_FFT);
+	printf("# time centroid bandwidth rolloff flatness flux\n");
+	for (int i = 0; i + SPECTRAL_FFT <= nr; i += SPECTRAL_HOP) {
+		struct spectral_features f;
+
+		magnitude_spectrum(samples + i, win, re, im, SPECTRAL_FFT);
+		spectral_features(re, i ? prev : NULL, SPECTRAL_FFT/2, &f);
+		memcpy(prev, re, sizeof(prev));
+
+		printf("%.3f %.1f %.1f %.1f %.4f %.4f\n",
+			timestamp(i + SPECTRAL_FFT/2),
+			f.centroid, f.bandwidth, f.rolloff, f.flatness, f.flux);
+	}
+}
+
+//
+// Optional arguments: number of mel bands (default 40) and
+// number of coefficients (default 13)
+//
+static void mfcc_analyze(float *samples, int nr, float arg[])
+{
+	static float re[SPECTRAL_FFT], im[SPECTRAL_FFT], win[SPECTRAL_FFT];
+	int bands = arg[0] >= 1 ? (int) arg[0] : 40;
+	int coeffs = arg[1] >= 1 ? (int) arg[1] : 13;
+	struct mel_filterbank mb;
+
+	if (coeffs > bands)
+		coeffs = bands;
+	if (mel_init(&mb, bands, SPECTRAL_FFT/2, 20, SAMPLES_PER_SEC/2) < 0) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+
+	window_hann(win, SPECTRAL_FFT);
+	for (int i = 0; i + SPECTRAL_FFT <= nr; i += SPECTRAL_HOP) {
+		float out[coeffs];
+
+		magnitude_spectrum(samples + i, win, re, im, SPECTRAL_FFT);
+		mfcc(&mb, re, coeffs, out);
+
+		printf("%.3f", timestamp(i + SPECTRAL_FFT/2));
+		for (int c = 0; c < coeffs; c++)
+			printf(" %.3f", out[c]);
+		printf("\n");
+	}
+	mel_free(&mb);
+}
+
+//
+// Phase correlation of the two captures as left/right channels.
+// The lowest reading of a desk-style correlation meter (with the
+// window size as its time constant) shows short dips that the
+// windows average out.
+//
+// Optional argument: window size in ms (default 100ms)
+//
+static void correlation_analyze(float *samples, int nr, float arg[])
+{
+	float ms = arg[0] > 0 ? arg[0] : 100;
+	int window = (int) (ms * SAMPLES_PER_SEC / 1000);
+	struct correlation_meter m;
+	float lowest = 1;
+	int lowest_pos = 0;
+
+	if (other_nr < nr)
+		nr = other_nr;
+	if (window < 1)
+		window = 1;
+
+	for (int i = 0; i < nr; i += window) {
+		int len = nr - i < window ? nr - i : window;
+		printf("%9.3f s: %+.3f\n", timestamp(i),
+			phase_correlation(samples + i, other + i, len));
+	}
+	printf("Overall: %+.3f\n", phase_correlation(samples, other, nr));
+
+	// Give the meter one time constant to settle
+	correlation_meter_init(&m, ms);
+	for (int i = 0; i < nr; i++) {
+		float c = correlation_meter_step(&m, samples[i], other[i]);
+
+		if (i >= window && c < lowest) {
+			lowest = c;
+			lowest_pos = i;
+		}
+	}
+	if (nr > window)
+		printf("Lowest:  %+.3f at %.3f s\n", lowest, timestamp(lowest_pos));
+}
+
+//
+// Impulse response from a recorded log sweep. The arguments are
+// the sweep length in seconds, and optionally the start and end
+// frequencies (default 20Hz .. 20kHz, like the 'sweep' effect).
+//
+// Reports the latency and level of the response, and how it
+// decays over the first 200ms.
+//
+static void ir_analyze(float *samples, int nr, float arg[])
+{
+	float f1 = arg[1] > 0 ? arg[1] : SWEEP_F1;
+	float f2 = arg[2] > 0 ? arg[2] : SWEEP_F2;
+	int window = (int) (SAMPLES_PER_SEC / 100);
+	float *ir, peak = 0;
+	int pos = 0;
+
+	if (arg[0] <= 0 || f2 <= f1) {
+		fprintf(stderr, "Bad sweep parameters\n");
+		exit(1);
+	}
+	ir = sweep_deconvolve(samples, nr, f1, f2, arg[0]);
+	if (!ir) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+
+	for (int i = 0; i < nr; i++) {
+		if (fabsf(ir[i]) > peak) {
+			peak = fabsf(ir[i]);
+			pos = i;
+		}
+	}
+	printf("Latency: %d samples (%.3f ms)\n", pos, pos * 1000 / SAMPLES_PER_SEC);
+	printf("Peak:    %.2f dB\n", linear_to_db(peak, -200));
+
+	for (int i = 0; i < 20 && pos + (i+1) * window <= nr; i++) {
+		float level = (float) rms(ir + pos + i * window, window);
+		printf("%6d ms: %7.2f dB\n", i * 10, linear_to_db(level / peak, -200));
+	}
+	free(ir);
+}
+
+//
+// Fingerprint of the (decoded) samples, to see whether two
+// renders are bit-identical without keeping both around. With
+// a non-zero argument, also list a hash per block, so that
+// diffing two of those lists shows where they diverge.
+//
+static void checksum_analyze(float *samples, int nr, float arg[])
+{
+	printf("Checksum: %016llx (%d samples)\n", hash_samples(samples, nr), nr);
+	if (!arg[0])
+		return;
+
+	int blocks = hash_nr_blocks(nr);
+	u64 *hash = malloc(blocks * sizeof(u64));
+	char ts[TIMEPOS_LEN];
+
+	if (!hash) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+	hash_blocks(samples, nr, hash);
+	for (int i = 0; i < blocks; i++)
+		printf("%s %016llx\n", format_timepos(file_pos(i * HASH_BLOCK), ts), hash[i]);
+	free(hash);
+}
+
+// Are two captures bit-identical, and if not, where do they differ?
+static void diff_analyze(float *samples, int nr, float arg[])
+{
+	int pos = first_difference(samples, nr, other, other_nr);
+	char ts[TIMEPOS_LEN];
+
+	if (pos < 0) {
+		printf("Identical (%d samples)\n", nr);
+		return;
+	}
+	printf("First difference at sample %lld (%s)", file_pos(pos), format_timepos(file_pos(pos), ts));
+	if (pos < nr && pos < other_nr)
+		printf(": %.9f vs %.9f\n", samples[pos], other[pos]);
+	else
+		printf(": lengths differ (%d vs %d samples)\n", nr, other_nr);
+}
+
+//
+// What's in the file: sample format, length, and any Broadcast
+// WAV metadata. The timecode is where the file starts, from the
+// bext time reference.
+//
+static void probe_analyze(float *samples, int nr, float arg[])
+{
+	char name[6], ts[TIMEPOS_LEN];
+
+	if (decoded_externally)
+		printf("Format:   decoded by ffmpeg\n");
+	else
+		printf("Format:   %s, %d channel%s, %g Hz\n", raw_format_name(&input_info, name),
+			input_info.channels, input_info.channels > 1 ? "s" : "",
+			input_info.rate ? input_info.rate : SAMPLES_PER_SEC);
+	printf("Length:   %s (%d samples at 48kHz)\n", format_timepos(nr, ts), nr);
+	if (!have_bext)
+		return;
+
+	double rate = input_info.rate ? input_info.rate : SAMPLES_PER_SEC;
+	u64 secs = (u64) (bext.time_reference / rate);
+	printf("Description: %s\n", bext.description);
+	printf("Originator:  %s\n", bext.originator);
+	printf("Origination: %s %s\n", bext.date, bext.time);
+	printf("Timecode:    %02llu:%02llu:%02llu (+%llu samples)\n",
+		secs / 3600 % 24, secs / 60 % 60, secs % 60,
+		bext.time_reference - (u64) (secs * rate));
+}
+
+//
+// A seekable zstd archive: the samples (with a WAV header if it's
+// a ".wav.zst") compressed in separate frames by the zstd binary,
+// and the seek table after them.
+//
+static int export_zstd(const char *name, const float *x, size_t frames,
+	const struct audio_format *fmt, int wav)
+{
+	size_t fb = frame_bytes(fmt), hdr = wav ? WAV_HEADER_SIZE : 0;
+	size_t len = hdr + frames * fb, chunk = ZSTD_SEEKABLE_FRAME / fb * fb;
+	unsigned char *data = malloc(len), *table;
+	struct zstd_frame *frame = malloc((len / chunk + 1) * sizeof(*frame));
+	int fd, nr = 0, ret = -1;
+
+	if (!data || !frame)
+		goto out;
+	if (wav)
+		wav_header(data, fmt, frames * fb);
+	encode_samples(data + hdr, x, (int) (frames * fmt->channels), fmt);
+
+	fd = open(name, O_WRONLY | O_CREAT | O_TRUNC, 0666);
+	if (fd < 0)
+		goto out;
+	for (size_t pos = 0; pos < len; pos += chunk, nr++) {
+		size_t size = len - pos < chunk ? len - pos : chunk, out_len;
+		unsigned char *out = run_filter(zstd, data + pos, size, &out_len);
+
+		if (!out || write(fd, out, out_len) != out_len) {
+			free(out);
+			close(fd);
+			goto out;
+		}
+		free(out);
+		frame[nr].size = out_len;
+		frame[nr].len = size;
+	}
+	table = zstd_seek_table_build(frame, nr, &len);
+	if (table && write(fd, table, len) == len)
+		ret = 0;
+	free(table);
+	if (close(fd) < 0)
+		ret = -1;
+out:
+	free(data);
+	free(frame);
+	return ret;
+}
+
+//
+// Not really an analysis: write out the samples, after all the
+// preprocessing (so '--start' and '--end' pick the part to save).
+// The output file is '--output=<file>', as raw samples in the
+// '--output-format' (default s32le), or as a WAV file of that if
+// the name ends in ".wav". Adding ".zst" to either makes it a
+// seekable zstd archive. '--normalize' is applied last, to just
+// the part that gets saved.
+//
+static void export_analyze(float *samples, int nr, float arg[])
+{
+	size_t len = export_name ? strlen(export_name) : 0;
+	int zst = export_name && has_suffix(export_name, len, ".zst");
+	int wav = export_name && has_suffix(export_name, zst ? len - 4 : len, ".wav");
+	char ts[TIMEPOS_LEN];
+	int ret;
+
+	if (!export_name) {
+		fprintf(stderr, "'export' needs '--output=<file>'\n");
+		exit(1);
+	}
+	if (normalize >= 0) {
+		float gain = normalize == 0 ? normalize_to_peak(samples, nr, normalize_target) :
+			     normalize == 1 ? normalize_to_rms(samples, nr, normalize_target) :
+			     normalize_to_lufs(samples, nr, normalize_target);
+		printf("Normalized to %s %g: %+.2f dB\n", normalize_modes[normalize], normalize_target, gain);
+	}
+
+	export_format.rate = SAMPLES_PER_SEC;
+	if (zst)
+		ret = export_zstd(export_name, samples, nr, &export_format, wav);
+	else
+		ret = audio_export(export_name, samples, nr, &export_format, wav);
+	if (ret < 0) {
+		perror(export_name);
+		exit(1);
+	}
+	printf("Wrote %d samples from %s (%.3f s) to %s\n", nr,
+		format_timepos(file_pos(0), ts), samples_to_secs(nr), export_name);
+}
+
+#define ANALYSIS(x, n) { #x, n, 1, x##_analyze }
+#define ANALYSIS2(x, n) { #x, n, 2, x##_analyze }
+struct analysis {
+	const char *name;
+	int nr_args, nr_files;
+	void (*analyze)(float *samples, int nr, float arg[]);
+} analyses[] = {
+	ANALYSIS(stats, 0),
+	ANALYSIS(histogram, 0),
+	ANALYSIS(loudness, 0),
+	ANALYSIS(envelope, 0),
+	ANALYSIS(hilbert, 0),
+	ANALYSIS(overview, 0),
+	ANALYSIS(zcr, 0),
+	ANALYSIS(silence, 1),
+	ANALYSIS(clipping, 0),
+	ANALYSIS(thd, 1),
+	ANALYSIS(snr, 1),
+	ANALYSIS(pitch, 0),
+	ANALYSIS(onsets, 0),
+	ANALYSIS(tempo, 0),
+	ANALYSIS(cepstrum, 0),
+	ANALYSIS(spectral, 0),
+	ANALYSIS(mfcc, 0),
+	ANALYSIS(ir, 1),
+	ANALYSIS2(align, 0),
+	ANALYSIS2(correlation, 0),
+	ANALYSIS(probe, 0),
+	ANALYSIS(checksum, 0),
+	ANALYSIS2(diff, 0),
+	ANALYSIS(export, 0),
+};
+
+int main(int argc, char **argv)
+{
+	struct analysis *an = NULL;
+	float args[4] = { 0, };
+	int nrargs = 0, inputs[2], nr_inputs = 0;
+	const char *names[2] = { NULL, NULL };
+
+	for (int i = 1; i < argc; i++) {
+		const char *arg = argv[i];
+		char *endptr;
+
+		if (!strncmp(arg, "--rate=", 7)) {
+			rate = strtof(arg+7, &endptr);
+			if (endptr != arg+7 && rate > 0)
+				continue;
+			fprintf(stderr, "Bad sample rate (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--start=", 8)) {
+			if (!parse_timepos(arg+8, &start) && start >= 0)
+				continue;
+			fprintf(stderr, "Bad start time (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--end=", 6)) {
+			if (!parse_timepos(arg+6, &end) && end > 0)
+				continue;
+			fprintf(stderr, "Bad end time (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--output=", 9)) {
+			export_name = arg+9;
+			continue;
+		}
+
+		if (!strncmp(arg, "--output-format=", 16)) {
+			if (!parse_raw_format(arg+16, &export_format))
+				continue;
+			fprintf(stderr, "Bad output sample format (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--size=", 7)) {
+			if (sscanf(arg+7, "%dx%d", &render.width, &render.height) == 2 &&
+			    render.width > 0 && render.height > 1)
+				continue;
+			fprintf(stderr, "Bad image size (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--db=", 5)) {
+			render.db_range = strtof(arg+5, &endptr);
+			if (endptr != arg+5 && render.db_range > 0)
+				continue;
+			fprintf(stderr, "Bad dB range (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--hot=", 6)) {
+			render.hot_db = strtof(arg+6, &endptr);
+			if (endptr != arg+6 && render.hot_db < 0)
+				continue;
+			fprintf(stderr, "Bad hot level (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--colors=", 9)) {
+			if (sscanf(arg+9, "%x,%x,%x", &render.background, &render.peak, &render.rms) == 3)
+				continue;
+			fprintf(stderr, "Bad colors (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--draw=", 7)) {
+			for (int i = 0; i < ARRAY_SIZE(draw_styles); i++)
+				if (!strcmp(arg+7, draw_styles[i]))
+					draw_style = i;
+			if (draw_style >= 0)
+				continue;
+			fprintf(stderr, "Bad drawing style (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--normalize=", 12)) {
+			const char *colon = strchr(arg+12, ':');
+			size_t len = colon ? (size_t) (colon - arg - 12) : 0;
+
+			for (int i = 0; i < ARRAY_SIZE(normalize_modes); i++)
+				if (len == strlen(normalize_modes[i]) && !strncmp(arg+12, normalize_modes[i], len))
+					normalize = i;
+			if (normalize >= 0) {
+				normalize_target = strtof(colon+1, &endptr);
+				if (endptr != colon+1 && !*endptr && normalize_target <= 0)
+					continue;
+			}
+			fprintf(stderr, "Bad normalization (%s), use peak, rms or lufs:<dB>\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--every=", 8)) {
+			if (!parse_timepos(arg+8, &every) && every > 0)
+				continue;
+			fprintf(stderr, "Bad bucket length (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strcmp(arg, "--cache")) {
+			peak_cache = 1;
+			continue;
+		}
+
+		if (!strcmp(arg, "--format=auto")) {
+			guess_format = 1;
+			continue;
+		}
+
+		if (!strncmp(arg, "--format=", 9)) {
+			guess_format = 0;
+			format_given = 1;
+			if (!parse_raw_format(arg+9, &raw_format))
+				continue;
+			fprintf(stderr, "Bad sample format (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--channels=", 11)) {
+			channels_given = 1;
+			raw_format.channels = strtol(arg+11, &endptr, 0);
+			if (endptr != arg+11 && raw_format.channels > 0)
+				continue;
+			fprintf(stderr, "Bad channel count (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--channel=", 10)) {
+			channel = strtol(arg+10, &endptr, 0);
+			if (endptr != arg+10 && channel >= 0)
+				continue;
+			fprintf(stderr, "Bad channel (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strcmp(arg, "--remove-dc")) {
+			dc = 1;
+			continue;
+		}
+
+		if (!strncmp(arg, "--remove-dc=", 12)) {
+			dc_cutoff = strtof(arg+12, &endptr);
+			if (endptr != arg+12 && dc_cutoff > 0) {
+				dc = 1;
+				continue;
+			}
+			fprintf(stderr, "Bad DC cutoff (%s)\n", arg);
+			exit(1);
+		}
+
+		float val = strtof(arg, &endptr);
+		if (endptr != arg) {
+			if (nrargs < ARRAY_SIZE(args)) {
+				args[nrargs++] = val;
+				continue;
+			}
+			fprintf(stderr, "Too many arguments\n");
+			exit(1);
+		}
+
+		if (!an) {
+			for (int i = 0; i < ARRAY_SIZE(analyses); i++) {
+				if (!strcmp(arg, analyses[i].name))
+					an = analyses+i;
+			}
+			if (an)
+				continue;
+		}
+
+		if (nr_inputs < ARRAY_SIZE(inputs)) {
+			if (!strcmp(arg, "-")) {
+				inputs[nr_inputs++] = 0;
+				continue;
+			}
+
+			if (is_url(arg)) {
+				int fd = net_open(arg);
+				if (fd < 0) {
+					fprintf(stderr, "Can't open %s\n", arg);
+					exit(1);
+				}
+				inputs[nr_inputs++] = fd;
+				continue;
+			}
+
+			int fd = open(arg, O_RDONLY);
+			if (fd < 0) {
+				perror(arg);
+				exit(1);
+			}
+			names[nr_inputs] = arg;
+			inputs[nr_inputs++] = fd;
+			continue;
+		}
+
+		fprintf(stderr, "Unrecognized option '%s'\n", arg);
+		exit(1);
+	}
+
+	if (!an) {
+		fprintf(stderr, "Pick one of");
+		for (int i = 0; i < ARRAY_SIZE(analyses); i++)
+			fprintf(stderr, " %s", analyses[i].name);
+		fprintf(stderr, "\n");
+		exit(1);
+	}
+
+	if (normalize >= 0 && an->analyze != export_analyze) {
+		fprintf(stderr, "Only 'export' can normalize\n");
+		exit(1);
+	}
+
+	if (nrargs < an->nr_args) {
+		fprintf(stderr, "'%s' needs %d argument(s)\n", an->name, an->nr_args);
+		exit(1);
+	}
+
+	// Single-file analyses default to reading stdin
+	if (nr_inputs != an->nr_files) {
+		if (an->nr_files == 1 && !nr_inputs) {
+			inputs[nr_inputs++] = 0;
+		} else {
+			fprintf(stderr, "'%s' needs %d file(s)\n", an->name, an->nr_files);
+			exit(1);
+		}
+	}
+
+	if (every) {
+		if (an->analyze != overview_analyze) {
+			fprintf(stderr, "Only 'overview' can go as the samples come in\n");
+			exit(1);
+		}
+		if (export_name || draw_style >= 0 || peak_cache || start || end || rate || dc || guess_format) {
+			fprintf(stderr, "'--every' only prints the levels of raw samples\n");
+			exit(1);
+		}
+		overview_stream(inputs[0], names[0]);
+		return 0;
+	}
+
+	int nr;
+	float *samples = load_capture(inputs[0], names[0], &nr);
+	if (nr_inputs > 1)
+		other = load_capture(inputs[1], names[1], &other_nr);
+
+	capture_name = names[0];
+
+	an->analyze(samples, nr, args);
+	return 0;
+}
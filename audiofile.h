@@ -0,0 +1,1045 @@
+//
+// Audio file formats
+//
+// Everything here is normally just headerless 32-bit mono
+// samples at 48kHz, because that's what ffmpeg will happily
+// turn anything into. But it's nice to be able to look at a WAV
+// file directly without a conversion step, so this knows how to
+// parse a WAV header and decode the common sample formats.
+//
+// Samples get decoded to floats in the usual -1 .. 1 range,
+// still interleaved if there's more than one channel.
+//
+enum sample_type {
+	sample_s16,
+	sample_s24,
+	sample_s32,
+	sample_f32,
+	sample_f64,
+};
+
+struct audio_format {
+	enum sample_type type;
+	int big_endian;
+	int channels;
+	float rate;		// zero if unknown
+};
+
+// What we get when there's no header
+#define AUDIO_FORMAT_RAW { sample_s32, 0, 1, 0 }
+
+static inline int sample_bytes(enum sample_type type)
+{
+	static const int bytes[] = { 2, 3, 4, 4, 8 };
+	return bytes[type];
+}
+
+static inline int frame_bytes(const struct audio_format *fmt)
+{
+	return sample_bytes(fmt->type) * fmt->channels;
+}
+
+//
+// Raw formats by their ffmpeg names: s16le, s24be, f32le etc.
+// Returns -1 for anything we don't know about.
+//
+static inline int parse_raw_format(const char *name, struct audio_format *fmt)
+{
+	static const char *const types[] = { "s16", "s24", "s32", "f32", "f64" };
+
+	for (int i = 0; i < (int) ARRAY_SIZE(types); i++) {
+		if (strncmp(name, types[i], 3))
+			continue;
+		if (strcmp(name + 3, "le") && strcmp(name + 3, "be"))
+			return -1;
+		fmt->type = i;
+		fmt->big_endian = name[3] == 'b';
+		return 0;
+	}
+	return -1;
+}
+
+// And back: 'buf' needs room for at least 6 characters
+static inline const char *raw_format_name(const struct audio_format *fmt, char *buf)
+{
+	static const char *const types[] = { "s16", "s24", "s32", "f32", "f64" };
+
+	snprintf(buf, 6, "%s%s", types[fmt->type], fmt->big_endian ? "be" : "le");
+	return buf;
+}
+
+// Little or big endian unsigned value of 'n' bytes
+static inline u64 load_bytes(const unsigned char *p, int n, int big_endian)
+{
+	u64 val = 0;
+
+	for (int i = 0; i < n; i++)
+		val |= (u64) p[big_endian ? i : n - 1 - i] << (8 * (n - 1 - i));
+	return val;
+}
+
+static inline float decode_sample(const unsigned char *p, const struct audio_format *fmt)
+{
+	u64 val = load_bytes(p, sample_bytes(fmt->type), fmt->big_endian);
+
+	switch (fmt->type) {
+	case sample_s16:
+		return ((s32) ((u32) val << 16) >> 16) * (1.0f / 32768);
+	case sample_s24:
+		// Sign-extend from 24 bits
+		return ((s32) ((u32) val << 8) >> 8) * (1.0f / 8388608);
+	case sample_s32:
+		return (float) ((s32) val * (1.0 / 2147483648.0));
+	case sample_f32: {
+		u32 bits = (u32) val;
+		float f;
+		memcpy(&f, &bits, sizeof(f));
+		return f;
+	}
+	default: {
+		double d;
+		memcpy(&d, &val, sizeof(d));
+		return (float) d;
+	}
+	}
+}
+
+//
+// Decode 'bytes' worth of samples, returning a newly allocated
+// buffer of interleaved floats with the number of frames (ie
+// samples per channel) in *frames. A partial frame at the end
+// is dropped.
+//
+// Everything downstream counts samples in an 'int', so this
+// refuses (returns NULL with *frames = -1) anything that has
+// more than INT_MAX samples in total, rather than overflowing.
+//
+static inline float *decode_samples(const unsigned char *buf, size_t bytes,
+	const struct audio_format *fmt, int *frames)
+{
+	int size = sample_bytes(fmt->type);
+	size_t nr = bytes / frame_bytes(fmt);
+	size_t samples = nr * fmt->channels;
+	float *out;
+
+	*frames = -1;
+	if (nr > (size_t) INT_MAX / fmt->channels)
+		return NULL;
+	*frames = 0;
+	out = malloc((samples + 1) * sizeof(float));
+	if (!out)
+		return NULL;
+	for (size_t i = 0; i < samples; i++)
+		out[i] = decode_sample(buf + i * size, fmt);
+	*frames = (int) nr;
+	return out;
+}
+
+//
+// Reading samples as they come in from a pipe (or a socket, or
+// 'arecord'): read() returns whatever happens to be there, which
+// need not be a whole number of samples, let alone frames. So
+// keep the leftover bytes around for the next time.
+//
+#define AUDIO_STREAM_BUF 8192
+
+struct audio_stream {
+	int fd;
+	struct audio_format fmt;
+	int len;
+	unsigned char buf[AUDIO_STREAM_BUF];
+};
+
+static inline void audio_stream_init(struct audio_stream *s, int fd, const struct audio_format *fmt)
+{
+	s->fd = fd;
+	s->fmt = *fmt;
+	s->len = 0;
+}
+
+//
+// Read up to 'max' frames of interleaved samples. This waits for
+// at least one whole frame, but otherwise returns what's there.
+// Returns 0 at end of file (dropping any partial frame), and -1
+// on errors.
+//
+static inline int audio_stream_read(struct audio_stream *s, float *out, int max)
+{
+	int size = frame_bytes(&s->fmt), frames;
+
+	// Read as much as fits: a datagram socket would throw away
+	// the part of a packet that doesn't
+	while (s->len < size) {
+		ssize_t n = read(s->fd, s->buf + s->len, AUDIO_STREAM_BUF - s->len);
+		if (n <= 0)
+			return (int) n;
+		s->len += n;
+	}
+
+	frames = s->len / size;
+	if (frames > max)
+		frames = max;
+	for (int i = 0; i < frames * s->fmt.channels; i++)
+		out[i] = decode_sample(s->buf + i * sample_bytes(s->fmt.type), &s->fmt);
+
+	s->len -= frames * size;
+	memmove(s->buf, s->buf + frames * size, s->len);
+	return frames;
+}
+
+//
+// Pick one channel out of interleaved samples. 'out' can be
+// the same buffer as 'x', since it only ever moves things down.
+//
+static inline void extract_channel(const float *x, int frames, int channels, int channel, float *out)
+{
+	for (int i = 0; i < frames; i++)
+		out[i] = x[i * channels + channel];
+}
+
+// Split interleaved samples into one buffer per channel
+static inline void deinterleave(const float *x, int frames, int channels, float **out)
+{
+	for (int c = 0; c < channels; c++)
+		extract_channel(x, frames, channels, c, out[c]);
+}
+
+// Average all the channels into the first 'frames' samples
+static inline void downmix(float *x, int frames, int channels)
+{
+	for (int i = 0; i < frames; i++) {
+		float sum = 0;
+
+		for (int c = 0; c < channels; c++)
+			sum += x[i * channels + c];
+		x[i] = sum / channels;
+	}
+}
+
+//
+// WAV files: a RIFF header, and then chunks of 'id, size, data'.
+// We need the "fmt " chunk for the format, and the "data" chunk
+// for the samples. Everything else gets skipped. Handles plain
+// PCM and IEEE float, and WAVE_FORMAT_EXTENSIBLE headers of
+// either of those.
+//
+// RF64 (and its BW64 twin) is the same thing for files over 4GB:
+// the 32-bit sizes are all ~0, and the real ones are in a "ds64"
+// chunk right after the header.
+//
+// Returns 0 and fills in the format and where the sample data
+// is if it looks like a WAV file we can read, and -1 if not.
+//
+#define WAV_FORMAT_PCM 1
+#define WAV_FORMAT_FLOAT 3
+#define WAV_FORMAT_EXTENSIBLE 0xfffe
+#define WAV_SIZE_IN_DS64 0xffffffffu
+
+//
+// Find a chunk in a RIFF file: returns its data (and size), or
+// NULL if there isn't one.
+//
+static inline const unsigned char *riff_chunk(const unsigned char *buf, size_t len,
+	const char *id, size_t *size)
+{
+	for (size_t pos = 12; pos + 8 <= len; ) {
+		const unsigned char *chunk = buf + pos;
+		size_t chunk_size = load_bytes(chunk + 4, 4, 0);
+
+		pos += 8;
+		if (!memcmp(chunk, id, 4)) {
+			*size = chunk_size <= len - pos ? chunk_size : len - pos;
+			return chunk + 8;
+		}
+		if (chunk_size > len - pos)
+			break;
+		pos += chunk_size + (chunk_size & 1);
+	}
+	return NULL;
+}
+
+//
+// Broadcast WAV ("bext" chunk) metadata. The time reference is
+// the sample count since midnight of the first sample, which is
+// what timecode gets derived from.
+//
+struct bext {
+	char description[257];
+	char originator[33];
+	char date[11];		// yyyy-mm-dd
+	char time[9];		// hh:mm:ss
+	u64 time_reference;
+};
+
+static inline int bext_parse(const unsigned char *buf, size_t len, struct bext *b)
+{
+	size_t size;
+	const unsigned char *p = riff_chunk(buf, len, "bext", &size);
+
+	if (!p || size < 346)
+		return -1;
+	memset(b, 0, sizeof(*b));
+	memcpy(b->description, p, 256);
+	memcpy(b->originator, p + 256, 32);
+	memcpy(b->date, p + 320, 10);
+	memcpy(b->time, p + 330, 8);
+	b->time_reference = load_bytes(p + 338, 8, 0);
+	return 0;
+}
+
+static inline int wav_parse(const unsigned char *buf, size_t len,
+	struct audio_format *fmt, size_t *offset, size_t *size)
+{
+	size_t pos = 12;
+	u64 data_size64 = 0;
+	int have_fmt = 0;
+
+	if (len < 12 || memcmp(buf + 8, "WAVE", 4))
+		return -1;
+	if (memcmp(buf, "RIFF", 4) && memcmp(buf, "RF64", 4) && memcmp(buf, "BW64", 4))
+		return -1;
+
+	while (pos + 8 <= len) {
+		const unsigned char *chunk = buf + pos;
+		size_t chunk_size = load_bytes(chunk + 4, 4, 0);
+		const unsigned char *data = chunk + 8;
+
+		pos += 8;
+		if (!memcmp(chunk, "ds64", 4)) {
+			if (chunk_size < 24 || pos + 24 > len)
+				return -1;
+			data_size64 = load_bytes(data + 8, 8, 0);
+		} else if (!memcmp(chunk, "fmt ", 4)) {
+			if (chunk_size < 16 || pos + 16 > len)
+				return -1;
+
+			int tag = (int) load_bytes(data, 2, 0);
+			int bits = (int) load_bytes(data + 14, 2, 0);
+
+			if (tag == WAV_FORMAT_EXTENSIBLE && chunk_size >= 26 && pos + 26 <= len)
+				tag = (int) load_bytes(data + 24, 2, 0);
+
+			fmt->channels = (int) load_bytes(data + 2, 2, 0);
+			fmt->rate = (float) load_bytes(data + 4, 4, 0);
+			fmt->big_endian = 0;
+			if (tag == WAV_FORMAT_PCM && bits == 16)
+				fmt->type = sample_s16;
+			else if (tag == WAV_FORMAT_PCM && bits == 24)
+				fmt->type = sample_s24;
+			else if (tag == WAV_FORMAT_PCM && bits == 32)
+				fmt->type = sample_s32;
+			else if (tag == WAV_FORMAT_FLOAT && bits == 32)
+				fmt->type = sample_f32;
+			else if (tag == WAV_FORMAT_FLOAT && bits == 64)
+				fmt->type = sample_f64;
+			else
+				return -1;
+			if (fmt->channels < 1)
+				return -1;
+			have_fmt = 1;
+		} else if (!memcmp(chunk, "data", 4)) {
+			if (!have_fmt)
+				return -1;
+			if (chunk_size == WAV_SIZE_IN_DS64 && data_size64)
+				chunk_size = data_size64;
+			// Streaming writers leave the size as 0 or ~0
+			*offset = pos;
+			*size = chunk_size && chunk_size <= len - pos ? chunk_size : len - pos;
+			return 0;
+		}
+
+		// Chunks are padded to an even size
+		pos += chunk_size + (chunk_size & 1);
+	}
+	return -1;
+}
+
+//
+// And the other direction: encoding floats, clipped to full
+// scale, and writing a plain (non-extensible) WAV header.
+//
+static inline void store_bytes(unsigned char *p, u64 val, int n, int big_endian)
+{
+	for (int i = 0; i < n; i++)
+		p[big_endian ? n - 1 - i : i] = (unsigned char) (val >> (8 * i));
+}
+
+static inline s32 clip_sample(float val, int bits)
+{
+	double max = (double) (1u << (bits - 1));
+	double x = rint(val * max);
+
+	if (x >= max)
+		return (s32) (max - 1);
+	if (x < -max)
+		return (s32) -max;
+	return (s32) x;
+}
+
+static inline void encode_sample(unsigned char *p, float val, const struct audio_format *fmt)
+{
+	int n = sample_bytes(fmt->type);
+	u64 bits;
+
+	switch (fmt->type) {
+	case sample_s16:
+		bits = (u32) clip_sample(val, 16);
+		break;
+	case sample_s24:
+		bits = (u32) clip_sample(val, 24);
+		break;
+	case sample_s32:
+		bits = (u32) clip_sample(val, 32);
+		break;
+	case sample_f32: {
+		u32 b;
+		memcpy(&b, &val, sizeof(b));
+		bits = b;
+		break;
+	}
+	default: {
+		double d = val;
+		memcpy(&bits, &d, sizeof(bits));
+		break;
+	}
+	}
+	store_bytes(p, bits, n, fmt->big_endian);
+}
+
+static inline void encode_samples(unsigned char *buf, const float *x, int nr, const struct audio_format *fmt)
+{
+	int size = sample_bytes(fmt->type);
+
+	for (int i = 0; i < nr; i++)
+		encode_sample(buf + (size_t) i * size, x[i], fmt);
+}
+
+//
+// If the length isn't known yet (we're writing to a pipe), use
+// ~0 for the data size: wav_parse() above, ffmpeg and most other
+// readers take that to mean "until the end of the file".
+//
+// The header always has room for a "ds64" chunk (as "JUNK" that
+// everybody skips), so that if the file turns out to be over 4GB
+// the header can be rewritten as RF64 in place at the end.
+//
+#define WAV_HEADER_SIZE 80
+#define WAV_UNKNOWN_SIZE (~0ull)
+
+static inline void wav_header(unsigned char hdr[WAV_HEADER_SIZE], const struct audio_format *fmt, u64 data_bytes)
+{
+	int is_float = fmt->type == sample_f32 || fmt->type == sample_f64;
+	int bits = 8 * sample_bytes(fmt->type);
+	int rf64 = data_bytes != WAV_UNKNOWN_SIZE && data_bytes > 0xffffffffu - WAV_HEADER_SIZE;
+	u32 size32 = data_bytes == WAV_UNKNOWN_SIZE || rf64 ? WAV_SIZE_IN_DS64 : (u32) data_bytes;
+
+	memset(hdr, 0, WAV_HEADER_SIZE);
+	memcpy(hdr, rf64 ? "RF64" : "RIFF", 4);
+	store_bytes(hdr + 4, size32 == WAV_SIZE_IN_DS64 ? size32 : size32 + WAV_HEADER_SIZE - 8, 4, 0);
+	memcpy(hdr + 8, "WAVE", 4);
+
+	memcpy(hdr + 12, rf64 ? "ds64" : "JUNK", 4);
+	store_bytes(hdr + 16, 28, 4, 0);
+	if (rf64) {
+		int frame = frame_bytes(fmt);
+		store_bytes(hdr + 20, data_bytes + WAV_HEADER_SIZE - 8, 8, 0);
+		store_bytes(hdr + 28, data_bytes, 8, 0);
+		store_bytes(hdr + 36, data_bytes / frame, 8, 0);
+	}
+
+	memcpy(hdr + 48, "fmt ", 4);
+	store_bytes(hdr + 52, 16, 4, 0);
+	store_bytes(hdr + 56, is_float ? WAV_FORMAT_FLOAT : WAV_FORMAT_PCM, 2, 0);
+	store_bytes(hdr + 58, fmt->channels, 2, 0);
+	store_bytes(hdr + 60, (u32) fmt->rate, 4, 0);
+	store_bytes(hdr + 64, (u32) fmt->rate * frame_bytes(fmt), 4, 0);
+	store_bytes(hdr + 68, frame_bytes(fmt), 2, 0);
+	store_bytes(hdr + 70, bits, 2, 0);
+	memcpy(hdr + 72, "data", 4);
+	store_bytes(hdr + 76, size32, 4, 0);
+}
+
+//
+// Writing raw (or WAV) sample data: encode into a buffer, and
+// write it out in big chunks rather than a few bytes at a time.
+// Nothing goes out until the buffer fills up or you flush it, so
+// anything that cares about latency should flush every block.
+//
+// 'bytes' is the total amount of sample data written so far, for
+// filling in a WAV header afterwards.
+//
+struct audio_writer {
+	int fd;
+	struct audio_format fmt;
+	int len;
+	u64 bytes;
+	unsigned char buf[AUDIO_STREAM_BUF];
+};
+
+static inline void audio_writer_init(struct audio_writer *w, int fd, const struct audio_format *fmt)
+{
+	w->fd = fd;
+	w->fmt = *fmt;
+	w->len = 0;
+	w->bytes = 0;
+}
+
+//
+// Create (or truncate) a raw file, or append to an existing one.
+// If we're appending to something that ends in a partial frame
+// (say, a recording that got killed mid-write), that gets cut off
+// so that the new samples stay frame aligned.
+//
+static inline int audio_writer_open(struct audio_writer *w, const char *name,
+	const struct audio_format *fmt, int append)
+{
+	int fd = open(name, O_CREAT | O_WRONLY | (append ? O_APPEND : O_TRUNC), 0666);
+	struct stat st;
+
+	if (fd < 0)
+		return -1;
+	if (append && !fstat(fd, &st) && S_ISREG(st.st_mode)) {
+		off_t extra = st.st_size % frame_bytes(fmt);
+		if (extra && ftruncate(fd, st.st_size - extra) < 0) {
+			close(fd);
+			return -1;
+		}
+	}
+	audio_writer_init(w, fd, fmt);
+	return 0;
+}
+
+static inline int audio_writer_flush(struct audio_writer *w)
+{
+	int done = 0;
+
+	while (done < w->len) {
+		ssize_t n = write(w->fd, w->buf + done, w->len - done);
+		if (n < 0)
+			return -1;
+		done += n;
+	}
+	w->len = 0;
+	return 0;
+}
+
+// Write 'frames' frames of interleaved samples
+static inline int audio_writer_write(struct audio_writer *w, const float *x, int frames)
+{
+	int size = sample_bytes(w->fmt.type);
+	int nr = frames * w->fmt.channels;
+
+	for (int i = 0; i < nr; i++) {
+		if (w->len + size > AUDIO_STREAM_BUF && audio_writer_flush(w) < 0)
+			return -1;
+		encode_sample(w->buf + w->len, x[i], &w->fmt);
+		w->len += size;
+	}
+	w->bytes += (u64) nr * size;
+	return 0;
+}
+
+static inline int audio_writer_close(struct audio_writer *w)
+{
+	int ret = audio_writer_flush(w);
+
+	if (close(w->fd) < 0)
+		ret = -1;
+	return ret;
+}
+
+//
+// What kind of file is this? Only looks at the first few bytes,
+// so anything we don't recognize is 'raw'. Up to container_caf
+// we can read ourselves (see audio_parse() below), everything
+// after that is something we leave to ffmpeg, except for zstd
+// which is just compression around one of the others (see
+// seekable.h).
+//
+// Note that mp3 files without an ID3 tag (or raw ADTS AAC)
+// aren't recognized: their frame sync is just 0xffe, which a raw
+// capture starting with a small negative sample could trivially
+// match.
+//
+enum audio_container {
+	container_raw,
+	container_wav,
+	container_aiff,
+	container_caf,
+	container_flac,
+	container_ogg,
+	container_mp3,
+	container_mp4,
+	container_zstd,
+};
+
+static inline enum audio_container audio_sniff(const unsigned char *buf, size_t len)
+{
+	static const struct {
+		size_t offset, len;
+		const char *magic;
+		enum audio_container type;
+	} magic[] = {
+		{ 0, 4, "RIFF", container_wav },
+		{ 0, 4, "RF64", container_wav },
+		{ 0, 4, "BW64", container_wav },
+		{ 0, 4, "FORM", container_aiff },	// AIFF or AIFC
+		{ 0, 4, "caff", container_caf },
+		{ 0, 4, "fLaC", container_flac },
+		{ 0, 4, "OggS", container_ogg },
+		{ 0, 3, "ID3", container_mp3 },		// mp3 with tags in front
+		{ 4, 4, "ftyp", container_mp4 },	// mp4/m4a
+		{ 0, 4, "\x28\xb5\x2f\xfd", container_zstd },
+	};
+
+	for (int i = 0; i < (int) ARRAY_SIZE(magic); i++) {
+		if (len >= magic[i].offset + magic[i].len &&
+		    !memcmp(buf + magic[i].offset, magic[i].magic, magic[i].len))
+			return magic[i].type;
+	}
+	return container_raw;
+}
+
+//
+// AIFF is the big-endian Mac cousin of WAV: a "FORM" header, then
+// chunks of 'id, size, data' with the format in "COMM" and the
+// samples in "SSND". AIFC adds a compression type to "COMM", of
+// which we handle the uncompressed ones - including "sowt", which
+// is little-endian PCM in spite of everything.
+//
+// The sample rate is an 80-bit IEEE extended float, because of
+// course it is.
+//
+static inline double aiff_rate(const unsigned char *p)
+{
+	int exp = (int) load_bytes(p, 2, 1);
+	u64 mantissa = load_bytes(p + 2, 8, 1);
+	double val = ldexp((double) mantissa, (exp & 0x7fff) - 16383 - 63);
+
+	return exp & 0x8000 ? -val : val;
+}
+
+static inline int aiff_parse(const unsigned char *buf, size_t len,
+	struct audio_format *fmt, size_t *offset, size_t *size)
+{
+	size_t pos = 12;
+	int have_fmt = 0, aifc;
+
+	if (len < 12 || memcmp(buf, "FORM", 4))
+		return -1;
+	if (!memcmp(buf + 8, "AIFF", 4))
+		aifc = 0;
+	else if (!memcmp(buf + 8, "AIFC", 4))
+		aifc = 1;
+	else
+		return -1;
+
+	while (pos + 8 <= len) {
+		const unsigned char *chunk = buf + pos;
+		size_t chunk_size = load_bytes(chunk + 4, 4, 1);
+		const unsigned char *data = chunk + 8;
+
+		pos += 8;
+		if (!memcmp(chunk, "COMM", 4)) {
+			if (chunk_size < 18 || pos + 18 > len)
+				return -1;
+
+			int bits = (int) load_bytes(data + 6, 2, 1);
+			const unsigned char *type = (const unsigned char *) "NONE";
+
+			if (aifc) {
+				if (chunk_size < 22 || pos + 22 > len)
+					return -1;
+				type = data + 18;
+			}
+
+			fmt->channels = (int) load_bytes(data, 2, 1);
+			fmt->rate = (float) aiff_rate(data + 8);
+			fmt->big_endian = 1;
+			if (!memcmp(type, "NONE", 4) || !memcmp(type, "twos", 4) || !memcmp(type, "sowt", 4)) {
+				fmt->big_endian = memcmp(type, "sowt", 4) != 0;
+				if (bits == 16)
+					fmt->type = sample_s16;
+				else if (bits == 24)
+					fmt->type = sample_s24;
+				else if (bits == 32)
+					fmt->type = sample_s32;
+				else
+					return -1;
+			} else if (!memcmp(type, "fl32", 4) || !memcmp(type, "FL32", 4)) {
+				fmt->type = sample_f32;
+			} else if (!memcmp(type, "fl64", 4) || !memcmp(type, "FL64", 4)) {
+				fmt->type = sample_f64;
+			} else {
+				return -1;
+			}
+			if (fmt->channels < 1)
+				return -1;
+			have_fmt = 1;
+		} else if (!memcmp(chunk, "SSND", 4)) {
+			if (!have_fmt || chunk_size < 8 || pos + 8 > len)
+				return -1;
+
+			// The samples start 'offset' bytes after the 8-byte SSND header
+			size_t skip = 8 + load_bytes(data, 4, 1);
+			if (skip > chunk_size || pos + skip > len)
+				return -1;
+			*offset = pos + skip;
+			*size = chunk_size - skip <= len - *offset ? chunk_size - skip : len - *offset;
+			return 0;
+		}
+
+		// Chunks are padded to an even size
+		pos += chunk_size + (chunk_size & 1);
+	}
+	return -1;
+}
+
+//
+// CAF (Core Audio Format): "caff", version and flags, and then
+// chunks with 64-bit sizes. The format is in "desc", and the
+// samples are in "data" after a 4-byte edit count. A data size
+// of -1 means "until the end of the file". Only linear PCM.
+//
+#define CAF_FLAG_FLOAT 1
+#define CAF_FLAG_LITTLE_ENDIAN 2
+
+static inline int caf_parse(const unsigned char *buf, size_t len,
+	struct audio_format *fmt, size_t *offset, size_t *size)
+{
+	size_t pos = 8;
+	int have_fmt = 0;
+
+	if (len < 8 || memcmp(buf, "caff", 4))
+		return -1;
+
+	while (pos + 12 <= len) {
+		const unsigned char *chunk = buf + pos;
+		u64 chunk_size = load_bytes(chunk + 4, 8, 1);
+		const unsigned char *data = chunk + 12;
+
+		pos += 12;
+		if (!memcmp(chunk, "desc", 4)) {
+			if (chunk_size < 32 || pos + 32 > len)
+				return -1;
+
+			u64 bits = load_bytes(data, 8, 1);
+			double rate;
+			int flags = (int) load_bytes(data + 12, 4, 1);
+			int depth = (int) load_bytes(data + 28, 4, 1);
+
+			memcpy(&rate, &bits, sizeof(rate));
+			if (memcmp(data + 8, "lpcm", 4))
+				return -1;
+			fmt->rate = (float) rate;
+			fmt->channels = (int) load_bytes(data + 24, 4, 1);
+			fmt->big_endian = !(flags & CAF_FLAG_LITTLE_ENDIAN);
+			if (flags & CAF_FLAG_FLOAT && depth == 32)
+				fmt->type = sample_f32;
+			else if (flags & CAF_FLAG_FLOAT && depth == 64)
+				fmt->type = sample_f64;
+			else if (!(flags & CAF_FLAG_FLOAT) && depth == 16)
+				fmt->type = sample_s16;
+			else if (!(flags & CAF_FLAG_FLOAT) && depth == 24)
+				fmt->type = sample_s24;
+			else if (!(flags & CAF_FLAG_FLOAT) && depth == 32)
+				fmt->type = sample_s32;
+			else
+				return -1;
+			if (fmt->channels < 1)
+				return -1;
+			have_fmt = 1;
+		} else if (!memcmp(chunk, "data", 4)) {
+			if (!have_fmt || pos + 4 > len)
+				return -1;
+			*offset = pos + 4;
+			*size = len - *offset;
+			if (chunk_size != ~0ull && chunk_size >= 4 && chunk_size - 4 < *size)
+				*size = chunk_size - 4;
+			return 0;
+		}
+
+		if (chunk_size > len - pos)
+			return -1;
+		pos += chunk_size;
+	}
+	return -1;
+}
+
+//
+// Any of the containers we know how to read: fill in the format
+// and where the samples are, or return -1 if it's none of them
+// (or one we can't handle).
+//
+static inline int audio_parse(const unsigned char *buf, size_t len,
+	struct audio_format *fmt, size_t *offset, size_t *size)
+{
+	switch (audio_sniff(buf, len)) {
+	case container_wav:
+		return wav_parse(buf, len, fmt, offset, size);
+	case container_aiff:
+		return aiff_parse(buf, len, fmt, offset, size);
+	case container_caf:
+		return caf_parse(buf, len, fmt, offset, size);
+	default:
+		return -1;
+	}
+}
+
+//
+// Guessing the sample format of a headerless file. Real audio
+// is mostly low frequency stuff, so consecutive samples are
+// close to each other. Decoding it with the wrong width or byte
+// order scrambles that into something that looks like white
+// noise, where the average difference between neighbors is
+// about 1.4x the average level.
+//
+// So try all of them, and pick the one with the smallest
+// difference-to-level ratio. Except that float bit patterns read
+// as integers look like a smooth (very compressed) version of
+// the signal too, so the float formats get looked at first. The
+// other way around is easy to rule out: integers read as floats
+// come out either tiny or all over the place.
+//
+// Ties go to the earlier entry, and s32le (what we normally use)
+// is the first integer one. Returns -1 (leaving 'fmt' alone) if
+// nothing looks like audio, which includes digital silence.
+//
+// This can't tell you anything about the sample rate, and the
+// channel count in 'fmt' is taken as given. The only thing the
+// samples say about the rate is where the spectrum runs out, and
+// that's as far up as anything was played or recorded. It puts
+// 44.1kHz and 48kHz in the same place, and makes a clean bass DI
+// at 48kHz look like 192kHz. So the rate comes from a header, a
+// sidecar, or '--rate'.
+//
+#define GUESS_BYTES 65536
+#define GUESS_MAX_RATIO 0.7
+
+static inline double raw_format_score(const unsigned char *buf, size_t len, const struct audio_format *fmt)
+{
+	int size = sample_bytes(fmt->type), step = fmt->channels;
+	int nr = (int) ((len < GUESS_BYTES ? len : GUESS_BYTES) / (size * step));
+	double level = 0, diff = 0;
+	float prev = 0;
+
+	if (nr < 16)
+		return INFINITY;
+	for (int i = 0; i < nr; i++) {
+		float val = decode_sample(buf + (size_t) i * size * step, fmt);
+
+		if (!isfinite(val) || fabsf(val) > 16)
+			return INFINITY;
+		level += fabsf(val);
+		if (i)
+			diff += fabsf(val - prev);
+		prev = val;
+	}
+	// Way below what even a 24-bit integer could represent
+	if (level < nr * 1e-9)
+		return INFINITY;
+	return diff / level;
+}
+
+static inline int guess_raw_format(const unsigned char *buf, size_t len, struct audio_format *fmt)
+{
+	static const enum sample_type types[] = {
+		sample_f32, sample_f64,
+		sample_s32, sample_s16, sample_s24
+	};
+	struct audio_format best = *fmt, try = *fmt;
+	double best_score = GUESS_MAX_RATIO;
+
+	for (int i = 0; i < (int) ARRAY_SIZE(types); i++) {
+		// A plausible float format beats any integer one
+		if (types[i] == sample_s32 && best_score < GUESS_MAX_RATIO)
+			break;
+		for (int be = 0; be < 2; be++) {
+			try.type = types[i];
+			try.big_endian = be;
+
+			double score = raw_format_score(buf, len, &try);
+			if (score < best_score) {
+				best_score = score;
+				best = try;
+			}
+		}
+	}
+	if (best_score >= GUESS_MAX_RATIO)
+		return -1;
+	*fmt = best;
+	return 0;
+}
+
+//
+// Save 'frames' frames of interleaved samples to a new file: raw
+// in the given format, or a WAV file of it if 'wav' is set (and
+// then fmt->rate had better be filled in). Anything past 4GB
+// turns into RF64.
+//
+static inline int audio_export(const char *name, const float *x, size_t frames,
+	const struct audio_format *fmt, int wav)
+{
+	struct audio_writer w;
+	int ret = 0;
+
+	if (audio_writer_open(&w, name, fmt, 0) < 0)
+		return -1;
+	if (wav) {
+		unsigned char hdr[WAV_HEADER_SIZE];
+		u64 bytes = (u64) frames * frame_bytes(fmt);
+
+		wav_header(hdr, fmt, bytes);
+		if (write(w.fd, hdr, sizeof(hdr)) != sizeof(hdr))
+			ret = -1;
+	}
+	for (size_t pos = 0; pos < frames && !ret; pos += 4096) {
+		int nr = frames - pos < 4096 ? (int) (frames - pos) : 4096;
+		ret = audio_writer_write(&w, x + pos * fmt->channels, nr);
+	}
+	if (audio_writer_close(&w) < 0)
+		ret = -1;
+	return ret;
+}
+
+//
+// Looking at a file in place: map the whole thing, and decode
+// the samples as they're needed rather than all up front. Only
+// works for regular files, of course - pipes still need to be
+// read into memory.
+//
+// audio_file_map() just maps the bytes, the caller decides what
+// they are (see audio_sniff() and audio_parse() above) and then
+// points at the sample data with audio_file_set_format().
+//
+// For multi-GB captures where only a window is ever looked at,
+// audio_file_map_range() maps just part of the file. mmap wants
+// a page aligned offset, so the mapping can start a bit before
+// the part that was asked for.
+//
+struct audio_file {
+	unsigned char *map;		// what's mapped
+	size_t len;
+	size_t map_offset;		// where that is in the file
+	struct audio_format fmt;
+	const unsigned char *data;	// the samples in it
+	size_t frames;
+};
+
+// 'len' of zero means everything from 'offset' on
+static inline int audio_file_map_range(struct audio_file *f, int fd, size_t offset, size_t len)
+{
+	size_t page = sysconf(_SC_PAGESIZE), start = offset / page * page;
+	struct stat st;
+	void *map;
+
+	memset(f, 0, sizeof(*f));
+	if (fstat(fd, &st) < 0 || !S_ISREG(st.st_mode) || offset >= (size_t) st.st_size)
+		return -1;
+	if (!len || len > st.st_size - offset)
+		len = st.st_size - offset;
+	map = mmap(NULL, len + offset - start, PROT_READ, MAP_PRIVATE, fd, start);
+	if (map == MAP_FAILED)
+		return -1;
+	f->map = map;
+	f->len = len + offset - start;
+	f->map_offset = start;
+	f->data = f->map + offset - start;
+	return 0;
+}
+
+static inline int audio_file_map(struct audio_file *f, int fd)
+{
+	return audio_file_map_range(f, fd, 0, 0);
+}
+
+static inline void audio_file_unmap(struct audio_file *f)
+{
+	if (f->map)
+		munmap(f->map, f->len);
+	memset(f, 0, sizeof(*f));
+}
+
+// 'offset' is from the start of the file, not of the mapping
+static inline void audio_file_set_format(struct audio_file *f, const struct audio_format *fmt,
+	size_t offset, size_t size)
+{
+	f->fmt = *fmt;
+	f->data = f->map + offset - f->map_offset;
+	f->frames = size / frame_bytes(fmt);
+}
+
+//
+// Tell the kernel how frames [pos, pos+nr) are going to be used:
+// MADV_SEQUENTIAL when streaming through them (so it reads ahead
+// aggressively and drops pages behind us), MADV_WILLNEED to get
+// the reads going before we get there.
+//
+static inline void audio_file_advise(const struct audio_file *f, size_t pos, size_t nr, int advice)
+{
+	size_t page = sysconf(_SC_PAGESIZE);
+	unsigned long from, to;
+
+	if (pos >= f->frames)
+		return;
+	if (nr > f->frames - pos)
+		nr = f->frames - pos;
+	from = (unsigned long) (f->data + pos * frame_bytes(&f->fmt));
+	to = from + nr * frame_bytes(&f->fmt);
+	from = from / page * page;
+	if (to > from)
+		madvise((void *) from, to - from, advice);
+}
+
+// One sample of frame 'pos'
+static inline float audio_file_sample(const struct audio_file *f, size_t pos, int channel)
+{
+	size_t idx = pos * f->fmt.channels + channel;
+	return decode_sample(f->data + idx * sample_bytes(f->fmt.type), &f->fmt);
+}
+
+// Decode up to 'max' interleaved frames starting at frame 'pos'
+static inline int audio_file_read(const struct audio_file *f, size_t pos, float *out, int max)
+{
+	int nr;
+
+	if (pos >= f->frames)
+		return 0;
+	nr = f->frames - pos < (size_t) max ? (int) (f->frames - pos) : max;
+	for (int i = 0; i < nr * f->fmt.channels; i++)
+		out[i] = decode_sample(f->data + (pos * f->fmt.channels + i) * sample_bytes(f->fmt.type), &f->fmt);
+	return nr;
+}
+
+//
+// Walking through a (huge) file a chunk at a time:
+//
+//	struct audio_chunk c;
+//
+//	for_each_audio_chunk(&file, &c, 4096)
+//		... c.frames frames starting at frame c.pos, with the
+//		    raw bytes at c.data ...
+//
+// The last chunk is whatever is left over.
+//
+struct audio_chunk {
+	size_t pos;
+	int frames;
+	const unsigned char *data;
+};
+
+static inline int audio_file_next_chunk(const struct audio_file *f, struct audio_chunk *c, int len)
+{
+	c->pos += c->frames;
+	if (c->pos >= f->frames)
+		return 0;
+	c->frames = f->frames - c->pos < (size_t) len ? (int) (f->frames - c->pos) : len;
+	c->data = f->data + c->pos * frame_bytes(&f->fmt);
+
+	// Start reading the next one while this one gets looked at
+	audio_file_advise(f, c->pos + c->frames, c->frames, MADV_WILLNEED);
+	return 1;
+}
+
+#define for_each_audio_chunk(f, c, len) \
+	for ((c)->pos = 0, (c)->frames = 0; audio_file_next_chunk(f, c, len); )
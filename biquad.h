@@ -110,6 +110,50 @@ static inline void _biquad_allpass_filter(struct biquad_coeff *res, float f, flo
 	res->a2 = res->b0;
 }
 
+//
+// Turn an analog second-order section
+//
+//          b[0] s^2 + b[1] s + b[2]
+//   H(s) = ------------------------
+//          a[0] s^2 + a[1] s + a[2]
+//
+// into biquad coefficients using the plain bilinear transform
+// (no pre-warping). Done in double precision, because the
+// interesting cases have poles very close to z=1.
+//
+static inline void _biquad_bilinear(struct biquad_coeff *res, const double b[3], const double a[3])
+{
+	double K = 2*SAMPLES_PER_SEC, K2 = K*K;
+	double a0_inv = 1/(a[0]*K2 + a[1]*K + a[2]);
+
+	res->b0 = (float) ((b[0]*K2 + b[1]*K + b[2]) * a0_inv);
+	res->b1 = (float) (2*(b[2] - b[0]*K2) * a0_inv);
+	res->b2 = (float) ((b[0]*K2 - b[1]*K + b[2]) * a0_inv);
+	res->a1 = (float) (2*(a[2] - a[0]*K2) * a0_inv);
+	res->a2 = (float) ((a[0]*K2 - a[1]*K + a[2]) * a0_inv);
+}
+
+//
+// Magnitude response of a biquad at frequency 'f'
+//
+// This is not for the audio path, so use the real sin/cos
+// in double precision: the table lookup isn't good enough
+// for the low-frequency poles that sit right next to z=1.
+//
+static inline float biquad_response(struct biquad_coeff *c, float f)
+{
+	double w = 2*M_PI*f/SAMPLES_PER_SEC;
+	double cw = cos(w), sw = sin(w), c2w = cos(2*w), s2w = sin(2*w);
+
+	// Evaluate b0 + b1 z^-1 + b2 z^-2 (and a) at z = e^jw
+	double nre = c->b0 + c->b1*cw + c->b2*c2w;
+	double nim = c->b1*sw + c->b2*s2w;
+	double dre = 1 + c->a1*cw + c->a2*c2w;
+	double dim = c->a1*sw + c->a2*s2w;
+
+	return (float) sqrt((nre*nre + nim*nim) / (dre*dre + dim*dim));
+}
+
 static inline float biquad_step(struct biquad *bq, float x0)
 { return _biquad_step(&bq->coeff, &bq->state, x0); }
 
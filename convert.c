@@ -6,6 +6,12 @@
 #include <string.h>
 #include <pthread.h>
 #include <math.h>
+#include <ctype.h>
+#include <limits.h>
+#include <sys/stat.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <netdb.h>
 
 #define SAMPLES_PER_SEC (48000.0)
 
@@ -15,6 +21,10 @@
 #include "effect.h"
 #include "biquad.h"
 #include "process.h"
+#include "resample.h"
+#include "audiofile.h"
+#include "sidecar.h"
+#include "net.h"
 
 // Effects
 #include "flanger.h"
@@ -27,6 +37,12 @@
 #include "tube.h"
 #include "growlingbass.h"
 #include "pll.h"
+#include "weighting.h"
+#include "meter.h"
+#include "noise.h"
+#include "osc.h"
+#include "fft.h"
+#include "sweep.h"
 
 static void magnitude_describe(float pot[4]) { fprintf(stderr, "\n"); }
 static void magnitude_init(float pot[4]) {}
@@ -51,31 +67,158 @@ struct effect {
 	/* "Helper" effects */
 	EFF(am),
 	EFF(fm),
+	EFF(aweight),
+	EFF(cweight),
+	EFF(peakmeter),
+	EFF(rmsmeter),
+	EFF(vumeter),
+	EFF(ppm),
+	EFF(noise),
+	EFF(osc),
+	EFF(sweep),
 	EFF(magnitude),
 };
 
 #define UPDATE(x) x += 0.001 * (target_##x - x)
 
+//
+// Input at some other sample rate (--rate=<Hz>) gets converted
+// to SAMPLES_PER_SEC on the fly, one sample at a time, before
+// it hits the effect.
+//
+#define MAX_UPSAMPLE 8
+static struct resample_stream input_src;
+static int input_rate;
+
+static int resample_input(s32 *in, int nr, s32 *out)
+{
+	int count = 0;
+
+	for (int i = 0; i < nr; i++) {
+		float res[MAX_UPSAMPLE];
+		int n = resample_stream_push(&input_src, (float) in[i], res);
+
+		// The filter can overshoot a full-scale input
+		for (int j = 0; j < n; j++) {
+			float val = fminf(fmaxf(res[j], -2147483648.0f), 2147483520.0f);
+			out[count++] = (s32) val;
+		}
+	}
+	return count;
+}
+
 #define BLOCKSIZE 200
+
+//
+// Output as a WAV file (--wav=16|24|32|float), or as some other
+// raw format than s32le (--output-format=s16le etc). The WAV
+// header gets the real size filled in at the end if the output
+// is seekable (turning it into RF64 if it's over 4GB).
+//
+static struct audio_format output_format = AUDIO_FORMAT_RAW;
+static struct audio_writer writer;
+static int wav_output, append_output, sidecar_output;
+
+static int parse_wav_format(const char *arg)
+{
+	static const struct {
+		const char *name;
+		enum sample_type type;
+	} formats[] = {
+		{ "16", sample_s16 },
+		{ "24", sample_s24 },
+		{ "32", sample_s32 },
+		{ "float", sample_f32 },
+	};
+
+	for (int i = 0; i < ARRAY_SIZE(formats); i++) {
+		if (!strcmp(arg, formats[i].name)) {
+			output_format = (struct audio_format) { formats[i].type, 0, 1, SAMPLES_PER_SEC };
+			wav_output = 1;
+			return 0;
+		}
+	}
+	return -1;
+}
+
+// The plain s32le output doesn't need to go through the writer
+static int native_output(void)
+{
+	return !wav_output && output_format.type == sample_s32 && !output_format.big_endian;
+}
+
+static void write_wav_header(int out, u64 size)
+{
+	unsigned char hdr[WAV_HEADER_SIZE];
+
+	wav_header(hdr, &output_format, size);
+	write(out, hdr, sizeof(hdr));
+}
+
+static void finish_output(int out)
+{
+	if (native_output())
+		return;
+	audio_writer_flush(&writer);
+	if (wav_output && lseek(out, 0, SEEK_SET) == 0)
+		write_wav_header(out, writer.bytes);
+}
+
+//
+// Input in some other raw format than s32le (--format=s16le etc).
+// We still turn it into s32 samples for process_input().
+//
+static struct audio_format input_format = AUDIO_FORMAT_RAW;
+static struct audio_stream input_stream;
+
+static int read_input(int in, s32 *input)
+{
+	float buf[BLOCKSIZE];
+	int nr;
+
+	if (input_format.type == sample_s32 && !input_format.big_endian)
+		return read(in, input, BLOCKSIZE * 4);
+
+	nr = audio_stream_read(&input_stream, buf, BLOCKSIZE);
+	for (int i = 0; i < nr; i++)
+		input[i] = clip_sample(buf[i], 32);
+	return nr * 4;
+}
+
 static inline int make_one_noise(int in, int out, struct effect *eff)
 {
-	s32 input[BLOCKSIZE], output[BLOCKSIZE];
-	int nr = read(in, input, sizeof(input));
-	if (nr <= 0)
-		return nr;
+	s32 input[BLOCKSIZE], resampled[BLOCKSIZE * MAX_UPSAMPLE];
+	s32 output[BLOCKSIZE * MAX_UPSAMPLE];
+	float result[BLOCKSIZE * MAX_UPSAMPLE];
+	s32 *samples = input;
+	int bytes = read_input(in, input);
+	if (bytes <= 0)
+		return bytes;
+
+	int nr = bytes / 4;
+	if (input_rate) {
+		nr = resample_input(input, nr, resampled);
+		samples = resampled;
+	}
 
-	nr /= 4;
 	for (int i = 0; i < nr; i++) {
 		UPDATE(effect_delay);
 
-		float val = process_input(input[i]);
+		float val = process_input(samples[i]);
 
 		val = eff->step(val);
 
+		result[i] = val;
 		output[i] = process_output(val);
 	}
-	write(out, output, nr * 4);
-	return nr * 4;
+	if (native_output()) {
+		write(out, output, nr * 4);
+	} else {
+		// Don't sit on the samples, somebody may be listening
+		audio_writer_write(&writer, result, nr);
+		audio_writer_flush(&writer);
+	}
+	return bytes;
 }
 
 static int pot_control = -1;
@@ -104,10 +247,24 @@ static void *modify_pots(void *arg)
 	}
 }
 
+// Write 'output.raw.json' saying what's in the raw output file
+static void write_sidecar(const char *name, struct effect *eff)
+{
+	struct audio_format fmt = output_format;
+	char notes[SIDECAR_NOTES];
+
+	fmt.rate = SAMPLES_PER_SEC;
+	snprintf(notes, sizeof(notes), "%s %.2f %.2f %.2f %.2f", eff->name,
+		pots[0], pots[1], pots[2], pots[3]);
+	if (sidecar_write(name, &fmt, notes) < 0)
+		perror(name);
+}
+
 int main(int argc, char **argv)
 {
 	struct effect *eff = NULL;
 	int input = -1, output = -1;
+	const char *output_name = NULL;
 	int potnr = 0;
 
 	for (int i = 1; i < argc; i++) {
@@ -122,6 +279,45 @@ int main(int argc, char **argv)
 			exit(1);
 		}
 
+		if (!strncmp(arg, "--rate=", 7)) {
+			input_rate = strtol(arg+7, &endptr, 0);
+			if (endptr != arg+7 && input_rate >= SAMPLES_PER_SEC / MAX_UPSAMPLE)
+				continue;
+			fprintf(stderr, "Bad input sample rate (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--format=", 9)) {
+			if (!parse_raw_format(arg+9, &input_format))
+				continue;
+			fprintf(stderr, "Bad input sample format (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strncmp(arg, "--output-format=", 16)) {
+			if (!parse_raw_format(arg+16, &output_format))
+				continue;
+			fprintf(stderr, "Bad output sample format (%s)\n", arg);
+			exit(1);
+		}
+
+		if (!strcmp(arg, "--sidecar")) {
+			sidecar_output = 1;
+			continue;
+		}
+
+		if (!strcmp(arg, "--append")) {
+			append_output = 1;
+			continue;
+		}
+
+		if (!strncmp(arg, "--wav=", 6)) {
+			if (!parse_wav_format(arg+6))
+				continue;
+			fprintf(stderr, "Bad WAV format (%s), use 16, 24, 32 or float\n", arg);
+			exit(1);
+		}
+
 		// Is the argument a floating point number?
 		// The we assume it's a default pot value
 		float val = strtof(arg, &endptr);
@@ -151,6 +347,16 @@ int main(int argc, char **argv)
 				continue;
 			}
 
+			if (is_url(arg)) {
+				int fd = net_open(arg);
+				if (fd < 0) {
+					fprintf(stderr, "Can't open %s\n", arg);
+					exit(1);
+				}
+				input = fd;
+				continue;
+			}
+
 			int fd = open(arg, O_RDONLY);
 			if (fd < 0) {
 				perror(arg);
@@ -161,18 +367,11 @@ int main(int argc, char **argv)
 		}
 
 		if (output < 0) {
-			// We assume second filename is an output file
-			if (!strcmp(arg, "-")) {
-				output = 1;
-				continue;
-			}
-
-			int fd = open(arg, O_CREAT | O_WRONLY, 0666);
-			if (fd < 0) {
-				perror(arg);
-				exit(1);
-			}
-			output = fd;
+			// We assume second filename is an output file,
+			// but we don't know yet whether to append to it
+			output = 1;
+			if (strcmp(arg, "-"))
+				output_name = arg;
 			continue;
 		}
 
@@ -183,8 +382,23 @@ int main(int argc, char **argv)
 	if (input < 0)
 		input = 0;
 
-	if (output < 0)
+	if (wav_output && append_output) {
+		fprintf(stderr, "Can't append to a WAV file\n");
+		exit(1);
+	}
+
+	if (output_name) {
+		if (audio_writer_open(&writer, output_name, &output_format, append_output) < 0) {
+			perror(output_name);
+			exit(1);
+		}
+		output = writer.fd;
+		if (sidecar_output && !wav_output)
+			write_sidecar(output_name, eff);
+	} else {
 		output = 1;
+		audio_writer_init(&writer, output, &output_format);
+	}
 
 #ifdef F_SETPIPE_SZ
 	// Limit the output buffer size if we are
@@ -194,6 +408,15 @@ int main(int argc, char **argv)
 	fcntl(output, F_SETPIPE_SZ, 4096);
 #endif
 
+	audio_stream_init(&input_stream, input, &input_format);
+
+	if (input_rate == SAMPLES_PER_SEC)
+		input_rate = 0;
+	if (input_rate && resample_stream_init(&input_src, input_rate, SAMPLES_PER_SEC, 1) < 0) {
+		fprintf(stderr, "Out of memory\n");
+		exit(1);
+	}
+
 	fprintf(stderr, "Playing %s: ",	eff->name);
 	eff->describe(pots);
 
@@ -201,12 +424,17 @@ int main(int argc, char **argv)
 	if (pot_control >= 0)
 		pthread_create(&pot_thread, NULL, modify_pots, eff);
 
+	if (wav_output)
+		write_wav_header(output, WAV_UNKNOWN_SIZE);
+
 	for (;;) {
 		eff->init(pots);
 		if (make_one_noise(input, output, eff) <= 0)
 			break;
 	}
 
+	finish_output(output);
+
 	if (pot_control >= 0)
 		pthread_cancel(pot_thread);
 	return 0;
@@ -0,0 +1,131 @@
+//
+// Cross-correlation of two captures, for lining them up
+//
+// The classic case is a loopback capture (or the same thing
+// recorded with two mics) where one of them starts a bit later
+// than the other. Before diffing them we need to know by how
+// much.
+//
+// This is done with the FFT: zero-pad both to avoid wrap-around,
+// multiply one spectrum by the conjugate of the other, and the
+// inverse transform is the correlation at every lag. To keep the
+// memory use sane, only the first ALIGN_MAX_SAMPLES of each
+// capture are looked at, which is plenty to find an offset.
+//
+#define ALIGN_MAX_SAMPLES (1 << 20)
+
+//
+// Find the lag (within +- max_lag) where 'b' best matches 'a',
+// ie b[n] ~ a[n - lag]. A positive lag means 'b' is late.
+//
+// Returns the normalized correlation at that lag (-1 .. 1) in
+// *corr, and -1 if we ran out of memory. We look for the biggest
+// absolute correlation, so a polarity flip between the captures
+// shows up as a negative correlation rather than a wrong lag.
+//
+static inline int cross_correlate(const float *a, int na, const float *b, int nb,
+	int max_lag, int *lag, float *corr)
+{
+	if (na > ALIGN_MAX_SAMPLES)
+		na = ALIGN_MAX_SAMPLES;
+	if (nb > ALIGN_MAX_SAMPLES)
+		nb = ALIGN_MAX_SAMPLES;
+
+	int n = fft_size(na + nb);
+	float *are = calloc(n, sizeof(float)), *aim = calloc(n, sizeof(float));
+	float *bre = calloc(n, sizeof(float)), *bim = calloc(n, sizeof(float));
+	double ea = 0, eb = 0;
+	int ret = -1;
+
+	*lag = 0;
+	*corr = 0;
+	if (!are || !aim || !bre || !bim)
+		goto out;
+
+	for (int i = 0; i < na; i++) {
+		are[i] = a[i];
+		ea += (double) a[i] * a[i];
+	}
+	for (int i = 0; i < nb; i++) {
+		bre[i] = b[i];
+		eb += (double) b[i] * b[i];
+	}
+
+	fft(are, aim, n);
+	fft(bre, bim, n);
+
+	// conj(A) * B
+	for (int i = 0; i < n; i++) {
+		float re = are[i]*bre[i] + aim[i]*bim[i];
+		float im = are[i]*bim[i] - aim[i]*bre[i];
+		are[i] = re;
+		aim[i] = im;
+	}
+	ifft(are, aim, n);
+
+	// Lag 'k' is at index k for positive lags, n+k for negative ones
+	float best = 0;
+	for (int k = -max_lag; k <= max_lag; k++) {
+		if (k >= nb || -k >= na)
+			continue;
+		float val = are[k < 0 ? n + k : k];
+		if (fabsf(val) > fabsf(best)) {
+			best = val;
+			*lag = k;
+		}
+	}
+
+	if (ea && eb)
+		*corr = (float) (best / sqrt(ea * eb));
+	ret = 0;
+out:
+	free(are); free(aim);
+	free(bre); free(bim);
+	return ret;
+}
+
+//
+// Phase correlation between two channels (the "correlation
+// meter" on a mixing desk): +1 is identical (mono), 0 is
+// unrelated, and -1 is one channel inverted, which is the
+// thing that disappears when summed to mono.
+//
+static inline float phase_correlation(const float *l, const float *r, int nr)
+{
+	double lr = 0, ll = 0, rr = 0;
+
+	for (int i = 0; i < nr; i++) {
+		lr += (double) l[i] * r[i];
+		ll += (double) l[i] * l[i];
+		rr += (double) r[i] * r[i];
+	}
+	if (!ll || !rr)
+		return 0;
+	return (float) (lr / sqrt(ll * rr));
+}
+
+//
+// The streaming version keeps exponentially smoothed sums
+// with a time constant of 'ms' milliseconds
+//
+struct correlation_meter {
+	float coeff;
+	float lr, ll, rr;
+};
+
+static inline void correlation_meter_init(struct correlation_meter *m, float ms)
+{
+	memset(m, 0, sizeof(*m));
+	m->coeff = 1 - expf((float) (-1000 / (ms * SAMPLES_PER_SEC)));
+}
+
+static inline float correlation_meter_step(struct correlation_meter *m, float l, float r)
+{
+	m->lr += m->coeff * (l*r - m->lr);
+	m->ll += m->coeff * (l*l - m->ll);
+	m->rr += m->coeff * (r*r - m->rr);
+
+	if (m->ll <= 0 || m->rr <= 0)
+		return 0;
+	return m->lr / sqrtf(m->ll * m->rr);
+}
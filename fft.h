@@ -0,0 +1,197 @@
+//
+// Plain in-place radix-2 FFT
+//
+// Nothing clever: separate real and imaginary arrays, a
+// power-of-two size, bit-reversal followed by the butterflies.
+// The twiddle factors are done with a rotation in double so
+// that even the big transforms stay reasonably accurate.
+//
+// This is for offline analysis, not for the audio path.
+//
+static inline void fft_bitreverse(float *re, float *im, int n)
+{
+	for (int i = 1, j = 0; i < n; i++) {
+		int bit = n >> 1;
+
+		for (; j & bit; bit >>= 1)
+			j ^= bit;
+		j ^= bit;
+
+		if (i < j) {
+			float t;
+			t = re[i]; re[i] = re[j]; re[j] = t;
+			t = im[i]; im[i] = im[j]; im[j] = t;
+		}
+	}
+}
+
+// 'dir' is -1 for the forward transform, +1 for the inverse
+static inline void _fft(float *re, float *im, int n, int dir)
+{
+	fft_bitreverse(re, im, n);
+
+	for (int len = 2; len <= n; len <<= 1) {
+		double w = dir * 2 * M_PI / len;
+		double rc = cos(w), rs = sin(w);
+		int half = len >> 1;
+
+		for (int i = 0; i < n; i += len) {
+			double c = 1, s = 0;
+
+			for (int j = 0; j < half; j++) {
+				int a = i + j, b = a + half;
+				float tr = (float) (re[b]*c - im[b]*s);
+				float ti = (float) (re[b]*s + im[b]*c);
+				double tmp;
+
+				re[b] = re[a] - tr; im[b] = im[a] - ti;
+				re[a] += tr; im[a] += ti;
+
+				tmp = c*rc - s*rs;
+				s = s*rc + c*rs;
+				c = tmp;
+			}
+		}
+	}
+}
+
+static inline void fft(float *re, float *im, int n)
+{
+	_fft(re, im, n, -1);
+}
+
+// Note: this scales by 1/n, so ifft(fft(x)) == x
+static inline void ifft(float *re, float *im, int n)
+{
+	float scale = 1.0f / n;
+
+	_fft(re, im, n, 1);
+	for (int i = 0; i < n; i++) {
+		re[i] *= scale;
+		im[i] *= scale;
+	}
+}
+
+// Smallest power of two that is at least 'n'
+static inline int fft_size(int n)
+{
+	int size = 1;
+
+	while (size < n)
+		size <<= 1;
+	return size;
+}
+
+//
+// Window functions. The periodic versions, which is what
+// you want for spectral analysis and overlap-add.
+//
+static inline void window_hann(float *w, int n)
+{
+	for (int i = 0; i < n; i++)
+		w[i] = (float) (0.5 - 0.5*cos(2*M_PI*i/n));
+}
+
+//
+// Magnitude spectrum of one frame of 'n' samples, using 'win'
+// as the window. 're' and 'im' are the scratch space for the
+// transform, and on return re[0 .. n/2-1] holds the magnitudes
+// of the bins from DC up to just under Nyquist.
+//
+static inline void magnitude_spectrum(const float *x, const float *win, float *re, float *im, int n)
+{
+	for (int i = 0; i < n; i++) {
+		re[i] = x[i] * win[i];
+		im[i] = 0;
+	}
+	fft(re, im, n);
+
+	for (int k = 0; k < n/2; k++)
+		re[k] = sqrtf(re[k]*re[k] + im[k]*im[k]);
+}
+
+//
+// Real cepstrum: the inverse transform of the log magnitude
+// spectrum. Echoes and pitch periods show up as peaks at their
+// delay ("quefrency"), which is where the name games come from.
+//
+// 'n' has to be a power of two, and 'x' is windowed with a Hann
+// window first. The result has 'n' entries, but only the first
+// half is interesting since it's symmetric.
+//
+static inline int real_cepstrum(const float *x, int n, float *out)
+{
+	float *re = malloc(n * sizeof(float)), *im = malloc(n * sizeof(float));
+	int ret = -1;
+
+	if (!re || !im)
+		goto out;
+
+	window_hann(re, n);
+	for (int i = 0; i < n; i++) {
+		re[i] *= x[i];
+		im[i] = 0;
+	}
+	fft(re, im, n);
+
+	// The tiny offset keeps digital silence from going to -inf
+	for (int i = 0; i < n; i++) {
+		re[i] = logf(sqrtf(re[i]*re[i] + im[i]*im[i]) + 1e-9f);
+		im[i] = 0;
+	}
+	ifft(re, im, n);
+
+	memcpy(out, re, n * sizeof(float));
+	ret = 0;
+out:
+	free(re);
+	free(im);
+	return ret;
+}
+
+//
+// Analytic signal using the FFT version of the Hilbert transform:
+// zero the negative frequencies, double the positive ones, and
+// transform back. The real part is the original signal, and the
+// imaginary part is the signal shifted by 90 degrees.
+//
+// What makes it useful is the polar form: the magnitude is the
+// instantaneous amplitude (a nice smooth envelope), and the
+// angle the instantaneous phase. Either 'amp' or 'phase' can be
+// NULL if you don't care about it.
+//
+// The signal is zero-padded to twice the length, so the ends
+// don't wrap around into each other, but the envelope still
+// droops a bit right at the edges.
+//
+static inline int analytic_signal(const float *x, int nr, float *amp, float *phase)
+{
+	int n = fft_size(2 * nr);
+	float *re = calloc(n, sizeof(float)), *im = calloc(n, sizeof(float));
+	int ret = -1;
+
+	if (!re || !im)
+		goto out;
+
+	memcpy(re, x, nr * sizeof(float));
+	fft(re, im, n);
+	for (int k = 1; k < n/2; k++) {
+		re[k] *= 2;
+		im[k] *= 2;
+	}
+	for (int k = n/2 + 1; k < n; k++)
+		re[k] = im[k] = 0;
+	ifft(re, im, n);
+
+	for (int i = 0; i < nr; i++) {
+		if (amp)
+			amp[i] = sqrtf(re[i]*re[i] + im[i]*im[i]);
+		if (phase)
+			phase[i] = atan2f(im[i], re[i]);
+	}
+	ret = 0;
+out:
+	free(re);
+	free(im);
+	return ret;
+}
@@ -0,0 +1,131 @@
+//
+// Higher order filter design: Butterworth, Chebyshev (type I)
+// and Linkwitz-Riley lowpass/highpass filters as a cascade of
+// biquads.
+//
+// This just gives the coefficients, so they can be used (or
+// looked at) without any effect state around them. The usual
+// analog prototypes, with the cutoff pre-warped so that it ends
+// up in the right place after the bilinear transform.
+//
+// For odd orders the last section is a first order one (with
+// b2 = a2 = 0). Linkwitz-Riley is two Butterworth filters of
+// half the order in series, so its order has to be even, and
+// the lowpass and highpass sum flat at the crossover.
+//
+#define FILTER_MAX_ORDER 16
+#define FILTER_MAX_SECTIONS (FILTER_MAX_ORDER / 2)
+
+struct filter_coeff {
+	int nr;
+	struct biquad_coeff bq[FILTER_MAX_SECTIONS];
+};
+
+//
+// Add a lowpass or highpass section for the normalized (1 rad/s)
+// prototype denominator 's^2 + a1*s + a2', or 's + a2' if it's
+// a first order one. The lowpass sections have unity DC gain.
+//
+static inline void filter_section(struct filter_coeff *f, int highpass, double wc,
+	int first_order, double a1, double a2)
+{
+	struct biquad_coeff *c = f->bq + f->nr++;
+
+	if (first_order) {
+		// a2/(s+a2), or s/(s+a2) for the highpass
+		double p = highpass ? wc / a2 : wc * a2;
+		_biquad_bilinear(c,
+			highpass ? (double[3]) { 0, 1, 0 } : (double[3]) { 0, 0, p },
+			(double[3]) { 0, 1, p });
+		return;
+	}
+
+	// Highpass: s -> wc/s, lowpass: s -> s/wc
+	if (highpass)
+		_biquad_bilinear(c, (double[3]) { 1, 0, 0 },
+			(double[3]) { 1, a1 / a2 * wc, wc * wc / a2 });
+	else
+		_biquad_bilinear(c, (double[3]) { 0, 0, a2 * wc * wc },
+			(double[3]) { 1, a1 * wc, a2 * wc * wc });
+}
+
+static inline double filter_prewarp(float fc)
+{
+	return 2 * SAMPLES_PER_SEC * tan(M_PI * fc / SAMPLES_PER_SEC);
+}
+
+//
+// Chebyshev type I with 'ripple' dB of passband ripple. The
+// poles are the Butterworth ones squished onto an ellipse, and
+// with zero ripple this *is* Butterworth.
+//
+static inline int chebyshev(struct filter_coeff *f, int highpass, float fc, int order, float ripple)
+{
+	double wc = filter_prewarp(fc);
+	double v = 0;
+
+	if (order < 1 || order > FILTER_MAX_ORDER || fc <= 0 || fc >= SAMPLES_PER_SEC / 2)
+		return -1;
+	if (ripple > 0)
+		v = asinh(1 / sqrt(pow(10, ripple / 10) - 1)) / order;
+
+	f->nr = 0;
+	for (int k = 0; k < order / 2; k++) {
+		double theta = (2*k + 1) * M_PI / (2*order);
+		double re = ripple > 0 ? sinh(v) * sin(theta) : sin(theta);
+		double im = ripple > 0 ? cosh(v) * cos(theta) : cos(theta);
+
+		filter_section(f, highpass, wc, 0, 2*re, re*re + im*im);
+	}
+	if (order & 1)
+		filter_section(f, highpass, wc, 1, 0, ripple > 0 ? sinh(v) : 1);
+
+	// Even orders start the passband at the bottom of the ripple
+	if (ripple > 0 && !(order & 1)) {
+		float gain = db_to_linear(-ripple);
+		f->bq[0].b0 *= gain;
+		f->bq[0].b1 *= gain;
+		f->bq[0].b2 *= gain;
+	}
+	return 0;
+}
+
+static inline int butterworth(struct filter_coeff *f, int highpass, float fc, int order)
+{
+	return chebyshev(f, highpass, fc, order, 0);
+}
+
+static inline int linkwitz_riley(struct filter_coeff *f, int highpass, float fc, int order)
+{
+	int half = order / 2;
+
+	if (order & 1 || butterworth(f, highpass, fc, half) < 0)
+		return -1;
+	memcpy(f->bq + f->nr, f->bq, f->nr * sizeof(f->bq[0]));
+	f->nr *= 2;
+	return 0;
+}
+
+#define butterworth_lowpass(f, fc, order) butterworth(f, 0, fc, order)
+#define butterworth_highpass(f, fc, order) butterworth(f, 1, fc, order)
+#define chebyshev_lowpass(f, fc, order, ripple) chebyshev(f, 0, fc, order, ripple)
+#define chebyshev_highpass(f, fc, order, ripple) chebyshev(f, 1, fc, order, ripple)
+#define linkwitz_riley_lowpass(f, fc, order) linkwitz_riley(f, 0, fc, order)
+#define linkwitz_riley_highpass(f, fc, order) linkwitz_riley(f, 1, fc, order)
+
+static inline float filter_response(struct filter_coeff *f, float freq)
+{
+	float gain = 1;
+
+	for (int i = 0; i < f->nr; i++)
+		gain *= biquad_response(&f->bq[i], freq);
+	return gain;
+}
+
+// Filter a buffer in place, 'state' has one entry per section
+static inline void filter_process(struct filter_coeff *f, struct biquad_state *state, float *x, int nr)
+{
+	for (int i = 0; i < f->nr; i++)
+		for (int j = 0; j < nr; j++)
+			x[j] = _biquad_step(&f->bq[i], &state[i], x[j]);
+}
@@ -0,0 +1,122 @@
+//
+// Windowed-sinc FIR filter design and FFT convolution
+//
+// Linear phase lowpass, highpass and bandpass filters of any
+// length, using a Blackman window (about -74dB stopband, with
+// a transition band of roughly 5.5 * SAMPLES_PER_SEC / taps).
+// The highpass and bandpass ones are made by subtracting
+// lowpass filters, so they need an odd number of taps to have
+// a center tap to subtract from.
+//
+// Long filters are way too slow to apply sample by sample, so
+// fft_convolve() does it block by block with overlap-add.
+//
+static inline void fir_lowpass(float *h, int taps, float fc)
+{
+	double f = fc / SAMPLES_PER_SEC, center = (taps - 1) / 2.0, sum = 0;
+
+	for (int i = 0; i < taps; i++) {
+		double t = i - center;
+		double x = taps > 1 ? 2 * M_PI * i / (taps - 1) : 0;
+		double w = 0.42 - 0.5*cos(x) + 0.08*cos(2*x);
+		double s = t ? sin(2*M_PI*f*t) / (M_PI*t) : 2*f;
+
+		h[i] = (float) (s * w);
+		sum += h[i];
+	}
+
+	// Unity gain at DC
+	for (int i = 0; i < taps; i++)
+		h[i] = (float) (h[i] / sum);
+}
+
+static inline int fir_highpass(float *h, int taps, float fc)
+{
+	if (!(taps & 1))
+		return -1;
+	fir_lowpass(h, taps, fc);
+	for (int i = 0; i < taps; i++)
+		h[i] = -h[i];
+	h[taps / 2] += 1;
+	return 0;
+}
+
+static inline int fir_bandpass(float *h, int taps, float f1, float f2)
+{
+	float *low = malloc(taps * sizeof(float));
+
+	if (!(taps & 1) || !low) {
+		free(low);
+		return -1;
+	}
+	fir_lowpass(h, taps, f2);
+	fir_lowpass(low, taps, f1);
+	for (int i = 0; i < taps; i++)
+		h[i] -= low[i];
+	free(low);
+	return 0;
+}
+
+// Magnitude response at 'freq', straight from the definition
+static inline float fir_response(const float *h, int taps, float freq)
+{
+	double w = 2 * M_PI * freq / SAMPLES_PER_SEC, re = 0, im = 0;
+
+	for (int i = 0; i < taps; i++) {
+		re += h[i] * cos(w * i);
+		im -= h[i] * sin(w * i);
+	}
+	return (float) sqrt(re*re + im*im);
+}
+
+//
+// Convolve 'x' with the filter 'h'. Returns a newly allocated
+// buffer of the full 'nr + taps - 1' result (so the output is
+// delayed by (taps-1)/2 samples for the linear phase filters
+// above), with the length in *out_nr.
+//
+static inline float *fft_convolve(const float *x, int nr, const float *h, int taps, int *out_nr)
+{
+	int n = fft_size(2 * taps), block = n - taps + 1;
+	int len = nr + taps - 1;
+	float *hre = calloc(n, sizeof(float)), *him = calloc(n, sizeof(float));
+	float *re = malloc(n * sizeof(float)), *im = malloc(n * sizeof(float));
+	float *out = calloc(len + 1, sizeof(float));
+
+	*out_nr = 0;
+	if (!hre || !him || !re || !im || !out) {
+		free(out);
+		out = NULL;
+		goto done;
+	}
+
+	memcpy(hre, h, taps * sizeof(float));
+	fft(hre, him, n);
+
+	for (int pos = 0; pos < nr; pos += block) {
+		int count = nr - pos < block ? nr - pos : block;
+
+		memset(re, 0, n * sizeof(float));
+		memset(im, 0, n * sizeof(float));
+		memcpy(re, x + pos, count * sizeof(float));
+		fft(re, im, n);
+		for (int i = 0; i < n; i++) {
+			float r = re[i]*hre[i] - im[i]*him[i];
+			im[i] = re[i]*him[i] + im[i]*hre[i];
+			re[i] = r;
+		}
+		ifft(re, im, n);
+
+		// Each block spreads out by 'taps - 1' samples
+		int end = count + taps - 1;
+		if (pos + end > len)
+			end = len - pos;
+		for (int i = 0; i < end; i++)
+			out[pos + i] += re[i];
+	}
+	*out_nr = len;
+done:
+	free(hre); free(him);
+	free(re); free(im);
+	return out;
+}
@@ -0,0 +1,142 @@
+//
+// Fingerprinting sample data: is this render bit-identical to
+// the last one, and if not, where does it start to differ?
+//
+// The hash is XXH64 (same values as the reference xxHash, with
+// a seed of zero on little-endian machines). It's not a crypto
+// hash, it's just fast and good at noticing changed bits.
+//
+// For finding where two renders diverge without having both of
+// them around there's also a list of per-block hashes: the first
+// block hash that differs says where to start looking.
+//
+#define HASH_BLOCK 4096		// samples per block hash
+
+#define XXH_PRIME1 0x9E3779B185EBCA87ull
+#define XXH_PRIME2 0xC2B2AE3D27D4EB4Full
+#define XXH_PRIME3 0x165667B19E3779F9ull
+#define XXH_PRIME4 0x85EBCA77C2B2AE63ull
+#define XXH_PRIME5 0x27D4EB2F165667C5ull
+
+static inline u64 xxh_rotl(u64 x, int r)
+{
+	return (x << r) | (x >> (64 - r));
+}
+
+static inline u64 xxh_read64(const unsigned char *p)
+{
+	u64 val;
+	memcpy(&val, p, sizeof(val));
+	return val;
+}
+
+static inline u32 xxh_read32(const unsigned char *p)
+{
+	u32 val;
+	memcpy(&val, p, sizeof(val));
+	return val;
+}
+
+static inline u64 xxh_round(u64 acc, u64 input)
+{
+	acc += input * XXH_PRIME2;
+	return xxh_rotl(acc, 31) * XXH_PRIME1;
+}
+
+static inline u64 xxh_merge(u64 acc, u64 val)
+{
+	acc ^= xxh_round(0, val);
+	return acc * XXH_PRIME1 + XXH_PRIME4;
+}
+
+static inline u64 xxh64(const void *data, size_t len, u64 seed)
+{
+	const unsigned char *p = data, *end = p + len;
+	u64 h;
+
+	if (len >= 32) {
+		u64 v1 = seed + XXH_PRIME1 + XXH_PRIME2;
+		u64 v2 = seed + XXH_PRIME2;
+		u64 v3 = seed;
+		u64 v4 = seed - XXH_PRIME1;
+
+		do {
+			v1 = xxh_round(v1, xxh_read64(p));
+			v2 = xxh_round(v2, xxh_read64(p + 8));
+			v3 = xxh_round(v3, xxh_read64(p + 16));
+			v4 = xxh_round(v4, xxh_read64(p + 24));
+			p += 32;
+		} while (p + 32 <= end);
+
+		h = xxh_rotl(v1, 1) + xxh_rotl(v2, 7) + xxh_rotl(v3, 12) + xxh_rotl(v4, 18);
+		h = xxh_merge(h, v1);
+		h = xxh_merge(h, v2);
+		h = xxh_merge(h, v3);
+		h = xxh_merge(h, v4);
+	} else {
+		h = seed + XXH_PRIME5;
+	}
+	h += len;
+
+	for (; p + 8 <= end; p += 8) {
+		h ^= xxh_round(0, xxh_read64(p));
+		h = xxh_rotl(h, 27) * XXH_PRIME1 + XXH_PRIME4;
+	}
+	if (p + 4 <= end) {
+		h ^= xxh_read32(p) * XXH_PRIME1;
+		h = xxh_rotl(h, 23) * XXH_PRIME2 + XXH_PRIME3;
+		p += 4;
+	}
+	for (; p < end; p++) {
+		h ^= *p * XXH_PRIME5;
+		h = xxh_rotl(h, 11) * XXH_PRIME1;
+	}
+
+	h ^= h >> 33;
+	h *= XXH_PRIME2;
+	h ^= h >> 29;
+	h *= XXH_PRIME3;
+	h ^= h >> 32;
+	return h;
+}
+
+static inline u64 hash_samples(const float *x, int nr)
+{
+	return xxh64(x, (size_t) nr * sizeof(float), 0);
+}
+
+// One hash per HASH_BLOCK samples (the last one may be short)
+static inline int hash_blocks(const float *x, int nr, u64 *out)
+{
+	int count = 0;
+
+	for (int pos = 0; pos < nr; pos += HASH_BLOCK) {
+		int len = nr - pos < HASH_BLOCK ? nr - pos : HASH_BLOCK;
+		out[count++] = hash_samples(x + pos, len);
+	}
+	return count;
+}
+
+#define hash_nr_blocks(nr) (((nr) + HASH_BLOCK - 1) / HASH_BLOCK)
+
+//
+// The first sample where 'a' and 'b' differ (bit for bit), or
+// -1 if they're identical. If one is a prefix of the other, the
+// first difference is where the shorter one ends.
+//
+static inline int first_difference(const float *a, int a_nr, const float *b, int b_nr)
+{
+	int nr = a_nr < b_nr ? a_nr : b_nr;
+
+	for (int pos = 0; pos < nr; pos += HASH_BLOCK) {
+		int len = nr - pos < HASH_BLOCK ? nr - pos : HASH_BLOCK;
+
+		if (!memcmp(a + pos, b + pos, len * sizeof(float)))
+			continue;
+		for (int i = 0; i < len; i++) {
+			if (memcmp(a + pos + i, b + pos + i, sizeof(float)))
+				return pos + i;
+		}
+	}
+	return a_nr == b_nr ? -1 : nr;
+}
@@ -0,0 +1,115 @@
+//
+// Integrated loudness (EBU R128 / ITU-R BS.1770) and normalization
+//
+// K-weight the signal, and look at the mean square in 400ms
+// blocks overlapping by 75%. Blocks quieter than -70 LUFS are
+// ignored entirely, and then so is anything more than 10LU
+// below the average of what's left. The loudness of the rest is
+//
+//	-0.691 + 10*log10(mean square)
+//
+// which is what makes a full scale 1kHz sine read -3 LUFS.
+//
+// This is the mono case: for stereo the channel energies just
+// add up (so the same signal in both channels reads 3dB louder).
+//
+#define LUFS_BLOCK 4			// 100ms sub-blocks per gating block
+#define LUFS_SUBBLOCK ((int) (SAMPLES_PER_SEC / 10))
+#define LUFS_ABSOLUTE_GATE -70
+#define LUFS_RELATIVE_GATE -10
+#define LUFS_SILENCE -200		// what digital silence reads
+
+static inline double lufs(double meansq)
+{
+	return meansq > 0 ? -0.691 + 10 * log10(meansq) : LUFS_SILENCE;
+}
+
+static inline float integrated_loudness(const float *x, int nr)
+{
+	int nr_sub = nr / LUFS_SUBBLOCK, nr_blocks = nr_sub - LUFS_BLOCK + 1;
+	double *sub, sum = 0, gate;
+	struct weighting k;
+	int count = 0;
+
+	if (nr_blocks <= 0)
+		return LUFS_SILENCE;
+	sub = calloc(nr_sub + nr_blocks, sizeof(double));
+	if (!sub)
+		return LUFS_SILENCE;
+
+	memset(&k, 0, sizeof(k));
+	weighting_k(&k);
+	for (int i = 0; i < nr_sub * LUFS_SUBBLOCK; i++) {
+		float val = weighting_step(&k, x[i]);
+		sub[i / LUFS_SUBBLOCK] += (double) val * val;
+	}
+
+	// Mean square of each 400ms block
+	double *block = sub + nr_sub;
+	for (int b = 0; b < nr_blocks; b++) {
+		for (int j = 0; j < LUFS_BLOCK; j++)
+			block[b] += sub[b + j];
+		block[b] /= LUFS_BLOCK * LUFS_SUBBLOCK;
+	}
+
+	for (int b = 0; b < nr_blocks; b++) {
+		if (lufs(block[b]) > LUFS_ABSOLUTE_GATE) {
+			sum += block[b];
+			count++;
+		}
+	}
+	if (!count)
+		goto out;
+
+	gate = lufs(sum / count) + LUFS_RELATIVE_GATE;
+	sum = 0;
+	count = 0;
+	for (int b = 0; b < nr_blocks; b++) {
+		double l = lufs(block[b]);
+		if (l > LUFS_ABSOLUTE_GATE && l > gate) {
+			sum += block[b];
+			count++;
+		}
+	}
+out:
+	free(sub);
+	return (float) (count ? lufs(sum / count) : LUFS_SILENCE);
+}
+
+//
+// Normalization: these scale the samples in place, and return
+// the gain (in dB) that they applied. Silence is left alone.
+//
+static inline float normalize_to_peak(float *x, int nr, float target_db)
+{
+	float gain, level = peak(x, nr);
+
+	if (!level)
+		return 0;
+	gain = target_db - linear_to_db(level, LUFS_SILENCE);
+	apply_gain_db(x, nr, gain);
+	return gain;
+}
+
+// Plain RMS, for quickly matching test captures to each other
+static inline float normalize_to_rms(float *x, int nr, float target_db)
+{
+	float gain, level = (float) rms(x, nr);
+
+	if (!level)
+		return 0;
+	gain = target_db - linear_to_db(level, LUFS_SILENCE);
+	apply_gain_db(x, nr, gain);
+	return gain;
+}
+
+static inline float normalize_to_lufs(float *x, int nr, float target_lufs)
+{
+	float gain, level = integrated_loudness(x, nr);
+
+	if (level <= LUFS_SILENCE)
+		return 0;
+	gain = target_lufs - level;
+	apply_gain_db(x, nr, gain);
+	return gain;
+}
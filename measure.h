@@ -0,0 +1,603 @@
+//
+// Offline measurements on a whole buffer of samples
+//
+// Unlike the effects, these don't work a sample at a time:
+// they get the whole capture as a float array (already scaled
+// to the usual -1 .. 1 range) and just crunch through it.
+// Sums are done in double, because a few minutes of audio is
+// a lot of samples to accumulate in a float.
+//
+
+//
+// Least-squares fit of a sine wave of a known frequency
+// (plus a DC offset) to the samples. The sine/cosine basis
+// is generated by just rotating a unit vector, which is
+// plenty precise in double.
+//
+struct tone {
+	double freq;
+	double c, s, dc;
+};
+
+static inline double tone_amplitude(struct tone *t)
+{
+	return sqrt(t->c*t->c + t->s*t->s);
+}
+
+static inline void tone_fit(const float *x, int nr, double freq, struct tone *t)
+{
+	double w = 2*M_PI*freq/SAMPLES_PER_SEC;
+	double rc = cos(w), rs = sin(w);
+	double c = 1, s = 0;
+	double cc = 0, ss = 0, cs = 0, c1 = 0, s1 = 0;
+	double xc = 0, xs = 0, x1 = 0;
+
+	for (int i = 0; i < nr; i++) {
+		double v = x[i], tmp;
+
+		cc += c*c; ss += s*s; cs += c*s;
+		c1 += c; s1 += s;
+		xc += v*c; xs += v*s; x1 += v;
+
+		tmp = c*rc - s*rs;
+		s = s*rc + c*rs;
+		c = tmp;
+	}
+
+	// Solve the 3x3 normal equations with Cramer's rule
+	double m[3][3] = {
+		{ cc, cs, c1 },
+		{ cs, ss, s1 },
+		{ c1, s1, nr },
+	};
+	double det =	m[0][0]*(m[1][1]*m[2][2] - m[1][2]*m[2][1]) -
+			m[0][1]*(m[1][0]*m[2][2] - m[1][2]*m[2][0]) +
+			m[0][2]*(m[1][0]*m[2][1] - m[1][1]*m[2][0]);
+
+	t->freq = freq;
+	t->c = t->s = t->dc = 0;
+	if (!det)
+		return;
+
+	t->c = (xc*(m[1][1]*m[2][2] - m[1][2]*m[2][1]) -
+		m[0][1]*(xs*m[2][2] - m[1][2]*x1) +
+		m[0][2]*(xs*m[2][1] - m[1][1]*x1)) / det;
+	t->s = (m[0][0]*(xs*m[2][2] - m[1][2]*x1) -
+		xc*(m[1][0]*m[2][2] - m[1][2]*m[2][0]) +
+		m[0][2]*(m[1][0]*x1 - xs*m[2][0])) / det;
+	t->dc = (m[0][0]*(m[1][1]*x1 - xs*m[2][1]) -
+		m[0][1]*(m[1][0]*x1 - xs*m[2][0]) +
+		xc*(m[1][0]*m[2][1] - m[1][1]*m[2][0])) / det;
+}
+
+static inline void tone_subtract(float *x, int nr, struct tone *t)
+{
+	double w = 2*M_PI*t->freq/SAMPLES_PER_SEC;
+	double rc = cos(w), rs = sin(w);
+	double c = 1, s = 0;
+
+	for (int i = 0; i < nr; i++) {
+		double tmp;
+
+		x[i] -= (float) (t->c*c + t->s*s + t->dc);
+
+		tmp = c*rc - s*rs;
+		s = s*rc + c*rs;
+		c = tmp;
+	}
+}
+
+//
+// The hot loops for scanning big captures. These use the gcc
+// vector extensions rather than any particular instruction set,
+// so the compiler can turn them into SSE/AVX/NEON or whatever
+// the host has (and plain scalar code if it has nothing).
+//
+// The sums are done in double lanes, so they're exactly as good
+// as the plain double loop they replace, however long the file.
+//
+typedef float v4sf __attribute__((vector_size(16)));
+typedef int v4si __attribute__((vector_size(16)));
+typedef double v4df __attribute__((vector_size(32)));
+
+static inline v4sf v4_load(const float *x)
+{
+	v4sf v;
+	memcpy(&v, x, sizeof(v));
+	return v;
+}
+
+// Lane-wise 'mask ? a : b' (C doesn't do '?:' on vectors)
+static inline v4sf v4_select(v4si mask, v4sf a, v4sf b)
+{
+	return (v4sf) (((v4si) a & mask) | ((v4si) b & ~mask));
+}
+
+// Two accumulators, so the adds don't wait on each other
+static inline double rms(const float *x, int nr)
+{
+	v4df acc = { 0, }, acc2 = { 0, };
+	double sum = 0;
+	int i;
+
+	for (i = 0; i + 8 <= nr; i += 8) {
+		v4df v = __builtin_convertvector(v4_load(x + i), v4df);
+		v4df w = __builtin_convertvector(v4_load(x + i + 4), v4df);
+		acc += v * v;
+		acc2 += w * w;
+	}
+	acc += acc2;
+	for (int j = 0; j < 4; j++)
+		sum += acc[j];
+	for (; i < nr; i++)
+		sum += (double) x[i] * x[i];
+	return nr ? sqrt(sum / nr) : 0;
+}
+
+// The sum and the sum of squares, for means and RMS levels
+static inline void sum_squares(const float *x, int nr, double *sump, double *squaresp)
+{
+	v4df acc = { 0, }, acc2 = { 0, };
+	double sum = 0, squares = 0;
+	int i;
+
+	for (i = 0; i + 4 <= nr; i += 4) {
+		v4df v = __builtin_convertvector(v4_load(x + i), v4df);
+		acc += v;
+		acc2 += v * v;
+	}
+	for (int j = 0; j < 4; j++) {
+		sum += acc[j];
+		squares += acc2[j];
+	}
+	for (; i < nr; i++) {
+		sum += x[i];
+		squares += (double) x[i] * x[i];
+	}
+	*sump = sum;
+	*squaresp = squares;
+}
+
+static inline void minmax(const float *x, int nr, float *minp, float *maxp)
+{
+	float min = nr ? x[0] : 0, max = min;
+	int i = 0;
+
+	if (nr >= 4) {
+		v4sf vmin = v4_load(x), vmax = vmin;
+
+		for (i = 4; i + 4 <= nr; i += 4) {
+			v4sf v = v4_load(x + i);
+			vmin = v4_select(v < vmin, v, vmin);
+			vmax = v4_select(v > vmax, v, vmax);
+		}
+		for (int j = 0; j < 4; j++) {
+			min = fminf(min, vmin[j]);
+			max = fmaxf(max, vmax[j]);
+		}
+	}
+	for (; i < nr; i++) {
+		min = fminf(min, x[i]);
+		max = fmaxf(max, x[i]);
+	}
+	*minp = min;
+	*maxp = max;
+}
+
+static inline float peak(const float *x, int nr)
+{
+	float min, max;
+
+	minmax(x, nr, &min, &max);
+	return fmaxf(-min, max);
+}
+
+//
+// Double precision versions, for when the samples come in as
+// doubles (or straight from the s32 data) and the float step
+// would throw away the low bits of a full 32-bit sample.
+//
+static inline double s32_to_f64(s32 sample)
+{
+	return sample * (1.0 / 2147483648.0);
+}
+
+static inline double rms_f64(const double *x, int nr)
+{
+	double sum = 0;
+
+	for (int i = 0; i < nr; i++)
+		sum += x[i] * x[i];
+	return nr ? sqrt(sum / nr) : 0;
+}
+
+static inline double peak_f64(const double *x, int nr)
+{
+	double peak = 0;
+
+	for (int i = 0; i < nr; i++)
+		peak = fmax(peak, fabs(x[i]));
+	return peak;
+}
+
+// dBFS with a floor, like linear_to_db()
+static inline double dbfs_f64(double val, double floor_db)
+{
+	val = fabs(val);
+	if (val <= pow(10, floor_db / 20))
+		return floor_db;
+	return 20 * log10(val);
+}
+
+//
+// Level envelopes: the RMS or peak of 'window' samples every
+// 'hop' samples. 'win' is NULL for a rectangular window, or
+// the 'window' weights (eg from window_hann()) for a smoother
+// curve when the frames overlap. The weighted peak is of the
+// windowed signal, so it reads low near the frame edges.
+//
+// Both fill in up to 'max' frames, and return how many.
+//
+static inline int windowed_frames(int nr, int window, int hop)
+{
+	if (window <= 0 || hop <= 0 || nr < window)
+		return 0;
+	return (nr - window) / hop + 1;
+}
+
+static inline int windowed_rms(const float *x, int nr, int window, int hop,
+	const float *win, float *out, int max)
+{
+	int frames = windowed_frames(nr, window, hop);
+	double norm = window;
+
+	if (win) {
+		norm = 0;
+		for (int j = 0; j < window; j++)
+			norm += win[j];
+	}
+	if (frames > max)
+		frames = max;
+
+	for (int f = 0; f < frames; f++) {
+		const float *frame = x + f * hop;
+		double sum = 0;
+
+		for (int j = 0; j < window; j++)
+			sum += (double) frame[j] * frame[j] * (win ? win[j] : 1);
+		out[f] = (float) sqrt(sum / norm);
+	}
+	return frames;
+}
+
+static inline int windowed_peak(const float *x, int nr, int window, int hop,
+	const float *win, float *out, int max)
+{
+	int frames = windowed_frames(nr, window, hop);
+
+	if (frames > max)
+		frames = max;
+
+	for (int f = 0; f < frames; f++) {
+		const float *frame = x + f * hop;
+		float peak = 0;
+
+		for (int j = 0; j < window; j++)
+			peak = fmaxf(peak, fabsf(frame[j] * (win ? win[j] : 1)));
+		out[f] = peak;
+	}
+	return frames;
+}
+
+//
+// One-pass summary statistics. Note that the s32 full scale
+// values both turn into exactly +-1.0 as floats, so counting
+// clipped samples is just comparing against that.
+//
+struct stats {
+	int nr, clipped;
+	float min, max, peak;
+	double rms, dc, crest;
+};
+
+static inline void measure_stats(const float *x, int nr, struct stats *st)
+{
+	double sum = 0, sum2 = 0;
+	int i = 0;
+
+	memset(st, 0, sizeof(*st));
+	st->nr = nr;
+	if (!nr)
+		return;
+
+	// Everything in one pass, so a big capture is only read once
+	st->min = st->max = x[0];
+	if (nr >= 4) {
+		v4sf vmin = v4_load(x), vmax = vmin;
+		v4df acc = { 0, }, acc2 = { 0, };
+		v4si clipped = { 0, };
+
+		for (; i + 4 <= nr; i += 4) {
+			v4sf v = v4_load(x + i);
+			v4df d = __builtin_convertvector(v, v4df);
+
+			vmin = v4_select(v < vmin, v, vmin);
+			vmax = v4_select(v > vmax, v, vmax);
+			clipped -= (v >= 1.0f) | (v <= -1.0f);
+			acc += d;
+			acc2 += d * d;
+		}
+		for (int j = 0; j < 4; j++) {
+			st->min = fminf(st->min, vmin[j]);
+			st->max = fmaxf(st->max, vmax[j]);
+			st->clipped += clipped[j];
+			sum += acc[j];
+			sum2 += acc2[j];
+		}
+	}
+	for (; i < nr; i++) {
+		float v = x[i];
+
+		if (v < st->min)
+			st->min = v;
+		if (v > st->max)
+			st->max = v;
+		if (fabsf(v) >= 1)
+			st->clipped++;
+		sum += v;
+		sum2 += (double) v * v;
+	}
+
+	st->peak = fmaxf(-st->min, st->max);
+	st->dc = sum / nr;
+	st->rms = sqrt(sum2 / nr);
+	st->crest = st->rms ? st->peak / st->rms : 0;
+}
+
+//
+// DC offset is just the mean. Removing it can either subtract
+// that mean, or (if 'cutoff' is non-zero) run the classic
+// one-pole DC blocker highpass over the samples, which also
+// gets rid of any slow drift in the offset.
+//
+static inline double dc_offset(const float *x, int nr)
+{
+	double sum = 0;
+
+	for (int i = 0; i < nr; i++)
+		sum += x[i];
+	return nr ? sum / nr : 0;
+}
+
+static inline void remove_dc(float *x, int nr, double cutoff)
+{
+	if (nr <= 0)
+		return;
+	if (!cutoff) {
+		float dc = (float) dc_offset(x, nr);
+		for (int i = 0; i < nr; i++)
+			x[i] -= dc;
+		return;
+	}
+
+	double R = exp(-2*M_PI*cutoff/SAMPLES_PER_SEC);
+	double prev = x[0], y = 0;
+	for (int i = 0; i < nr; i++) {
+		double in = x[i];
+		y = in - prev + R*y;
+		prev = in;
+		x[i] = (float) y;
+	}
+}
+
+//
+// Zero crossings. A crossing at index 'i' means that x[i-1]
+// and x[i] are on different sides of zero (with zero itself
+// counting as positive), so 'i' is the first sample after
+// the crossing and is what you want to snap a loop point or
+// a selection edge to.
+//
+static inline int is_zero_crossing(const float *x, int i)
+{
+	return (x[i-1] < 0) != (x[i] < 0);
+}
+
+static inline int count_zero_crossings(const float *x, int nr)
+{
+	int count = 0;
+
+	for (int i = 1; i < nr; i++)
+		count += is_zero_crossing(x, i);
+	return count;
+}
+
+// Crossings per second
+static inline double zero_crossing_rate(const float *x, int nr)
+{
+	if (nr < 2)
+		return 0;
+	return count_zero_crossings(x, nr) * SAMPLES_PER_SEC / (nr - 1);
+}
+
+//
+// Find the next zero crossing at or after 'from' (direction > 0)
+// or at or before it (direction < 0). Returns -1 if there is none.
+//
+static inline int next_zero_crossing(const float *x, int nr, int from, int direction)
+{
+	int step = direction < 0 ? -1 : 1;
+
+	if (from < 1)
+		from = 1;
+	if (direction < 0 && from > nr - 1)
+		from = nr - 1;
+	for (int i = from; i > 0 && i < nr; i += step) {
+		if (is_zero_crossing(x, i))
+			return i;
+	}
+	return -1;
+}
+
+//
+// Amplitude histogram. 'edges' gets bins+1 entries, and
+// 'counts' gets one per bin.
+//
+// The linear version spans the whole -1 .. 1 range. The dB
+// version looks at the absolute value, from HISTOGRAM_DB_FLOOR
+// up to 0dBFS, and anything quieter than the floor (including
+// digital silence) ends up in the first bin.
+//
+#define HISTOGRAM_DB_FLOOR -120
+
+static inline void measure_histogram(const float *x, int nr, int bins, int db_scale,
+	float *edges, int *counts)
+{
+	float lo = db_scale ? HISTOGRAM_DB_FLOOR : -1;
+	float hi = db_scale ? 0 : 1;
+	float scale = bins / (hi - lo);
+
+	for (int i = 0; i <= bins; i++)
+		edges[i] = lo + (hi - lo) * i / bins;
+	memset(counts, 0, bins * sizeof(int));
+
+	for (int i = 0; i < nr; i++) {
+		float v = x[i];
+		int bin;
+
+		if (db_scale)
+			v = linear_to_db(v, lo);
+		bin = (int) ((v - lo) * scale);
+		if (bin < 0)
+			bin = 0;
+		if (bin >= bins)
+			bin = bins - 1;
+		counts[bin]++;
+	}
+}
+
+//
+// Sample ranges [start, end) for the detectors below
+//
+struct range {
+	int start, end;
+};
+
+//
+// Find runs of at least 'min_len' samples whose absolute
+// value is below 'level' (or at/above it, if 'above' is set).
+// Fills in at most 'max' ranges and returns how many it found.
+//
+static inline int find_runs(const float *x, int nr, float level, int above,
+	int min_len, struct range *ranges, int max)
+{
+	int count = 0, start = -1;
+
+	for (int i = 0; i <= nr && count < max; i++) {
+		int in_run = i < nr && ((fabsf(x[i]) >= level) == !!above);
+
+		if (in_run) {
+			if (start < 0)
+				start = i;
+			continue;
+		}
+		if (start >= 0 && i - start >= min_len) {
+			ranges[count].start = start;
+			ranges[count].end = i;
+			count++;
+		}
+		start = -1;
+	}
+	return count;
+}
+
+static inline int detect_silence(const float *x, int nr, float threshold_db,
+	int min_len, struct range *ranges, int max)
+{
+	float level = db_to_linear(threshold_db);
+
+	return find_runs(x, nr, level, 0, min_len, ranges, max);
+}
+
+//
+// Clipping shows up as runs of consecutive samples stuck at
+// (or very near) full scale. A single full-scale sample is
+// usually just a hot peak, so the caller decides how long a
+// run has to be before it counts.
+//
+static inline int detect_clipping(const float *x, int nr, float level_db,
+	int min_len, struct range *ranges, int max)
+{
+	float level = db_to_linear(level_db);
+
+	return find_runs(x, nr, level, 1, min_len, ranges, max);
+}
+
+//
+// Harmonic distortion of a test tone with a known fundamental.
+//
+// We fit (and remove) the fundamental, and whatever is left is
+// "distortion and noise". Then we keep fitting and removing the
+// harmonics below Nyquist, and what is left after that is just
+// the noise.
+//
+// All the results are RMS values, so that they can be directly
+// compared with each other.
+//
+#define MAX_HARMONICS 10
+
+struct thd {
+	double fundamental;	// RMS of the fundamental
+	double harmonics;	// RMS of harmonics 2..N
+	double residual;	// RMS of everything but the fundamental
+	double noise;		// RMS of what is left after harmonics
+	int nr_harmonics;
+};
+
+static inline void measure_thd(const float *samples, int nr, double freq, struct thd *res)
+{
+	float *x = malloc(nr * sizeof(float));
+	double sum = 0;
+	struct tone t;
+
+	memset(res, 0, sizeof(*res));
+	if (!x)
+		return;
+	memcpy(x, samples, nr * sizeof(float));
+
+	tone_fit(x, nr, freq, &t);
+	tone_subtract(x, nr, &t);
+	res->fundamental = tone_amplitude(&t) / M_SQRT2;
+	res->residual = rms(x, nr);
+
+	for (int k = 2; k <= MAX_HARMONICS; k++) {
+		double a;
+
+		if (k * freq >= SAMPLES_PER_SEC / 2)
+			break;
+		tone_fit(x, nr, k * freq, &t);
+		tone_subtract(x, nr, &t);
+		a = tone_amplitude(&t);
+		sum += a*a / 2;
+		res->nr_harmonics = k - 1;
+	}
+	res->harmonics = sqrt(sum);
+	res->noise = rms(x, nr);
+	free(x);
+}
+
+//
+// Signal-to-noise ignores the harmonics entirely, while SINAD
+// counts them as part of the noise. SINAD is the number that
+// converter people care about, since it gives the "effective
+// number of bits".
+//
+static inline double thd_snr(struct thd *t)
+{
+	return t->fundamental / t->noise;
+}
+
+static inline double thd_sinad(struct thd *t)
+{
+	double f = t->fundamental, r = t->residual;
+	return sqrt(f*f + r*r) / r;
+}
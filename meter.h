@@ -0,0 +1,191 @@
+//
+// Streaming level meters with attack/release ballistics
+//
+// These follow the signal a sample at a time (or a block at
+// a time), unlike the offline measurements that look at the
+// whole capture at once. The attack time is how quickly the
+// meter rises to a louder signal, the release how quickly it
+// falls back down.
+//
+// Both are simple one-pole smoothers, using a different time
+// constant depending on whether the level is going up or down.
+// The peak meter smooths the absolute value, the RMS meter the
+// squared signal (and returns the square root of that).
+//
+struct meter {
+	float attack, release;
+	float level;
+};
+
+// One-pole coefficient for a time constant in ms (0 means "instant")
+static inline float meter_coeff(float ms)
+{
+	if (ms <= 0)
+		return 1;
+	return 1 - expf((float) (-1000 / (ms * SAMPLES_PER_SEC)));
+}
+
+static inline void meter_set(struct meter *m, float attack_ms, float release_ms)
+{
+	m->attack = meter_coeff(attack_ms);
+	m->release = meter_coeff(release_ms);
+}
+
+static inline void meter_update(struct meter *m, float val)
+{
+	float coeff = val > m->level ? m->attack : m->release;
+	m->level += coeff * (val - m->level);
+}
+
+static inline float peak_meter_step(struct meter *m, float in)
+{
+	meter_update(m, fabsf(in));
+	return m->level;
+}
+
+static inline float rms_meter_step(struct meter *m, float in)
+{
+	meter_update(m, in * in);
+	return sqrtf(m->level);
+}
+
+static inline float peak_meter_block(struct meter *m, const float *x, int nr)
+{
+	for (int i = 0; i < nr; i++)
+		meter_update(m, fabsf(x[i]));
+	return m->level;
+}
+
+static inline float rms_meter_block(struct meter *m, const float *x, int nr)
+{
+	for (int i = 0; i < nr; i++)
+		meter_update(m, x[i] * x[i]);
+	return sqrtf(m->level);
+}
+
+//
+// Helper "effects" that output the meter reading instead of
+// the audio, so that the ballistics can be looked at with the
+// visualizer. Note that convert's noise gate (see process.h) is
+// still in front of them, so the first 100ms or so, and anything
+// much quieter than -60dBFS, reads low.
+//
+//	pot[0]: attack time, 0 .. 100ms
+//	pot[1]: release time, 0 .. 2s
+//
+static struct meter peakmeter, rmsmeter;
+
+static inline void meter_describe(float pot[4])
+{
+	fprintf(stderr, " attack=%g ms", cubic(pot[0], 0, 100));
+	fprintf(stderr, " release=%g ms\n", cubic(pot[1], 0, 2000));
+}
+
+static inline void peakmeter_describe(float pot[4]) { meter_describe(pot); }
+static inline void peakmeter_init(float pot[4])
+{
+	meter_set(&peakmeter, cubic(pot[0], 0, 100), cubic(pot[1], 0, 2000));
+}
+static inline float peakmeter_step(float in) { return peak_meter_step(&peakmeter, in); }
+
+static inline void rmsmeter_describe(float pot[4]) { meter_describe(pot); }
+static inline void rmsmeter_init(float pot[4])
+{
+	meter_set(&rmsmeter, cubic(pot[0], 0, 100), cubic(pot[1], 0, 2000));
+}
+static inline float rmsmeter_step(float in) { return rms_meter_step(&rmsmeter, in); }
+
+//
+// VU meter: the full-wave rectified signal through a second
+// order mechanical-ish system that reaches 99% of a step in
+// 300ms with about 1.3% overshoot (f0 ~ 2.13Hz, damping 0.81).
+//
+// That is just a tiny bit of differential equation, integrated
+// a sample at a time. A 2Hz biquad would be hopeless in float
+// at 48kHz, but this form doesn't care.
+//
+// The rectified average is scaled so that a sine wave reads its
+// RMS value, and 0VU is at -18dBFS (the EBU alignment level).
+//
+#define VU_F0 2.13
+#define VU_DAMPING 0.81
+#define VU_REFERENCE_DBFS -18
+
+struct vu_meter {
+	float pos, speed;
+};
+
+static inline float vu_meter_step(struct vu_meter *m, float in)
+{
+	const float w = (float) (2*M_PI*VU_F0);
+	const float dt = (float) (1 / SAMPLES_PER_SEC);
+	float rect = fabsf(in) * (float) (M_PI / (2*M_SQRT2));
+
+	m->speed += dt * (w*w*(rect - m->pos) - 2*VU_DAMPING*w*m->speed);
+	m->pos += dt * m->speed;
+	return m->pos;
+}
+
+static inline float vu_reading(float level)
+{
+	return linear_to_db(level, -100) - VU_REFERENCE_DBFS;
+}
+
+//
+// Quasi-peak programme meters (IEC 60268-10)
+//
+// Type I (the German DIN meter): 5ms integration time, falls
+// back 20dB in 1.5s. Type II (BBC/EBU): 10ms integration time,
+// falls back 24dB in 2.8s.
+//
+// The attack is a one-pole peak follower on the rectified signal,
+// with the time constant picked so that a 5kHz tone burst of the
+// integration time reads 2dB under steady state. The fall-back
+// is linear in dB, ie a constant multiplier per sample.
+//
+struct ppm_meter {
+	float attack, release;
+	float level;
+};
+
+static inline void ppm_set(struct ppm_meter *m, int type)
+{
+	// Attack time constant (ms), fall-back dB and time (s)
+	float tau = type == 1 ? 1.3f : 2.5f;
+	float db = type == 1 ? 20 : 24;
+	float secs = type == 1 ? 1.5f : 2.8f;
+
+	m->attack = meter_coeff(tau);
+	m->release = db_to_linear((float) (-db / (secs * SAMPLES_PER_SEC)));
+}
+
+static inline float ppm_meter_step(struct ppm_meter *m, float in)
+{
+	float rect = fabsf(in);
+
+	if (rect > m->level)
+		m->level += m->attack * (rect - m->level);
+	else
+		m->level *= m->release;
+	return m->level;
+}
+
+//
+// And the helper "effects" for those:
+//
+//	vumeter: no pots
+//	ppm: pot[0] < 0.5 is type I, otherwise type II
+//
+static struct vu_meter vumeter;
+static struct ppm_meter ppm;
+
+static inline void vumeter_describe(float pot[4]) { fprintf(stderr, "\n"); }
+static inline void vumeter_init(float pot[4]) { }
+static inline float vumeter_step(float in) { return vu_meter_step(&vumeter, in); }
+
+static inline void ppm_describe(float pot[4])
+{
+	fprintf(stderr, " type=%s\n", pot[0] < 0.5 ? "I" : "II");
+}
+static inline void ppm_init(float pot[4]) { ppm_set(&ppm, pot[0] < 0.5 ? 1 : 2); }
+static inline float ppm_step(float in) { return ppm_meter_step(&ppm, in); }
@@ -0,0 +1,155 @@
+//
+// Network audio sources: raw samples from a socket, so that a
+// capture box somewhere else can be listened to (or analyzed)
+// without copying files around first.
+//
+//	tcp://host:port		connect to a server that sends samples
+//	tcp://:port		wait for one connection, and read that
+//	udp://:port		samples in datagrams sent to this port
+//	udp://addr:port		.. only on the local address 'addr'
+//	http://host[:port]/path	a plain HTTP GET of a raw stream
+//
+// All of them just give back a file descriptor to read() the
+// samples from, same as a pipe. The format is whatever the other
+// end sends - there's no header to tell us, so use '--format'
+// and friends as usual.
+//
+// UDP has no idea about frames, and read() on a datagram socket
+// throws away whatever doesn't fit, so datagrams need to be
+// smaller than AUDIO_STREAM_BUF and lost ones are just gone.
+//
+static inline int is_url(const char *name)
+{
+	return strstr(name, "://") != NULL;
+}
+
+// Split "host:port/path" into its parts, host may be empty
+static inline int net_split(const char *s, char *host, int hostlen, char *port, int portlen, const char **path)
+{
+	const char *colon = strchr(s, ':'), *slash = strchr(s, '/');
+	const char *host_end = colon && (!slash || colon < slash) ? colon : slash ? slash : s + strlen(s);
+	int len = host_end - s;
+
+	if (len >= hostlen)
+		return -1;
+	memcpy(host, s, len);
+	host[len] = 0;
+
+	port[0] = 0;
+	if (*host_end == ':') {
+		const char *p = host_end + 1;
+		len = slash ? slash - p : (int) strlen(p);
+		if (!len || len >= portlen)
+			return -1;
+		memcpy(port, p, len);
+		port[len] = 0;
+	}
+	*path = slash ? slash : "/";
+	return 0;
+}
+
+static inline int net_socket(const char *host, const char *port, int type, int server)
+{
+	struct addrinfo hints = { 0 }, *res, *ai;
+	int fd = -1;
+
+	hints.ai_family = AF_UNSPEC;
+	hints.ai_socktype = type;
+	hints.ai_flags = server ? AI_PASSIVE : 0;
+	if (getaddrinfo(*host ? host : NULL, port, &hints, &res))
+		return -1;
+
+	for (ai = res; ai; ai = ai->ai_next) {
+		fd = socket(ai->ai_family, ai->ai_socktype, ai->ai_protocol);
+		if (fd < 0)
+			continue;
+		if (server) {
+			int one = 1;
+			setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &one, sizeof(one));
+			if (!bind(fd, ai->ai_addr, ai->ai_addrlen))
+				break;
+		} else if (!connect(fd, ai->ai_addr, ai->ai_addrlen)) {
+			break;
+		}
+		close(fd);
+		fd = -1;
+	}
+	freeaddrinfo(res);
+	return fd;
+}
+
+//
+// Send a GET and skip the response headers. This reads a byte at
+// a time so that none of the samples after the headers get lost.
+//
+static inline int http_get(int fd, const char *host, const char *path)
+{
+	char buf[1024];
+	int len = snprintf(buf, sizeof(buf),
+		"GET %s HTTP/1.0\r\nHost: %s\r\nUser-Agent: AudioNoise\r\n\r\n", path, host);
+	int status = 0, line = 0, n = 0;
+	char c;
+
+	if (len >= sizeof(buf) || write(fd, buf, len) != len)
+		return -1;
+
+	while (read(fd, &c, 1) == 1) {
+		if (c == '\r')
+			continue;
+		if (c != '\n') {
+			if (n < sizeof(buf) - 1)
+				buf[n++] = c;
+			continue;
+		}
+		buf[n] = 0;
+		if (!line++ && sscanf(buf, "HTTP/%*s %d", &status) != 1)
+			return -1;
+		if (!n)
+			return status == 200 ? 0 : -1;
+		n = 0;
+	}
+	return -1;
+}
+
+// Returns a file descriptor to read from, or -1
+static inline int net_open(const char *url)
+{
+	char host[256], port[32];
+	const char *path;
+	int fd;
+
+	if (!strncmp(url, "tcp://", 6)) {
+		if (net_split(url + 6, host, sizeof(host), port, sizeof(port), &path) < 0 || !*port)
+			return -1;
+		if (*host)
+			return net_socket(host, port, SOCK_STREAM, 0);
+
+		int sock = net_socket(host, port, SOCK_STREAM, 1);
+		if (sock < 0 || listen(sock, 1) < 0) {
+			if (sock >= 0)
+				close(sock);
+			return -1;
+		}
+		fd = accept(sock, NULL, NULL);
+		close(sock);
+		return fd;
+	}
+
+	if (!strncmp(url, "udp://", 6)) {
+		if (net_split(url + 6, host, sizeof(host), port, sizeof(port), &path) < 0 || !*port)
+			return -1;
+		return net_socket(host, port, SOCK_DGRAM, 1);
+	}
+
+	if (!strncmp(url, "http://", 7)) {
+		if (net_split(url + 7, host, sizeof(host), port, sizeof(port), &path) < 0 || !*host)
+			return -1;
+		fd = net_socket(host, *port ? port : "80", SOCK_STREAM, 0);
+		if (fd >= 0 && http_get(fd, host, path) < 0) {
+			close(fd);
+			return -1;
+		}
+		return fd;
+	}
+	return -1;
+}
@@ -0,0 +1,149 @@
+//
+// Noise generators: white, pink, brown and blue
+//
+// The random numbers come from a plain xorshift32, so the same
+// seed always gives the same noise. That's what you want for
+// test signals that need to be reproducible, and it's cheap
+// enough for the audio path.
+//
+// White is flat, pink falls 3dB/octave (equal energy per
+// octave), brown falls 6dB/octave (integrated white), and blue
+// rises 3dB/octave (differentiated pink). They are all scaled
+// to peak somewhere around +-1, but only white is strictly
+// limited to that.
+//
+enum noise_color {
+	noise_white,
+	noise_pink,
+	noise_brown,
+	noise_blue,
+};
+
+struct noise_gen {
+	u32 state;
+	float b[7];		// pink filter state
+	float brown, pink;	// last brown and pink values
+};
+
+static inline void noise_seed(struct noise_gen *n, u32 seed)
+{
+	memset(n, 0, sizeof(*n));
+
+	// xorshift gets stuck at zero
+	n->state = seed ? seed : 0x9e3779b9;
+}
+
+static inline u32 noise_random(struct noise_gen *n)
+{
+	u32 x = n->state;
+
+	x ^= x << 13;
+	x ^= x >> 17;
+	x ^= x << 5;
+	return n->state = x;
+}
+
+// Uniform -1 .. 1
+static inline float noise_white_step(struct noise_gen *n)
+{
+	return (float) (s32) noise_random(n) * (1.0f / 2147483648.0f);
+}
+
+//
+// Paul Kellet's "refined" pink filter: a sum of one-pole
+// lowpass filters that is within +-0.05dB of -3dB/octave
+// above ~10Hz at 44.1kHz (and close enough at 48kHz).
+//
+static inline float noise_pink_step(struct noise_gen *n)
+{
+	float white = noise_white_step(n);
+	float *b = n->b;
+	float pink;
+
+	b[0] = 0.99886f * b[0] + white * 0.0555179f;
+	b[1] = 0.99332f * b[1] + white * 0.0750759f;
+	b[2] = 0.96900f * b[2] + white * 0.1538520f;
+	b[3] = 0.86650f * b[3] + white * 0.3104856f;
+	b[4] = 0.55000f * b[4] + white * 0.5329522f;
+	b[5] = -0.7616f * b[5] - white * 0.0168980f;
+	pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362f;
+	b[6] = white * 0.115926f;
+
+	return n->pink = pink * 0.11f;
+}
+
+// Leaky integrator, so that it doesn't wander off to infinity
+static inline float noise_brown_step(struct noise_gen *n)
+{
+	float white = noise_white_step(n);
+
+	n->brown = (n->brown + 0.02f * white) / 1.02f;
+	return n->brown * 3.5f;
+}
+
+static inline float noise_blue_step(struct noise_gen *n)
+{
+	float prev = n->pink;
+
+	return (noise_pink_step(n) - prev) * 3;
+}
+
+static inline float noise_next(struct noise_gen *n, enum noise_color color)
+{
+	switch (color) {
+	case noise_pink:
+		return noise_pink_step(n);
+	case noise_brown:
+		return noise_brown_step(n);
+	case noise_blue:
+		return noise_blue_step(n);
+	default:
+		return noise_white_step(n);
+	}
+}
+
+static inline void noise_fill(struct noise_gen *n, enum noise_color color, float *buf, int nr)
+{
+	for (int i = 0; i < nr; i++)
+		buf[i] = noise_next(n, color);
+}
+
+//
+// Helper "effect" that adds noise to the input:
+//
+//	pot[0]: white, pink, brown or blue
+//	pot[1]: level, -90 .. 0dB
+//
+static struct {
+	struct noise_gen gen;
+	enum noise_color color;
+	float level;
+} noise;
+
+static const char *const noise_names[] = { "white", "pink", "brown", "blue" };
+
+static inline enum noise_color noise_pot_color(float pot)
+{
+	int color = (int) (pot * 4);
+
+	return color > noise_blue ? noise_blue : color;
+}
+
+static inline void noise_describe(float pot[4])
+{
+	fprintf(stderr, " color=%s", noise_names[noise_pot_color(pot[0])]);
+	fprintf(stderr, " level=%g dB\n", linear(pot[1], -90, 0));
+}
+
+static inline void noise_init(float pot[4])
+{
+	if (!noise.gen.state)
+		noise_seed(&noise.gen, 1);
+	noise.color = noise_pot_color(pot[0]);
+	noise.level = db_to_linear(linear(pot[1], -90, 0));
+}
+
+static inline float noise_step(float in)
+{
+	return in + noise.level * noise_next(&noise.gen, noise.color);
+}
@@ -0,0 +1,116 @@
+//
+// Overlap-add framework for frequency domain effects
+//
+// The effect only has to provide a function that gets the
+// spectrum of one frame (re[] and im[], 'size' bins of the full
+// complex FFT) and modifies it in place. Everything else - the
+// input buffering, windowing, FFT, inverse FFT and adding the
+// frames back together - is done here, one sample at a time,
+// so it fits the usual 'step()' model.
+//
+// Two flavors:
+//
+//  - plain OLA: Hann window on the way in, nothing on the way
+//    out. Fine for gentle filtering, but anything that makes a
+//    discontinuity at the frame edges will click.
+//
+//  - weighted OLA: sqrt-Hann on the way in *and* on the way out,
+//    so whatever the processing does gets faded in and out too.
+//    This is what you want for noise reduction or pitch shifting.
+//
+// The hop needs to divide the frame size, and be at most half
+// of it (a quarter is the usual choice for WOLA). The output is
+// delayed by 'size' samples.
+//
+typedef void (*ola_fn)(float *re, float *im, int size, void *data);
+
+struct ola {
+	int size, hop, count;
+	float *win, *syn;	// analysis and synthesis windows
+	float *in, *acc, *ready;
+	float *re, *im;
+	ola_fn process;
+	void *data;
+};
+
+static inline void ola_free(struct ola *o)
+{
+	free(o->win); free(o->syn);
+	free(o->in); free(o->acc); free(o->ready);
+	free(o->re); free(o->im);
+	memset(o, 0, sizeof(*o));
+}
+
+static inline int ola_init(struct ola *o, int size, int hop, int weighted, ola_fn process, void *data)
+{
+	memset(o, 0, sizeof(*o));
+	if (size & (size - 1) || hop <= 0 || size % hop || hop > size / 2)
+		return -1;
+
+	o->size = size;
+	o->hop = hop;
+	o->process = process;
+	o->data = data;
+	o->win = malloc(size * sizeof(float));
+	o->syn = malloc(size * sizeof(float));
+	o->in = calloc(size, sizeof(float));
+	o->acc = calloc(size, sizeof(float));
+	o->ready = calloc(hop, sizeof(float));
+	o->re = malloc(size * sizeof(float));
+	o->im = malloc(size * sizeof(float));
+	if (!o->win || !o->syn || !o->in || !o->acc || !o->ready || !o->re || !o->im) {
+		ola_free(o);
+		return -1;
+	}
+
+	window_hann(o->win, size);
+	for (int i = 0; i < size; i++) {
+		if (weighted)
+			o->win[i] = sqrtf(o->win[i]);
+		o->syn[i] = weighted ? o->win[i] : 1;
+	}
+
+	// Scale the synthesis window so that the overlapping
+	// frames add up to exactly unity
+	double sum = 0;
+	for (int i = 0; i < size; i += hop)
+		sum += o->win[i] * o->syn[i];
+	for (int i = 0; i < size; i++)
+		o->syn[i] = (float) (o->syn[i] / sum);
+	return 0;
+}
+
+static inline void ola_frame(struct ola *o)
+{
+	int size = o->size, hop = o->hop;
+
+	for (int i = 0; i < size; i++) {
+		o->re[i] = o->in[i] * o->win[i];
+		o->im[i] = 0;
+	}
+	fft(o->re, o->im, size);
+	if (o->process)
+		o->process(o->re, o->im, size, o->data);
+	ifft(o->re, o->im, size);
+
+	for (int i = 0; i < size; i++)
+		o->acc[i] += o->re[i] * o->syn[i];
+
+	// The first 'hop' samples won't get any more contributions
+	memcpy(o->ready, o->acc, hop * sizeof(float));
+	memmove(o->acc, o->acc + hop, (size - hop) * sizeof(float));
+	memset(o->acc + size - hop, 0, hop * sizeof(float));
+	memmove(o->in, o->in + hop, (size - hop) * sizeof(float));
+}
+
+static inline float ola_step(struct ola *o, float in)
+{
+	float out = o->ready[o->count];
+
+	o->in[o->size - o->hop + o->count] = in;
+	if (++o->count == o->hop) {
+		ola_frame(o);
+		o->count = 0;
+	}
+	return out;
+}
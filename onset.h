@@ -0,0 +1,202 @@
+//
+// Onset detection using spectral flux
+//
+// Take the (log-compressed) magnitude spectrum of overlapping
+// Hann-windowed frames, and sum up how much each bin increased
+// compared to the previous frame. Energy suddenly showing up
+// in many bins at once is a note or a hit starting.
+//
+// The flux envelope has one value per hop, and onsets are the
+// local peaks of that envelope that stick out enough above the
+// local average around them.
+//
+#define ONSET_FFT 1024
+#define ONSET_HOP 256
+#define ONSET_AVERAGE 8		// +- frames for the local average
+#define ONSET_MIN_GAP 12	// frames, 64ms at 48kHz
+#define ONSET_LOG_GAIN 100	// log(1 + gain*|X|) compression
+#define ONSET_MIN_FLUX 10	// ignore the tiny wobbles of steady tones
+
+static inline float *onset_envelope(const float *x, int nr, int *nr_frames)
+{
+	static float re[ONSET_FFT], im[ONSET_FFT], win[ONSET_FFT];
+	static float prev[ONSET_FFT/2];
+	int frames = nr < ONSET_FFT ? 0 : (nr - ONSET_FFT) / ONSET_HOP + 1;
+	float *flux = calloc(frames + 1, sizeof(float));
+
+	*nr_frames = 0;
+	if (!flux)
+		return NULL;
+
+	window_hann(win, ONSET_FFT);
+	memset(prev, 0, sizeof(prev));
+
+	for (int f = 0; f < frames; f++) {
+		const float *frame = x + f * ONSET_HOP;
+		float sum = 0;
+
+		magnitude_spectrum(frame, win, re, im, ONSET_FFT);
+		for (int k = 0; k < ONSET_FFT/2; k++) {
+			float val = logf(1 + ONSET_LOG_GAIN * re[k]);
+			float diff = val - prev[k];
+
+			if (diff > 0)
+				sum += diff;
+			prev[k] = val;
+		}
+
+		// The first frame has nothing to compare against
+		flux[f] = f ? sum : 0;
+	}
+
+	*nr_frames = frames;
+	return flux;
+}
+
+// Sample position of the middle of a flux frame
+static inline int onset_frame_to_sample(int frame)
+{
+	return frame * ONSET_HOP + ONSET_FFT / 2;
+}
+
+//
+// Fill in up to 'max' onset positions (in samples), and
+// return how many were found.
+//
+static inline int detect_onsets(const float *x, int nr, int *onsets, int max)
+{
+	int frames, count = 0, last = -ONSET_MIN_GAP;
+	float *flux = onset_envelope(x, nr, &frames);
+	float peak = 0;
+
+	if (!flux)
+		return 0;
+
+	for (int f = 0; f < frames; f++)
+		peak = fmaxf(peak, flux[f]);
+
+	for (int f = 1; f + 1 < frames && count < max; f++) {
+		float v = flux[f], sum = 0;
+		int n = 0;
+
+		if (v <= flux[f-1] || v < flux[f+1])
+			continue;
+		if (f - last < ONSET_MIN_GAP)
+			continue;
+
+		for (int i = f - ONSET_AVERAGE; i <= f + ONSET_AVERAGE; i++) {
+			if (i < 0 || i >= frames)
+				continue;
+			sum += flux[i];
+			n++;
+		}
+
+		// Needs to be well above the local average, and not
+		// just some tiny wiggle compared to the real hits
+		if (v < 1.5f * sum / n || v < 0.1f * peak || v < ONSET_MIN_FLUX)
+			continue;
+
+		onsets[count++] = onset_frame_to_sample(f);
+		last = f;
+	}
+
+	free(flux);
+	return count;
+}
+
+//
+// Tempo estimation from the onset envelope
+//
+// Autocorrelate the (mean-removed) flux envelope over the
+// lags that correspond to 60..200 BPM, weighted towards
+// 120 BPM so that we don't pick half or double the tempo
+// just because the autocorrelation happens to like it.
+//
+// The beats are then placed at whatever phase of that period
+// lines up with the most flux.
+//
+#define TEMPO_MIN_BPM 60
+#define TEMPO_MAX_BPM 200
+#define TEMPO_CENTER_BPM 120
+
+#define ONSET_FRAMES_PER_SEC (SAMPLES_PER_SEC / ONSET_HOP)
+
+//
+// Returns the number of beat positions (in samples) filled in,
+// and the tempo in *bpm (zero if nothing useful was found).
+//
+static inline int estimate_tempo(const float *x, int nr, float *bpm, int *beats, int max)
+{
+	int frames, count = 0;
+	float *flux = onset_envelope(x, nr, &frames);
+	int min_lag = (int) (ONSET_FRAMES_PER_SEC * 60 / TEMPO_MAX_BPM);
+	int max_lag = (int) (ONSET_FRAMES_PER_SEC * 60 / TEMPO_MIN_BPM);
+	double mean = 0, best = 0;
+	int lag = 0;
+
+	*bpm = 0;
+	if (!flux)
+		return 0;
+	if (frames < 2 * max_lag)
+		goto out;
+
+	for (int f = 0; f < frames; f++)
+		mean += flux[f];
+	mean /= frames;
+	for (int f = 0; f < frames; f++)
+		flux[f] = fmaxf(0, (float) (flux[f] - mean));
+
+	double *ac = calloc(max_lag + 2, sizeof(double));
+	if (!ac)
+		goto out;
+	for (int l = min_lag - 1; l <= max_lag + 1; l++) {
+		double sum = 0;
+		for (int f = 0; f + l < frames; f++)
+			sum += (double) flux[f] * flux[f + l];
+		ac[l] = sum / (frames - l);
+	}
+
+	// Log-gaussian weighting around the center tempo, one octave wide
+	for (int l = min_lag; l <= max_lag; l++) {
+		double octaves = log2(ONSET_FRAMES_PER_SEC * 60 / l / TEMPO_CENTER_BPM);
+		double val = ac[l] * exp(-0.5 * octaves * octaves);
+
+		if (val > best) {
+			best = val;
+			lag = l;
+		}
+	}
+
+	if (!lag) {
+		free(ac);
+		goto out;
+	}
+
+	// Parabolic interpolation of the period
+	double a = ac[lag-1], b = ac[lag], c = ac[lag+1];
+	double denom = a - 2*b + c;
+	double period = lag + (denom ? (a - c) / (2*denom) : 0);
+	free(ac);
+
+	*bpm = (float) (ONSET_FRAMES_PER_SEC * 60 / period);
+
+	// Find the beat phase with the most flux on it
+	int phase = 0;
+	best = -1;
+	for (int p = 0; p < lag; p++) {
+		double sum = 0;
+		for (double f = p; f < frames; f += period)
+			sum += flux[(int) f];
+		if (sum > best) {
+			best = sum;
+			phase = p;
+		}
+	}
+
+	for (double f = phase; f < frames && count < max; f += period)
+		beats[count++] = onset_frame_to_sample((int) f);
+
+out:
+	free(flux);
+	return count;
+}
@@ -0,0 +1,122 @@
+//
+// Test signal oscillators: sine, square, saw, triangle, impulse, DC
+//
+// These are for making test signals, not for playing music: the
+// square, saw and triangle are the naive versions and alias
+// like crazy at high frequencies. The phase is kept in double
+// so that a long tone doesn't drift.
+//
+// 'phase' is in cycles (0 .. 1), so 0.25 starts a sine at its peak.
+//
+enum osc_wave {
+	osc_sine,
+	osc_square,
+	osc_saw,
+	osc_triangle,
+	osc_impulse,
+	osc_dc,
+};
+
+struct oscillator {
+	enum osc_wave wave;
+	double phase, step;
+	float amplitude;
+};
+
+static inline void oscillator_init(struct oscillator *o, enum osc_wave wave, float freq, float amplitude, float phase)
+{
+	o->wave = wave;
+	o->phase = phase - floor(phase);
+	o->step = freq / SAMPLES_PER_SEC;
+	o->amplitude = amplitude;
+}
+
+static inline float osc_value(enum osc_wave wave, double phase, double step)
+{
+	switch (wave) {
+	case osc_sine:
+		return (float) sin(2*M_PI*phase);
+	case osc_square:
+		return phase < 0.5 ? 1 : -1;
+	case osc_saw:
+		return (float) (2*phase - 1);
+	case osc_triangle:
+		return (float) (phase < 0.5 ? 4*phase - 1 : 3 - 4*phase);
+	case osc_impulse:
+		// One sample at the start of each cycle
+		return phase < step ? 1 : 0;
+	default:
+		return 1;
+	}
+}
+
+static inline float oscillator_step(struct oscillator *o)
+{
+	float val = osc_value(o->wave, o->phase, o->step);
+
+	o->phase += o->step;
+	o->phase -= floor(o->phase);
+	return o->amplitude * val;
+}
+
+static inline void oscillator_fill(struct oscillator *o, float *buf, int nr)
+{
+	for (int i = 0; i < nr; i++)
+		buf[i] = oscillator_step(o);
+}
+
+//
+// A whole buffer of 'secs' seconds of the waveform. Returns
+// a newly allocated buffer with the number of samples in *nr.
+//
+static inline float *oscillator_generate(enum osc_wave wave, float freq, float amplitude, float phase, float secs, int *nr)
+{
+	struct oscillator o;
+	int len = (int) lrint(secs * SAMPLES_PER_SEC);
+	float *buf = malloc((len + 1) * sizeof(float));
+
+	*nr = 0;
+	if (!buf)
+		return NULL;
+
+	oscillator_init(&o, wave, freq, amplitude, phase);
+	oscillator_fill(&o, buf, len);
+	*nr = len;
+	return buf;
+}
+
+//
+// Helper "effect" that ignores the input and outputs a test
+// signal instead (feed it /dev/zero for as long as you want):
+//
+//	pot[0]: sine, square, saw, triangle, impulse or DC
+//	pot[1]: frequency, 220Hz .. 6.5kHz
+//	pot[2]: level, -60 .. 0dB
+//
+static struct oscillator osc;
+
+static const char *const osc_names[] = { "sine", "square", "saw", "triangle", "impulse", "dc" };
+
+static inline enum osc_wave osc_pot_wave(float pot)
+{
+	int wave = (int) (pot * ARRAY_SIZE(osc_names));
+
+	return wave > osc_dc ? osc_dc : wave;
+}
+
+static inline void osc_describe(float pot[4])
+{
+	fprintf(stderr, " wave=%s", osc_names[osc_pot_wave(pot[0])]);
+	fprintf(stderr, " freq=%g Hz", pot_frequency(pot[1]));
+	fprintf(stderr, " level=%g dB\n", linear(pot[2], -60, 0));
+}
+
+static inline void osc_init(float pot[4])
+{
+	// Keep the phase going from one block to the next
+	osc.wave = osc_pot_wave(pot[0]);
+	osc.step = pot_frequency(pot[1]) / SAMPLES_PER_SEC;
+	osc.amplitude = db_to_linear(linear(pot[2], -60, 0));
+}
+
+static inline float osc_step(float in) { return oscillator_step(&osc); }
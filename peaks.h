@@ -0,0 +1,428 @@
+//
+// Waveform overviews: the min and max of the samples behind each
+// column of a display, however many samples that is. And their
+// mean and RMS, for drawing the usual lighter RMS band inside the
+// peaks.
+//
+// Scanning all of a long capture on every redraw gets slow when
+// zoomed out, so the peak pyramid keeps the min/max at power-of-two
+// decimations: level 0 has one entry per PEAK_BASE samples, level 1
+// one per 2*PEAK_BASE, and so on, each built from pairs of entries
+// in the level below. All of it together is about 1/20 the size
+// of the samples, and it only has to be built once per file.
+//
+// The entries keep the sum and sum of squares rather than the mean
+// and RMS, so that they just add up. Divide by the number of
+// samples afterwards with peak_mean() and peak_rms().
+//
+// Any range then takes at most two entries per level, plus the
+// samples at the ragged ends that don't fill a whole level 0
+// entry. So the lookup is exact, and a single spike doesn't
+// show up in the neighbouring columns as well.
+//
+#define PEAK_BASE 256
+#define PEAK_LEVELS 40
+
+struct peak {
+	float min, max;
+	double sum, squares;
+};
+
+#define PEAK_EMPTY ((struct peak) { INFINITY, -INFINITY, 0, 0 })
+
+struct peak_pyramid {
+	size_t nr;			// samples covered
+	int levels;
+	size_t len[PEAK_LEVELS];
+	struct peak *level[PEAK_LEVELS];
+};
+
+static inline void peak_merge(struct peak *a, struct peak b)
+{
+	if (b.min < a->min)
+		a->min = b.min;
+	if (b.max > a->max)
+		a->max = b.max;
+	a->sum += b.sum;
+	a->squares += b.squares;
+}
+
+//
+// The samples don't have to be floats: they can be left in whatever
+// raw format they're in (see audiofile.h), so that a mapped capture
+// doesn't need to be converted first. Without a format, they're
+// plain floats, 'channels' of them interleaved.
+//
+// For multichannel data, only 'channel' is looked at. It's picked
+// out a little at a time, so there's never a deinterleaved copy of
+// the whole thing.
+//
+struct peak_samples {
+	const void *data;
+	const struct audio_format *fmt;
+	int channel, channels;
+};
+
+#define peak_floats(x) ((struct peak_samples) { x, NULL, 0, 1 })
+#define peak_interleaved(x, channels, channel) ((struct peak_samples) { x, NULL, channel, channels })
+
+// The vector loops from measure.h on floats
+static inline struct peak peak_scan_floats(const float *x, int nr)
+{
+	struct peak res = PEAK_EMPTY;
+
+	if (nr) {
+		minmax(x, nr, &res.min, &res.max);
+		sum_squares(x, nr, &res.sum, &res.squares);
+	}
+	return res;
+}
+
+static inline struct peak peak_scan(const struct peak_samples *s, size_t from, size_t nr)
+{
+	struct peak res = PEAK_EMPTY;
+	float buf[PEAK_BASE];
+
+	// Plain mono floats are the common case
+	if (!s->fmt && s->channels == 1 && nr <= INT_MAX)
+		return peak_scan_floats((const float *) s->data + from, (int) nr);
+
+	// Anything else gets picked out into floats a level 0 entry at
+	// a time, so that it's added up the exact same way
+	while (nr) {
+		int n = nr < PEAK_BASE ? (int) nr : PEAK_BASE;
+
+		if (!s->fmt) {
+			const float *x = (const float *) s->data + from * s->channels + s->channel;
+
+			for (int i = 0; i < n; i++, x += s->channels)
+				buf[i] = *x;
+		} else {
+			int fb = frame_bytes(s->fmt);
+			const unsigned char *p = (const unsigned char *) s->data + from * fb +
+				s->channel * sample_bytes(s->fmt->type);
+
+			for (int i = 0; i < n; i++, p += fb)
+				buf[i] = decode_sample(p, s->fmt);
+		}
+		peak_merge(&res, peak_scan_floats(buf, n));
+		from += n;
+		nr -= n;
+	}
+	return res;
+}
+
+// 'nr' is the number of samples the peak is for
+static inline float peak_mean(const struct peak *pk, size_t nr)
+{
+	return nr ? (float) (pk->sum / nr) : 0;
+}
+
+static inline float peak_rms(const struct peak *pk, size_t nr)
+{
+	return nr ? (float) sqrt(pk->squares / nr) : 0;
+}
+
+// Levels in dBFS, for coloring the loud parts (see linear_to_db())
+static inline float peak_dbfs(const struct peak *pk, float floor_db)
+{
+	if (pk->min > pk->max)
+		return floor_db;
+	return linear_to_db(fmaxf(fabsf(pk->min), fabsf(pk->max)), floor_db);
+}
+
+static inline float peak_rms_dbfs(const struct peak *pk, size_t nr, float floor_db)
+{
+	return linear_to_db(peak_rms(pk, nr), floor_db);
+}
+
+static inline void peak_pyramid_free(struct peak_pyramid *p)
+{
+	for (int i = 0; i < p->levels; i++)
+		free(p->level[i]);
+	memset(p, 0, sizeof(*p));
+}
+
+// All the levels for 'nr' samples, with nothing in them yet
+static inline int peak_pyramid_alloc(struct peak_pyramid *p, size_t nr)
+{
+	size_t len = (nr + PEAK_BASE - 1) / PEAK_BASE;
+
+	memset(p, 0, sizeof(*p));
+	p->nr = nr;
+	while (len && p->levels < PEAK_LEVELS) {
+		p->level[p->levels] = malloc(len * sizeof(struct peak));
+		if (!p->level[p->levels]) {
+			peak_pyramid_free(p);
+			return -1;
+		}
+		p->len[p->levels++] = len;
+		if (len == 1)
+			break;
+		len = (len + 1) / 2;
+	}
+	return 0;
+}
+
+//
+// More samples got appended (to a capture that's still being
+// recorded, say): only the entries covering the new ones need
+// redoing, which is the last (partial) one at each level and what
+// comes after it. 's' has to describe all 'nr' samples, old and
+// new ones.
+//
+static inline int peak_pyramid_extend(struct peak_pyramid *p, const struct peak_samples *s, size_t nr)
+{
+	size_t first = p->nr / PEAK_BASE, len = (nr + PEAK_BASE - 1) / PEAK_BASE;
+
+	if (nr <= p->nr)
+		return 0;
+
+	for (int l = 0; l < PEAK_LEVELS; l++) {
+		if (l == p->levels || p->len[l] != len) {
+			struct peak *level = realloc(l < p->levels ? p->level[l] : NULL, len * sizeof(struct peak));
+
+			if (!level)
+				return -1;
+			if (l == p->levels) {
+				p->levels++;
+				first = 0;
+			}
+			p->level[l] = level;
+			p->len[l] = len;
+		}
+
+		for (size_t i = first; i < len; i++) {
+			if (!l) {
+				size_t pos = i * PEAK_BASE;
+				p->level[0][i] = peak_scan(s, pos, nr - pos < PEAK_BASE ? nr - pos : PEAK_BASE);
+				continue;
+			}
+			p->level[l][i] = p->level[l - 1][2*i];
+			if (2*i + 1 < p->len[l - 1])
+				peak_merge(p->level[l] + i, p->level[l - 1][2*i + 1]);
+		}
+
+		if (len == 1)
+			break;
+		len = (len + 1) / 2;
+		first /= 2;
+	}
+	p->nr = nr;
+	return 0;
+}
+
+static inline int peak_pyramid_build(struct peak_pyramid *p, const struct peak_samples *s, size_t nr)
+{
+	memset(p, 0, sizeof(*p));
+	if (peak_pyramid_extend(p, s, nr) < 0) {
+		peak_pyramid_free(p);
+		return -1;
+	}
+	return 0;
+}
+
+// The samples [from, to)
+static inline struct peak peak_range(const struct peak_pyramid *p, const struct peak_samples *s,
+	size_t from, size_t to)
+{
+	struct peak res;
+	size_t first, last;
+
+	if (to > p->nr)
+		to = p->nr;
+	if (from >= to)
+		return PEAK_EMPTY;
+
+	// The level 0 entries [first, last) are all inside the range
+	first = (from + PEAK_BASE - 1) / PEAK_BASE;
+	last = to / PEAK_BASE;
+	if (first >= last)
+		return peak_scan(s, from, to - from);
+
+	res = peak_scan(s, from, first * PEAK_BASE - from);
+	peak_merge(&res, peak_scan(s, last * PEAK_BASE, to - last * PEAK_BASE));
+	for (int level = 0; first < last && level < p->levels; level++) {
+		if (first & 1)
+			peak_merge(&res, p->level[level][first++]);
+		if (last & 1)
+			peak_merge(&res, p->level[level][--last]);
+		first >>= 1;
+		last >>= 1;
+	}
+	return res;
+}
+
+// Split [from, to) into 'cols' columns for drawing
+static inline void peak_columns(const struct peak_pyramid *p, const struct peak_samples *s,
+	size_t from, size_t to, int cols, struct peak *out)
+{
+	for (int c = 0; c < cols; c++)
+		out[c] = peak_range(p, s, from + (to - from) * c / cols, from + (to - from) * (c + 1) / cols);
+}
+
+//
+// When the samples come from a stream (see audio_stream_read(),
+// and 'analyze overview --every') there's never the whole thing
+// to build a pyramid from, but the peaks of fixed-size buckets
+// can still be collected as chunks of samples come in, whatever
+// size the chunks are.
+//
+struct peak_bucketer {
+	size_t size, fill;
+	struct peak cur;
+};
+
+static inline void peak_bucketer_init(struct peak_bucketer *b, size_t size)
+{
+	b->size = size;
+	b->fill = 0;
+	b->cur = PEAK_EMPTY;
+}
+
+//
+// Returns how many buckets the chunk completed, which are written
+// to 'out' (there have to be at most 'max' of them, so make that
+// nr / size + 1).
+//
+static inline int peak_bucketer_push(struct peak_bucketer *b, const float *x, size_t nr,
+	struct peak *out, int max)
+{
+	int done = 0;
+
+	// Past 'max', only what doesn't complete another bucket fits
+	while (nr && (done < max || b->size - b->fill > nr)) {
+		size_t len = b->size - b->fill < nr ? b->size - b->fill : nr;
+		struct peak pk = peak_scan(&peak_floats(x), 0, len);
+
+		peak_merge(&b->cur, pk);
+		b->fill += len;
+		x += len;
+		nr -= len;
+		if (b->fill == b->size) {
+			out[done++] = b->cur;
+			b->fill = 0;
+			b->cur = PEAK_EMPTY;
+		}
+	}
+	return done;
+}
+
+// The last, partial bucket at the end of the stream (returns its size)
+static inline size_t peak_bucketer_flush(struct peak_bucketer *b, struct peak *out)
+{
+	size_t len = b->fill;
+
+	*out = b->cur;
+	peak_bucketer_init(b, b->size);
+	return len;
+}
+
+//
+// Building the pyramid still means reading every sample once, which
+// for a huge capture is the slow part of opening it. So it can be
+// saved next to the capture ('capture.raw' gets 'capture.raw.peaks')
+// and loaded from there next time.
+//
+// The cache is only used if it's for the same samples. Hashing all
+// of them would be as slow as rebuilding, so the fingerprint is the
+// length and a hash of PEAK_CACHE_BLOCKS blocks spread evenly over
+// the samples: enough to notice a different or re-recorded file.
+//
+// It's in native byte order, it's just a cache.
+//
+#define PEAK_CACHE_SUFFIX ".peaks"
+#define PEAK_CACHE_MAGIC "APEAKS02"
+#define PEAK_CACHE_BLOCKS 64
+
+struct peak_cache_header {
+	char magic[8];
+	u64 fingerprint, nr;
+	u32 base, levels;
+};
+
+static inline u64 peak_fingerprint(const struct peak_samples *s, size_t nr)
+{
+	size_t size = s->fmt ? frame_bytes(s->fmt) : s->channels * sizeof(float);
+	const unsigned char *data = s->data;
+	u64 h = xxh64(&nr, sizeof(nr), 0);
+
+	if (nr <= (size_t) PEAK_CACHE_BLOCKS * HASH_BLOCK)
+		return xxh64(data, nr * size, h);
+
+	for (int i = 0; i < PEAK_CACHE_BLOCKS; i++) {
+		size_t pos = (u64) (nr - HASH_BLOCK) * i / (PEAK_CACHE_BLOCKS - 1);
+		h = xxh64(data + pos * size, HASH_BLOCK * size, h);
+	}
+	return h;
+}
+
+static inline int peak_cache_name(const char *name, char *path)
+{
+	return snprintf(path, PATH_MAX, "%s" PEAK_CACHE_SUFFIX, name) < PATH_MAX ? 0 : -1;
+}
+
+static inline int peak_cache_save(const struct peak_pyramid *p, const char *name, u64 fingerprint)
+{
+	struct peak_cache_header hdr = { PEAK_CACHE_MAGIC, fingerprint, p->nr, PEAK_BASE, p->levels };
+	char path[PATH_MAX];
+	FILE *f;
+	int ret = 0;
+
+	if (peak_cache_name(name, path) < 0 || !(f = fopen(path, "w")))
+		return -1;
+	if (fwrite(&hdr, sizeof(hdr), 1, f) != 1)
+		ret = -1;
+	for (int l = 0; l < p->levels && !ret; l++) {
+		if (fwrite(p->level[l], sizeof(struct peak), p->len[l], f) != p->len[l])
+			ret = -1;
+	}
+	if (fclose(f) || ret) {
+		unlink(path);
+		return -1;
+	}
+	return 0;
+}
+
+// Returns -1 if there's no cache, or it's for some other samples
+//
+// A capture that's still being recorded will have grown since its
+// cache was saved. If the cache is for the start of the samples,
+// only the new ones need looking at (see peak_pyramid_extend()).
+//
+// Returns 0 if the cache was good as it is, 1 if it had to be
+// extended (so it's worth saving again), and -1 if it's no use.
+//
+static inline int peak_cache_load(struct peak_pyramid *p, const char *name, const struct peak_samples *s, size_t nr)
+{
+	struct peak_cache_header hdr;
+	char path[PATH_MAX];
+	FILE *f;
+
+	if (peak_cache_name(name, path) < 0 || !(f = fopen(path, "r")))
+		return -1;
+	if (fread(&hdr, sizeof(hdr), 1, f) != 1 || memcmp(hdr.magic, PEAK_CACHE_MAGIC, 8) ||
+	    hdr.base != PEAK_BASE || !hdr.nr || hdr.nr > nr ||
+	    hdr.fingerprint != peak_fingerprint(s, hdr.nr) ||
+	    peak_pyramid_alloc(p, hdr.nr) < 0) {
+		fclose(f);
+		return -1;
+	}
+	for (int l = 0; l < p->levels; l++) {
+		if (hdr.levels != p->levels ||
+		    fread(p->level[l], sizeof(struct peak), p->len[l], f) != p->len[l]) {
+			peak_pyramid_free(p);
+			fclose(f);
+			return -1;
+		}
+	}
+	fclose(f);
+
+	if (hdr.nr == nr)
+		return 0;
+	if (peak_pyramid_extend(p, s, nr) < 0) {
+		peak_pyramid_free(p);
+		return -1;
+	}
+	return 1;
+}
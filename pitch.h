@@ -0,0 +1,92 @@
+//
+// Pitch detection using the YIN algorithm
+//
+// (de Cheveigné and Kawahara, "YIN, a fundamental frequency
+// estimator for speech and music", 2002)
+//
+// This is a plain time-domain implementation without any FFT
+// tricks, so it's O(window * lags) per frame. That's fine for
+// offline analysis, and the range is set up to cover a bass
+// guitar (low E is 41Hz) without going too crazy.
+//
+// 40Hz .. 2kHz at 48kHz sample rate
+#define PITCH_MAX_LAG 1200
+#define PITCH_MIN_LAG 24
+
+// The integration window, and the number of samples each frame needs
+#define PITCH_WINDOW PITCH_MAX_LAG
+#define PITCH_FRAME (PITCH_WINDOW + PITCH_MAX_LAG)
+
+// The "absolute threshold" of the cumulative mean normalized difference
+#define PITCH_THRESHOLD 0.15f
+
+struct pitch {
+	float freq;		// 0 if no pitch was found
+	float confidence;	// 0 .. 1
+};
+
+static inline void pitch_detect(const float *x, struct pitch *res)
+{
+	static float d[PITCH_MAX_LAG+1];
+	float sum = 0;
+	int tau;
+
+	// Difference function, normalized by its cumulative mean
+	d[0] = 1;
+	for (tau = 1; tau <= PITCH_MAX_LAG; tau++) {
+		float diff = 0;
+
+		for (int j = 0; j < PITCH_WINDOW; j++) {
+			float delta = x[j] - x[j+tau];
+			diff += delta * delta;
+		}
+		sum += diff;
+		d[tau] = sum ? diff * tau / sum : 1;
+	}
+
+	// First dip under the threshold, followed down to its minimum
+	for (tau = PITCH_MIN_LAG; tau < PITCH_MAX_LAG; tau++) {
+		if (d[tau] < PITCH_THRESHOLD) {
+			while (tau + 1 < PITCH_MAX_LAG && d[tau+1] < d[tau])
+				tau++;
+			break;
+		}
+	}
+
+	res->freq = 0;
+	if (tau >= PITCH_MAX_LAG) {
+		// Nothing under the threshold: report the best we saw
+		// as the confidence, but don't claim a pitch
+		float best = 1;
+		for (tau = PITCH_MIN_LAG; tau < PITCH_MAX_LAG; tau++)
+			best = fminf(best, d[tau]);
+		res->confidence = 1 - best;
+		return;
+	}
+
+	// Parabolic interpolation around the minimum
+	float a = d[tau-1], b = d[tau], c = d[tau+1];
+	float denom = a - 2*b + c;
+	float shift = denom ? (a - c) / (2*denom) : 0;
+
+	res->freq = (float) (SAMPLES_PER_SEC / (tau + shift));
+	res->confidence = fmaxf(0, 1 - b);
+}
+
+//
+// Turn a frequency into the nearest note name and
+// how many cents off from it we are (A4 = 440Hz)
+//
+static inline const char *pitch_note(float freq, int *octave, float *cents)
+{
+	static const char *names[12] = {
+		"C", "C#", "D", "D#", "E", "F",
+		"F#", "G", "G#", "A", "A#", "B"
+	};
+	float semitones = 12 * log2f(freq / 440) + 57;
+	int note = (int) lrintf(semitones);
+
+	*cents = 100 * (semitones - note);
+	*octave = note / 12;
+	return names[note % 12];
+}
@@ -0,0 +1,278 @@
+//
+// Drawing waveform overviews (see peaks.h) as images, for reports
+// and such: one column of pixels per bucket, with the min..max
+// range in it and the lighter RMS band inside that.
+//
+// SVG is just text. PNG would normally mean zlib, but deflate has
+// "stored" blocks that aren't compressed at all, and an overview
+// image is small enough that it doesn't matter much.
+//
+// Columns that peak above 'hot_db' get drawn in the 'hot' color
+// instead, to make clipping (or nearly) stand out.
+//
+// And for a terminal there's text: one '|' per character cell, or
+// half blocks (1x2 dots in a cell) or braille (2x4) to get more
+// out of the same number of characters, if the terminal has them.
+//
+struct render_options {
+	int width, height;
+	u32 background, peak, rms;	// 0xRRGGBB
+	float db_range;			// dB scale down to -db_range, or 0 for linear
+	u32 hot;
+	float hot_db;			// dBFS, or 0 for no hot columns
+};
+
+#define RENDER_OPTIONS { 1000, 200, 0xffffff, 0x3060c0, 0x90b0e0, 0, 0xd02020, 0 }
+
+struct render_column {
+	float min, max, rms;
+};
+
+enum render_style { render_ascii, render_blocks, render_braille };
+
+// Where 'val' goes vertically, from 0 (top) to 1 (bottom)
+static inline float render_pos(float val, const struct render_options *opt)
+{
+	if (opt->db_range > 0) {
+		float mag = fabsf(val) > 0 ? 1 + 20 * log10f(fabsf(val)) / opt->db_range : 0;
+
+		if (mag < 0)
+			mag = 0;
+		val = val < 0 ? -mag : mag;
+	}
+	if (val > 1)
+		val = 1;
+	if (val < -1)
+		val = -1;
+	return (1 - val) / 2;
+}
+
+static inline u32 render_peak_color(const struct render_column *c, const struct render_options *opt)
+{
+	float peak = fmaxf(fabsf(c->min), fabsf(c->max));
+
+	if (opt->hot_db < 0 && linear_to_db(peak, -200) >= opt->hot_db)
+		return opt->hot;
+	return opt->peak;
+}
+
+static inline int render_row(float val, const struct render_options *opt)
+{
+	return (int) lrintf(render_pos(val, opt) * (opt->height - 1));
+}
+
+// The image as 8-bit RGB, one row after the other
+static inline void render_raster(const struct render_column *col, const struct render_options *opt,
+	unsigned char *rgb)
+{
+	size_t stride = (size_t) opt->width * 3;
+
+	for (int y = 0; y < opt->height; y++) {
+		for (int x = 0; x < opt->width; x++)
+			store_bytes(rgb + y * stride + x * 3, opt->background, 3, 1);
+	}
+
+	for (int x = 0; x < opt->width; x++) {
+		const struct render_column *c = col + x;
+		int top, bottom, rms_top, rms_bottom;
+
+		if (c->min > c->max)
+			continue;
+		top = render_row(c->max, opt);
+		bottom = render_row(c->min, opt);
+		rms_top = render_row(c->rms, opt);
+		rms_bottom = render_row(-c->rms, opt);
+		for (int y = top; y <= bottom; y++) {
+			u32 color = y >= rms_top && y <= rms_bottom ? opt->rms : render_peak_color(c, opt);
+			store_bytes(rgb + y * stride + x * 3, color, 3, 1);
+		}
+	}
+}
+
+static inline int render_svg(FILE *f, const struct render_column *col, const struct render_options *opt)
+{
+	int w = opt->width, h = opt->height;
+
+	fprintf(f, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"%d\" height=\"%d\" "
+		"viewBox=\"0 0 %d %d\" shape-rendering=\"crispEdges\">\n", w, h, w, h);
+	fprintf(f, "<rect width=\"%d\" height=\"%d\" fill=\"#%06x\"/>\n", w, h, opt->background);
+
+	for (int pass = 0; pass < 2; pass++) {
+		fprintf(f, "<g fill=\"#%06x\">\n", pass ? opt->rms : opt->peak);
+		for (int x = 0; x < w; x++) {
+			const struct render_column *c = col + x;
+			float top, bottom;
+
+			if (c->min > c->max)
+				continue;
+			top = render_pos(c->max, opt);
+			bottom = render_pos(c->min, opt);
+			if (pass) {
+				top = fmaxf(top, render_pos(c->rms, opt));
+				bottom = fminf(bottom, render_pos(-c->rms, opt));
+				if (top >= bottom)
+					continue;
+			}
+			fprintf(f, "<rect x=\"%d\" y=\"%.2f\" width=\"1\" height=\"%.2f\"", x, top * h,
+				fmaxf((bottom - top) * h, 1));
+			if (!pass && render_peak_color(c, opt) != opt->peak)
+				fprintf(f, " fill=\"#%06x\"", opt->hot);
+			fprintf(f, "/>\n");
+		}
+		fprintf(f, "</g>\n");
+	}
+	fprintf(f, "</svg>\n");
+	return ferror(f) ? -1 : 0;
+}
+
+static inline u32 png_crc(u32 crc, const unsigned char *p, size_t len)
+{
+	crc = ~crc;
+	while (len--) {
+		crc ^= *p++;
+		for (int k = 0; k < 8; k++)
+			crc = (crc >> 1) ^ (0xedb88320u & -(crc & 1));
+	}
+	return ~crc;
+}
+
+static inline void png_chunk(FILE *f, const char *type, const unsigned char *data, size_t len)
+{
+	unsigned char buf[4];
+
+	store_bytes(buf, len, 4, 1);
+	fwrite(buf, 1, 4, f);
+	fwrite(type, 1, 4, f);
+	if (len)
+		fwrite(data, 1, len, f);
+	store_bytes(buf, png_crc(png_crc(0, (const unsigned char *) type, 4), data, len), 4, 1);
+	fwrite(buf, 1, 4, f);
+}
+
+static inline int render_png(FILE *f, const struct render_column *col, const struct render_options *opt)
+{
+	size_t stride = (size_t) opt->width * 3, raw_len = (stride + 1) * opt->height;
+	size_t blocks = (raw_len + 65534) / 65535, len = 2 + raw_len + 5 * blocks + 4;
+	unsigned char *rgb = malloc(stride * opt->height), *z = malloc(len), *p = z;
+	unsigned char hdr[13] = { 0 };
+	u32 a = 1, b = 0;
+
+	if (!rgb || !z) {
+		free(rgb);
+		free(z);
+		return -1;
+	}
+	render_raster(col, opt, rgb);
+
+	// zlib header, then the rows (each with filter type 0) in stored blocks
+	*p++ = 0x78;
+	*p++ = 0x01;
+	for (size_t pos = 0; pos < raw_len; ) {
+		size_t n = raw_len - pos < 65535 ? raw_len - pos : 65535;
+
+		*p++ = pos + n == raw_len;
+		store_bytes(p, n, 2, 0);
+		store_bytes(p + 2, ~n & 0xffff, 2, 0);
+		p += 4;
+		for (size_t i = 0; i < n; i++, pos++) {
+			size_t row = pos / (stride + 1), x = pos % (stride + 1);
+			unsigned char c = x ? rgb[row * stride + x - 1] : 0;
+
+			*p++ = c;
+			a = (a + c) % 65521;
+			b = (b + a) % 65521;
+		}
+	}
+	store_bytes(p, (u64) b << 16 | a, 4, 1);
+
+	store_bytes(hdr, opt->width, 4, 1);
+	store_bytes(hdr + 4, opt->height, 4, 1);
+	hdr[8] = 8;		// bits per channel
+	hdr[9] = 2;		// RGB
+
+	fwrite("\x89PNG\r\n\x1a\n", 1, 8, f);
+	png_chunk(f, "IHDR", hdr, sizeof(hdr));
+	png_chunk(f, "IDAT", z, len);
+	png_chunk(f, "IEND", NULL, 0);
+	free(rgb);
+	free(z);
+	return ferror(f) ? -1 : 0;
+}
+
+// Columns and rows of dots in a character cell
+static inline int render_dots_x(enum render_style style)
+{
+	return style == render_braille ? 2 : 1;
+}
+
+static inline int render_dots_y(enum render_style style)
+{
+	return style == render_braille ? 4 : style == render_blocks ? 2 : 1;
+}
+
+// 'dots' has bit (x * 4 + y) set for dot x, y of the cell
+static inline void render_cell(FILE *f, unsigned dots, enum render_style style)
+{
+	static const char *const blocks[] = { " ", "\xe2\x96\x80", "\xe2\x96\x84", "\xe2\x96\x88" };
+	static const unsigned char braille[8] = { 0x01, 0x02, 0x04, 0x40, 0x08, 0x10, 0x20, 0x80 };
+	unsigned b = 0;
+
+	switch (style) {
+	case render_ascii:
+		putc(dots ? '|' : ' ', f);
+		return;
+	case render_blocks:
+		fputs(blocks[dots & 3], f);
+		return;
+	case render_braille:
+		for (int i = 0; i < 8; i++)
+			if (dots & (1u << i))
+				b |= braille[i];
+		if (!b) {
+			putc(' ', f);
+			return;
+		}
+		// U+2800 + b in UTF-8
+		putc(0xe2, f);
+		putc(0xa0 | b >> 6, f);
+		putc(0x80 | (b & 0x3f), f);
+		return;
+	}
+}
+
+//
+// The min..max of 'cells' times render_dots_x() columns, in 'cells'
+// characters by 'rows' lines (the RMS and colors don't make it).
+//
+static inline int render_text(FILE *f, const struct render_column *col, int cells, int rows,
+	enum render_style style, const struct render_options *opt)
+{
+	int dx = render_dots_x(style), dy = render_dots_y(style);
+	struct render_options dots = *opt;
+
+	dots.height = rows * dy;
+	for (int row = 0; row < rows; row++) {
+		for (int cell = 0; cell < cells; cell++) {
+			unsigned bits = 0;
+
+			for (int x = 0; x < dx; x++) {
+				const struct render_column *c = col + cell * dx + x;
+				int top, bottom;
+
+				if (c->min > c->max)
+					continue;
+				top = render_row(c->max, &dots);
+				bottom = render_row(c->min, &dots);
+				for (int y = 0; y < dy; y++) {
+					int r = row * dy + y;
+
+					if (r >= top && r <= bottom)
+						bits |= 1u << (x * 4 + y);
+				}
+			}
+			render_cell(f, bits, style);
+		}
+		putc('\n', f);
+	}
+	return ferror(f) ? -1 : 0;
+}
@@ -0,0 +1,270 @@
+//
+// Windowed-sinc sample rate conversion
+//
+// Everything else assumes a fixed SAMPLES_PER_SEC, so captures
+// at 44.1kHz or 96kHz need converting before they can be fed to
+// the effects or compared with other captures.
+//
+// This is the classic polyphase approach: a table of Kaiser
+// windowed sinc kernels at 'RESAMPLE_PHASES' fractional offsets,
+// linearly interpolated between neighbouring phases, so any
+// ratio works (not just nice rational ones).
+//
+// The quality setting picks the kernel length and window:
+//
+//	0: 8 zero crossings per side  (fast, ~-60dB aliasing)
+//	1: 16 zero crossings per side (~-90dB)
+//	2: 32 zero crossings per side (slow, ~-110dB)
+//
+// When going down in rate, the cutoff drops and the kernel
+// gets correspondingly longer, so quality doesn't suffer.
+//
+#define RESAMPLE_PHASES 256
+
+struct resampler {
+	int half;		// kernel taps on each side of the center
+	int taps;		// 2*half
+	float *table;		// (RESAMPLE_PHASES+1) rows of 'taps'
+	double step;		// input samples per output sample
+	double pos;		// next output position in 'buf'
+	float *buf;
+	int len, size;
+};
+
+static inline int resampler_init(struct resampler *r, double in_rate, double out_rate, int quality)
+{
+	static const struct {
+		int zeros;
+		double beta, cutoff;
+	} q[] = {
+		{  8,  6.0, 0.90 },
+		{ 16,  8.6, 0.94 },
+		{ 32, 10.0, 0.97 },
+	};
+
+	if (quality < 0)
+		quality = 0;
+	if (quality >= (int) ARRAY_SIZE(q))
+		quality = ARRAY_SIZE(q) - 1;
+
+	double fc = q[quality].cutoff * fmin(1, out_rate / in_rate);
+	double beta = q[quality].beta;
+	int half = (int) ceil(q[quality].zeros / fmin(1, out_rate / in_rate));
+
+	memset(r, 0, sizeof(*r));
+	r->half = half;
+	r->taps = 2*half;
+	r->step = in_rate / out_rate;
+	r->table = malloc((RESAMPLE_PHASES+1) * r->taps * sizeof(float));
+	if (!r->table)
+		return -1;
+
+	for (int p = 0; p <= RESAMPLE_PHASES; p++) {
+		float *row = r->table + p * r->taps;
+		double frac = (double) p / RESAMPLE_PHASES, sum = 0;
+
+		for (int j = 0; j < r->taps; j++) {
+			row[j] = (float) windowed_sinc(j - half + 1 - frac, half, fc, beta);
+			sum += row[j];
+		}
+
+		// Make each phase have exactly unity gain at DC
+		for (int j = 0; j < r->taps; j++)
+			row[j] = (float) (row[j] / sum);
+	}
+
+	// Start with 'half-1' samples of silence, so that the first
+	// output sample lines up with the first input sample
+	r->pos = half - 1;
+	r->len = half - 1;
+	r->size = 0;
+	return 0;
+}
+
+static inline void resampler_free(struct resampler *r)
+{
+	free(r->table);
+	free(r->buf);
+	r->table = r->buf = NULL;
+}
+
+//
+// One output sample: 'x' points to the 'taps' input samples
+// around it, and 'p' and 'frac' are the kernel phase
+//
+static inline float resample_point(struct resampler *r, const float *x, int p, float frac)
+{
+	const float *a = r->table + p * r->taps;
+	const float *b = a + r->taps;
+	float sa = 0, sb = 0;
+
+	for (int j = 0; j < r->taps; j++) {
+		sa += x[j] * a[j];
+		sb += x[j] * b[j];
+	}
+	return sa + (sb - sa) * frac;
+}
+
+//
+// Feed 'nr' input samples, and write at most 'max' output
+// samples. Returns the number of output samples written.
+//
+// Any input that wasn't needed yet stays buffered inside the
+// resampler, so it's fine to call this with nr = 0 to just
+// drain more output.
+//
+static inline int resampler_process(struct resampler *r, const float *in, int nr, float *out, int max)
+{
+	int count = 0;
+
+	if (r->len + nr > r->size) {
+		int size = r->len + nr + r->taps;
+		float *buf = realloc(r->buf, size * sizeof(float));
+		if (!buf)
+			return 0;
+		if (!r->size)
+			memset(buf, 0, r->len * sizeof(float));
+		r->buf = buf;
+		r->size = size;
+	}
+	memcpy(r->buf + r->len, in, nr * sizeof(float));
+	r->len += nr;
+
+	while (count < max) {
+		int i = (int) r->pos;
+		double phase = (r->pos - i) * RESAMPLE_PHASES;
+		int p = (int) phase;
+		float frac = (float) (phase - p);
+
+		// Need input up to buf[i + half]
+		if (i + r->half >= r->len)
+			break;
+
+		out[count++] = resample_point(r, r->buf + i - r->half + 1, p, frac);
+		r->pos += r->step;
+	}
+
+	// Drop the input we no longer need
+	int drop = (int) r->pos - r->half + 1;
+	if (drop > 0) {
+		if (drop > r->len)
+			drop = r->len;
+		memmove(r->buf, r->buf + drop, (r->len - drop) * sizeof(float));
+		r->len -= drop;
+		r->pos -= drop;
+	}
+	return count;
+}
+
+//
+// Offline conversion of a whole buffer. Returns a newly
+// allocated buffer, with the number of samples in *out_nr.
+//
+static inline float *resample(const float *x, int nr, double in_rate, double out_rate, int quality, int *out_nr)
+{
+	struct resampler r;
+	int max = (int) ceil(nr * out_rate / in_rate);
+	float *out, *zero;
+	int count;
+
+	*out_nr = 0;
+	if (resampler_init(&r, in_rate, out_rate, quality) < 0)
+		return NULL;
+
+	out = malloc((max + 1) * sizeof(float));
+	zero = calloc(r.half + 1, sizeof(float));
+	if (!out || !zero) {
+		free(out);
+		free(zero);
+		resampler_free(&r);
+		return NULL;
+	}
+
+	// Flush the tail out with the same amount of silence we started with
+	count = resampler_process(&r, x, nr, out, max);
+	count += resampler_process(&r, zero, r.half + 1, out + count, max - count);
+
+	free(zero);
+	resampler_free(&r);
+	*out_nr = count;
+	return out;
+}
+
+//
+// Push-based streaming conversion for live input
+//
+// The block interface above buffers however much input it's
+// given. This one is for the "one sample at a time" world of
+// the effects: push one input sample, and get back however many
+// output samples became ready (at most ceil(out_rate/in_rate)).
+//
+// The history is a fixed ring of 'taps+1' samples, so there is
+// no allocation after init and the latency is fixed: the output
+// lags the input by exactly 'half' input samples.
+//
+// The ring is stored twice in a row, so the kernel always sees
+// a contiguous window without having to care about wrapping.
+//
+struct resample_stream {
+	struct resampler r;
+	float *ring;
+	int len, w;
+	double delay;		// how far the next output is behind the newest input
+};
+
+static inline int resample_stream_init(struct resample_stream *s, double in_rate, double out_rate, int quality)
+{
+	if (resampler_init(&s->r, in_rate, out_rate, quality) < 0)
+		return -1;
+	s->len = s->r.taps + 1;
+	s->ring = calloc(2 * s->len, sizeof(float));
+	if (!s->ring) {
+		resampler_free(&s->r);
+		return -1;
+	}
+	s->w = 0;
+
+	// The first input sample is time zero for the first output
+	s->delay = -1;
+	return 0;
+}
+
+static inline void resample_stream_free(struct resample_stream *s)
+{
+	resampler_free(&s->r);
+	free(s->ring);
+	s->ring = NULL;
+}
+
+// Latency in input samples
+static inline int resample_stream_latency(struct resample_stream *s)
+{
+	return s->r.half;
+}
+
+static inline int resample_stream_push(struct resample_stream *s, float in, float *out)
+{
+	int count = 0;
+
+	s->ring[s->w] = s->ring[s->w + s->len] = in;
+	if (++s->w == s->len)
+		s->w = 0;
+	s->delay += 1;
+
+	while (s->delay >= s->r.half) {
+		int back = (int) ceil(s->delay);
+		double phase = (back - s->delay) * RESAMPLE_PHASES;
+		int p = (int) phase;
+		float frac = (float) (phase - p);
+
+		// The window starts 'back + half - 1' samples before the
+		// newest one, and the newest one is at 'w - 1'
+		int start = s->w - 1 - (back + s->r.half - 1);
+		if (start < 0)
+			start += s->len;
+
+		out[count++] = resample_point(&s->r, s->ring + start, p, frac);
+		s->delay -= s->r.step;
+	}
+	return count;
+}
@@ -0,0 +1,112 @@
+//
+// Seekable zstd archives of raw captures
+//
+// Long captures are mostly silence (or close to it), and zstd
+// squeezes them down a lot. But a plain .zst file can only be
+// decompressed from the start, so the zstd "seekable format"
+// compresses the data as a series of independent frames and
+// then adds a seek table at the end, in a skippable frame that
+// normal zstd just ignores:
+//
+//	magic 0x184D2A5E, size
+//	compressed size, decompressed size (, checksum) per frame
+//	number of frames, descriptor, magic 0x8F92EAB1
+//
+// Any run of consecutive frames is itself a valid zstd stream,
+// so the frames for just one part of the capture can be handed
+// to 'zstd -d' without touching the rest.
+//
+// We don't do the compression ourselves, that's what the zstd
+// binary is for. This just deals with the table.
+//
+#define ZSTD_SKIPPABLE_MAGIC 0x184D2A5Eu
+#define ZSTD_SEEKABLE_MAGIC 0x8F92EAB1u
+#define ZSTD_SEEKABLE_FOOTER 9
+#define ZSTD_SEEKABLE_CHECKSUM 0x80
+#define ZSTD_SEEKABLE_FRAME (1 << 22)	// bytes of samples per frame we write
+
+struct zstd_frame {
+	u64 offset, size;	// where it is in the archive
+	u64 start, len;		// and what part of the data it holds
+};
+
+//
+// Returns the number of frames (with a newly allocated table in
+// *frames), or -1 if there's no seek table.
+//
+static inline int zstd_seek_table(const unsigned char *buf, size_t len, struct zstd_frame **frames)
+{
+	const unsigned char *footer = buf + len - ZSTD_SEEKABLE_FOOTER, *p;
+	u64 offset = 0, start = 0;
+	int nr, entry;
+	size_t table;
+
+	*frames = NULL;
+	if (len < 8 + ZSTD_SEEKABLE_FOOTER || load_bytes(footer + 5, 4, 0) != ZSTD_SEEKABLE_MAGIC)
+		return -1;
+	nr = (int) load_bytes(footer, 4, 0);
+	entry = footer[4] & ZSTD_SEEKABLE_CHECKSUM ? 12 : 8;
+	table = (size_t) nr * entry + ZSTD_SEEKABLE_FOOTER;
+	if (nr <= 0 || table + 8 > len)
+		return -1;
+
+	p = buf + len - table - 8;
+	if (load_bytes(p, 4, 0) != ZSTD_SKIPPABLE_MAGIC || load_bytes(p + 4, 4, 0) != table)
+		return -1;
+	p += 8;
+
+	*frames = malloc(nr * sizeof(**frames));
+	if (!*frames)
+		return -1;
+	for (int i = 0; i < nr; i++, p += entry) {
+		struct zstd_frame *f = *frames + i;
+
+		f->offset = offset;
+		f->size = load_bytes(p, 4, 0);
+		f->start = start;
+		f->len = load_bytes(p + 4, 4, 0);
+		offset += f->size;
+		start += f->len;
+	}
+	if (offset > len - table - 8) {
+		free(*frames);
+		*frames = NULL;
+		return -1;
+	}
+	return nr;
+}
+
+// The skippable frame with the table, to go at the end
+static inline unsigned char *zstd_seek_table_build(const struct zstd_frame *frames, int nr, size_t *len)
+{
+	size_t table = (size_t) nr * 8 + ZSTD_SEEKABLE_FOOTER;
+	unsigned char *buf = malloc(table + 8), *p = buf;
+
+	if (!buf)
+		return NULL;
+	store_bytes(p, ZSTD_SKIPPABLE_MAGIC, 4, 0);
+	store_bytes(p + 4, table, 4, 0);
+	p += 8;
+	for (int i = 0; i < nr; i++, p += 8) {
+		store_bytes(p, frames[i].size, 4, 0);
+		store_bytes(p + 4, frames[i].len, 4, 0);
+	}
+	store_bytes(p, nr, 4, 0);
+	p[4] = 0;
+	store_bytes(p + 5, ZSTD_SEEKABLE_MAGIC, 4, 0);
+	*len = table + 8;
+	return buf;
+}
+
+// The frames [*first, *last] that hold data bytes [from, to)
+static inline int zstd_frames_for(const struct zstd_frame *frames, int nr, u64 from, u64 to,
+	int *first, int *last)
+{
+	*first = 0;
+	while (*first < nr && frames[*first].start + frames[*first].len <= from)
+		(*first)++;
+	*last = *first;
+	while (*last + 1 < nr && frames[*last + 1].start < to)
+		(*last)++;
+	return *first < nr ? 0 : -1;
+}
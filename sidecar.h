@@ -0,0 +1,180 @@
+//
+// Sidecar metadata for raw captures
+//
+// A raw file has no idea what rate or format it is, so that can
+// live next to it: 'capture.raw' gets a 'capture.raw.json' like
+//
+//	{
+//		"rate": 44100,
+//		"channels": 2,
+//		"format": "s16le",
+//		"notes": "DI out of the Strat, neck pickup"
+//	}
+//
+// where every field is optional. This is not a general JSON
+// parser: it only deals with a flat object of numbers and
+// strings, which is all we ever write. Other keys are ignored.
+//
+#define SIDECAR_SUFFIX ".json"
+#define SIDECAR_MAX 4096
+#define SIDECAR_NOTES 256
+
+static inline const char *sidecar_skip(const char *s)
+{
+	while (isspace((unsigned char) *s))
+		s++;
+	return s;
+}
+
+// A quoted string into 'buf' (truncated to fit). Returns the
+// position after the closing quote, or NULL.
+static inline const char *sidecar_string(const char *s, char *buf, int size)
+{
+	int len = 0;
+
+	if (*s++ != '"')
+		return NULL;
+	while (*s != '"') {
+		char c = *s++;
+
+		if (!c)
+			return NULL;
+		if (c == '\\') {
+			switch ((c = *s++)) {
+			case 'n': c = '\n'; break;
+			case 't': c = '\t'; break;
+			case '"': case '\\': case '/': break;
+			default: return NULL;
+			}
+		}
+		if (len < size - 1)
+			buf[len++] = c;
+	}
+	buf[len] = 0;
+	return s + 1;
+}
+
+//
+// Update 'fmt' (and 'notes', if non-NULL) with whatever the
+// sidecar says. Returns -1 and leaves them alone if it doesn't
+// make sense.
+//
+static inline int sidecar_parse(const char *s, struct audio_format *fmt, char *notes, int size)
+{
+	struct audio_format new = *fmt;
+	char note[SIDECAR_NOTES] = "";
+	int have_notes = 0;
+
+	s = sidecar_skip(s);
+	if (*s++ != '{')
+		return -1;
+
+	for (;;) {
+		char key[32], val[SIDECAR_NOTES];
+
+		s = sidecar_skip(s);
+		if (*s == '}')
+			break;
+		s = sidecar_string(s, key, sizeof(key));
+		if (!s)
+			return -1;
+		s = sidecar_skip(s);
+		if (*s++ != ':')
+			return -1;
+		s = sidecar_skip(s);
+
+		if (*s == '"') {
+			s = sidecar_string(s, val, sizeof(val));
+			if (!s)
+				return -1;
+			if (!strcmp(key, "format") && parse_raw_format(val, &new) < 0)
+				return -1;
+			if (!strcmp(key, "notes")) {
+				memcpy(note, val, sizeof(note));
+				have_notes = 1;
+			}
+		} else {
+			char *end;
+			double num = strtod(s, &end);
+
+			if (end == s)
+				return -1;
+			s = end;
+			if (!strcmp(key, "rate")) {
+				if (num <= 0)
+					return -1;
+				new.rate = (float) num;
+			}
+			if (!strcmp(key, "channels")) {
+				if (num < 1)
+					return -1;
+				new.channels = (int) num;
+			}
+		}
+
+		s = sidecar_skip(s);
+		if (*s == ',')
+			s++;
+		else if (*s != '}')
+			return -1;
+	}
+
+	*fmt = new;
+	if (notes && have_notes)
+		snprintf(notes, size, "%s", note);
+	return 0;
+}
+
+// Returns -1 if there's no sidecar (or a broken one) for 'name'
+static inline int sidecar_read(const char *name, struct audio_format *fmt, char *notes, int size)
+{
+	char path[PATH_MAX], buf[SIDECAR_MAX];
+	FILE *f;
+	size_t len;
+
+	if (snprintf(path, sizeof(path), "%s" SIDECAR_SUFFIX, name) >= (int) sizeof(path))
+		return -1;
+	f = fopen(path, "r");
+	if (!f)
+		return -1;
+	len = fread(buf, 1, sizeof(buf) - 1, f);
+	fclose(f);
+	buf[len] = 0;
+	return sidecar_parse(buf, fmt, notes, size);
+}
+
+static inline int sidecar_write(const char *name, const struct audio_format *fmt, const char *notes)
+{
+	char path[PATH_MAX], type[6];
+	FILE *f;
+
+	if (snprintf(path, sizeof(path), "%s" SIDECAR_SUFFIX, name) >= (int) sizeof(path))
+		return -1;
+	f = fopen(path, "w");
+	if (!f)
+		return -1;
+
+	fprintf(f, "{\n");
+	if (fmt->rate)
+		fprintf(f, "\t\"rate\": %g,\n", fmt->rate);
+	fprintf(f, "\t\"channels\": %d,\n", fmt->channels);
+	fprintf(f, "\t\"format\": \"%s\"", raw_format_name(fmt, type));
+	if (notes) {
+		fprintf(f, ",\n\t\"notes\": \"");
+		for (; *notes; notes++) {
+			unsigned char c = *notes;
+
+			if (c == '"' || c == '\\')
+				fprintf(f, "\\%c", c);
+			else if (c == '\n')
+				fprintf(f, "\\n");
+			else if (c == '\t')
+				fprintf(f, "\\t");
+			else if (c >= ' ')
+				fputc(c, f);
+		}
+		fputc('"', f);
+	}
+	fprintf(f, "\n}\n");
+	return fclose(f) ? -1 : 0;
+}
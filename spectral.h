@@ -0,0 +1,145 @@
+//
+// Per-frame spectral features
+//
+// All of these work on a magnitude spectrum of 'bins' bins going
+// from DC up to (just under) Nyquist, like what magnitude_spectrum()
+// gives you:
+//
+//  - centroid: the "center of mass" of the spectrum (Hz), ie
+//    roughly how bright it sounds
+//  - bandwidth: the spread around that centroid (Hz)
+//  - rolloff: the frequency under which 85% of the energy is
+//  - flatness: geometric over arithmetic mean of the power
+//    spectrum. Close to 1 for noise, close to 0 for tones
+//  - flux: how much the spectrum changed from the previous frame
+//
+#define SPECTRAL_FFT 2048
+#define SPECTRAL_HOP 1024
+#define SPECTRAL_ROLLOFF 0.85f
+
+struct spectral_features {
+	float centroid, bandwidth, rolloff, flatness, flux;
+};
+
+static inline void spectral_features(const float *mag, const float *prev, int bins,
+	struct spectral_features *f)
+{
+	double hz = SAMPLES_PER_SEC / 2 / bins;
+	double sum = 0, wsum = 0, power = 0, logpower = 0, flux = 0;
+
+	for (int k = 0; k < bins; k++) {
+		double p = (double) mag[k] * mag[k];
+		double d = prev ? mag[k] - prev[k] : 0;
+
+		sum += mag[k];
+		wsum += k * hz * mag[k];
+		power += p;
+		logpower += log(p + 1e-20);
+		flux += d * d;
+	}
+
+	memset(f, 0, sizeof(*f));
+	f->flux = (float) sqrt(flux);
+	if (!sum)
+		return;
+
+	double centroid = wsum / sum, spread = 0, acc = 0;
+	for (int k = 0; k < bins; k++) {
+		double d = k * hz - centroid;
+		spread += d * d * mag[k];
+	}
+
+	int k = 0;
+	for (; k < bins - 1; k++) {
+		acc += (double) mag[k] * mag[k];
+		if (acc >= SPECTRAL_ROLLOFF * power)
+			break;
+	}
+
+	f->centroid = (float) centroid;
+	f->bandwidth = (float) sqrt(spread / sum);
+	f->rolloff = (float) (k * hz);
+	f->flatness = (float) (exp(logpower / bins) / (power / bins));
+}
+
+//
+// Mel filterbank and MFCCs
+//
+// The usual recipe: triangular filters evenly spaced on the mel
+// scale applied to the power spectrum, log of the band energies,
+// and a DCT-II (orthonormal) of those to decorrelate them.
+//
+struct mel_filterbank {
+	int bands, bins;
+	float *weights;		// 'bands' rows of 'bins' weights
+};
+
+static inline double hz_to_mel(double f)
+{
+	return 2595 * log10(1 + f / 700);
+}
+
+static inline double mel_to_hz(double mel)
+{
+	return 700 * (pow(10, mel / 2595) - 1);
+}
+
+static inline int mel_init(struct mel_filterbank *mb, int bands, int bins, double fmin, double fmax)
+{
+	double hz = SAMPLES_PER_SEC / 2 / bins;
+	double lo = hz_to_mel(fmin), hi = hz_to_mel(fmax);
+
+	mb->bands = bands;
+	mb->bins = bins;
+	mb->weights = calloc(bands * bins, sizeof(float));
+	if (!mb->weights)
+		return -1;
+
+	for (int b = 0; b < bands; b++) {
+		double left = mel_to_hz(lo + (hi - lo) * b / (bands + 1));
+		double center = mel_to_hz(lo + (hi - lo) * (b + 1) / (bands + 1));
+		double right = mel_to_hz(lo + (hi - lo) * (b + 2) / (bands + 1));
+		float *w = mb->weights + b * bins;
+
+		for (int k = 0; k < bins; k++) {
+			double f = k * hz;
+
+			if (f > left && f <= center)
+				w[k] = (float) ((f - left) / (center - left));
+			else if (f > center && f < right)
+				w[k] = (float) ((right - f) / (right - center));
+		}
+	}
+	return 0;
+}
+
+static inline void mel_free(struct mel_filterbank *mb)
+{
+	free(mb->weights);
+	mb->weights = NULL;
+}
+
+//
+// 'coeffs' MFCCs from a magnitude spectrum (coeffs <= bands)
+//
+static inline void mfcc(struct mel_filterbank *mb, const float *mag, int coeffs, float *out)
+{
+	double energy[mb->bands];
+
+	for (int b = 0; b < mb->bands; b++) {
+		const float *w = mb->weights + b * mb->bins;
+		double sum = 0;
+
+		for (int k = 0; k < mb->bins; k++)
+			sum += w[k] * (double) mag[k] * mag[k];
+		energy[b] = log(sum + 1e-10);
+	}
+
+	for (int c = 0; c < coeffs; c++) {
+		double sum = 0;
+
+		for (int b = 0; b < mb->bands; b++)
+			sum += energy[b] * cos(M_PI * c * (b + 0.5) / mb->bands);
+		out[c] = (float) (sum * sqrt((c ? 2.0 : 1.0) / mb->bands));
+	}
+}
@@ -0,0 +1,142 @@
+//
+// Exponential sine sweeps for impulse response measurement
+//
+// Play a sweep through the thing (a room, a pedal, an amp sim),
+// record what comes back, and convolve the recording with the
+// inverse filter. What is left is the impulse response.
+//
+// The inverse filter is just the sweep backwards, with its level
+// falling 6dB/octave to make up for the sweep spending more time
+// in the low octaves (Farina's method). The nice thing about the
+// exponential sweep is that harmonic distortion ends up *before*
+// the linear impulse response, so it doesn't mess it up.
+//
+// The sweep goes from f1 to f2 in 'secs' seconds:
+//
+//	x(t) = sin(2*pi*f1*L*(exp(t/L) - 1)),  L = secs / ln(f2/f1)
+//
+static inline double sweep_rate(float f1, float f2, float secs)
+{
+	return secs / log(f2 / f1);
+}
+
+static inline int sweep_length(float secs)
+{
+	return (int) lrint(secs * SAMPLES_PER_SEC);
+}
+
+static inline float log_sweep_value(float f1, double L, int i)
+{
+	double t = i / SAMPLES_PER_SEC;
+
+	return (float) sin(2*M_PI * f1 * L * (exp(t / L) - 1));
+}
+
+static inline void log_sweep(float f1, float f2, float secs, float *buf, int nr)
+{
+	double L = sweep_rate(f1, f2, secs);
+
+	for (int i = 0; i < nr; i++)
+		buf[i] = log_sweep_value(f1, L, i);
+}
+
+//
+// The inverse filter for a sweep of 'nr' samples, scaled so
+// that the sweep convolved with it has a unity peak.
+//
+static inline void log_sweep_inverse(float f1, float f2, float secs, float *buf, int nr)
+{
+	double L = sweep_rate(f1, f2, secs) * SAMPLES_PER_SEC;
+	double gain = 0;
+
+	for (int i = 0; i < nr; i++) {
+		double x = log_sweep_value(f1, L / SAMPLES_PER_SEC, nr - 1 - i);
+		double env = exp(-i / L);
+
+		buf[i] = (float) (x * env);
+		gain += x * x * env;
+	}
+	for (int i = 0; i < nr; i++)
+		buf[i] = (float) (buf[i] / gain);
+}
+
+//
+// Deconvolve a recording of the sweep. Returns a newly allocated
+// impulse response of 'nr' samples (the same length as the
+// recording), where index 0 is where the sweep started, so the
+// position of the peak is the latency of whatever it went through.
+//
+static inline float *sweep_deconvolve(const float *rec, int nr, float f1, float f2, float secs)
+{
+	int len = sweep_length(secs);
+	int n = fft_size(nr + len);
+	float *are = calloc(n, sizeof(float)), *aim = calloc(n, sizeof(float));
+	float *bre = calloc(n, sizeof(float)), *bim = calloc(n, sizeof(float));
+	float *ir = malloc((nr + 1) * sizeof(float));
+
+	if (!are || !aim || !bre || !bim || !ir) {
+		free(ir);
+		ir = NULL;
+		goto out;
+	}
+
+	memcpy(are, rec, nr * sizeof(float));
+	log_sweep_inverse(f1, f2, secs, bre, len);
+
+	fft(are, aim, n);
+	fft(bre, bim, n);
+	for (int i = 0; i < n; i++) {
+		float re = are[i]*bre[i] - aim[i]*bim[i];
+		float im = are[i]*bim[i] + aim[i]*bre[i];
+		are[i] = re;
+		aim[i] = im;
+	}
+	ifft(are, aim, n);
+
+	// Zero time is where the end of the inverse filter lines up
+	memcpy(ir, are + len - 1, nr * sizeof(float));
+out:
+	free(are); free(aim);
+	free(bre); free(bim);
+	return ir;
+}
+
+//
+// Helper "effect" that ignores the input and plays a 20Hz .. 20kHz
+// sweep followed by a second of silence, over and over:
+//
+//	pot[0]: sweep length, 1 .. 20s (whole seconds)
+//	pot[1]: level, -60 .. 0dB
+//
+#define SWEEP_F1 20
+#define SWEEP_F2 20000
+
+static struct {
+	float secs, level;
+	int pos;
+} sweep;
+
+static inline void sweep_describe(float pot[4])
+{
+	fprintf(stderr, " length=%g s", rintf(linear(pot[0], 1, 20)));
+	fprintf(stderr, " level=%g dB\n", linear(pot[1], -60, 0));
+}
+
+static inline void sweep_init(float pot[4])
+{
+	sweep.secs = rintf(linear(pot[0], 1, 20));
+	sweep.level = db_to_linear(linear(pot[1], -60, 0));
+}
+
+static inline float sweep_step(float in)
+{
+	int len = sweep_length(sweep.secs);
+	float val = 0;
+
+	if (sweep.pos >= len + SAMPLES_PER_SEC)
+		sweep.pos = 0;
+	if (sweep.pos < len)
+		val = log_sweep_value(SWEEP_F1, sweep_rate(SWEEP_F1, SWEEP_F2, sweep.secs), sweep.pos);
+	sweep.pos++;
+	return sweep.level * val;
+}
@@ -0,0 +1,53 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../fft.h"
+
+#define NR 48000
+#define EDGE 2400
+
+static float samples[NR], amp[NR], phase[NR];
+
+// 0.25 .. 0.75 at 'mod' Hz
+static double modulator(int i, double mod)
+{
+	return 0.5 + 0.25 * sin(2*M_PI*mod*i/SAMPLES_PER_SEC);
+}
+
+int main(int argc, char **argv)
+{
+	double carrier = 1000, mod = 5;
+	double max = 0, unwrapped = 0, sx = 0, sy = 0, sxx = 0, sxy = 0;
+	int n = 0;
+
+	for (int i = 0; i < NR; i++)
+		samples[i] = (float) (modulator(i, mod) * sin(2*M_PI*carrier*i/SAMPLES_PER_SEC));
+
+	if (analytic_signal(samples, NR, amp, phase) < 0) {
+		printf("analytic_signal failed\n");
+		return 1;
+	}
+
+	// The envelope should be the modulator, and the unwrapped
+	// phase a straight line whose slope is the carrier
+	for (int i = EDGE; i < NR - EDGE; i++) {
+		max = fmax(max, fabs(amp[i] - modulator(i, mod)));
+		if (i > EDGE) {
+			double d = phase[i] - phase[i-1];
+			unwrapped += d - 2*M_PI * round(d / (2*M_PI));
+		}
+		sx += i; sy += unwrapped;
+		sxx += (double) i * i; sxy += i * unwrapped;
+		n++;
+	}
+	double slope = (n * sxy - sx * sy) / (n * sxx - sx * sx);
+
+	printf("AM tone envelope: max error %.1f dB (expected < -60)\n", 20*log10(max));
+	printf("AM tone phase slope: %.3f Hz (expected %g)\n", slope * SAMPLES_PER_SEC / (2*M_PI), carrier);
+	return 0;
+}
@@ -0,0 +1,200 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+#include <ctype.h>
+#include <limits.h>
+#include <fcntl.h>
+#include <unistd.h>
+#include <sys/stat.h>
+#include <sys/mman.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../noise.h"
+#include "../audiofile.h"
+#include "../sidecar.h"
+#include "../hash.h"
+#include "../seekable.h"
+
+#define NR 4800
+static float samples[NR], white[NR];
+static unsigned char buf[NR * 8];
+
+// Encode 'x' in the given format, and see what the guess says
+static const char *guess(const float *x, enum sample_type type, int big_endian, char *name)
+{
+	struct audio_format fmt = { type, big_endian, 1, 0 };
+
+	encode_samples(buf, x, NR, &fmt);
+	fmt = (struct audio_format) AUDIO_FORMAT_RAW;
+	if (guess_raw_format(buf, NR * sample_bytes(type), &fmt) < 0)
+		return "none";
+	return raw_format_name(&fmt, name);
+}
+
+//
+// Map a stereo s16 file, and walk through it in chunks: the left
+// channel should be the test signal and the right one its
+// inverse.
+//
+static void chunk_test(void)
+{
+	struct audio_format fmt = { sample_s16, 0, 2, 0 };
+	static float stereo[2 * NR];
+	struct audio_file file;
+	struct audio_chunk c;
+	FILE *tmp = tmpfile();
+	int chunks = 0;
+	size_t frames = 0;
+	double max = 0;
+
+	for (int i = 0; i < NR; i++) {
+		stereo[2*i] = samples[i];
+		stereo[2*i+1] = -samples[i];
+	}
+	encode_samples(buf, stereo, 2 * NR, &fmt);
+	if (!tmp || fwrite(buf, frame_bytes(&fmt), NR, tmp) != NR || fflush(tmp) ||
+	    audio_file_map(&file, fileno(tmp)) < 0) {
+		printf("Chunks: can't map test file\n");
+		return;
+	}
+	audio_file_set_format(&file, &fmt, 0, file.len);
+
+	for_each_audio_chunk(&file, &c, 1000) {
+		for (int i = 0; i < c.frames; i++) {
+			max = fmax(max, fabs(audio_file_sample(&file, c.pos + i, 0) - samples[c.pos + i]));
+			max = fmax(max, fabs(audio_file_sample(&file, c.pos + i, 1) + samples[c.pos + i]));
+		}
+		frames += c.frames;
+		chunks++;
+	}
+	printf("Chunks: %d, %zu frames, max error %.1f dB\n", chunks, frames, 20 * log10(max + 1e-20));
+	audio_file_unmap(&file);
+
+	// Now just frames 1234..2233, which isn't page aligned
+	size_t offset = 1234 * frame_bytes(&fmt), size = 1000 * frame_bytes(&fmt);
+	float part[2000];
+
+	max = 0;
+	if (audio_file_map_range(&file, fileno(tmp), offset, size) < 0) {
+		printf("Range: can't map\n");
+		fclose(tmp);
+		return;
+	}
+	audio_file_set_format(&file, &fmt, offset, size);
+	int nr = audio_file_read(&file, 0, part, 1000);
+	for (int i = 0; i < nr; i++)
+		max = fmax(max, fabs(part[2*i] - samples[1234 + i]));
+	printf("Range: %d frames (%zu bytes mapped), max error %.1f dB\n", nr, file.len, 20 * log10(max + 1e-20));
+	audio_file_unmap(&file);
+	fclose(tmp);
+}
+
+int main(int argc, char **argv)
+{
+	static const char *const names[] = { "s16", "s24", "s32", "f32", "f64" };
+	static const struct {
+		const char *name, *magic;
+	} files[] = {
+		{ "WAV", "RIFF" }, { "AIFF", "FORM" }, { "FLAC", "fLaC" }, { "Ogg", "OggS" },
+		{ "MP3", "ID3\3" }, { "M4A", "\0\0\0\30ftyp" }, { "CAF", "caff" },
+		{ "zstd", "\x28\xb5\x2f\xfd" }, { "raw", "\1\2\3\4" },
+	};
+	struct noise_gen gen;
+
+	noise_seed(&gen, 1);
+	for (int i = 0; i < NR; i++) {
+		samples[i] = (float) (0.3 * sin(2*M_PI*440*i/SAMPLES_PER_SEC) +
+			0.1 * sin(2*M_PI*3000*i/SAMPLES_PER_SEC));
+		white[i] = 0.3f * noise_next(&gen, noise_white);
+	}
+
+	for (int type = 0; type < ARRAY_SIZE(names); type++) {
+		for (int be = 0; be < 2; be++) {
+			char name[6];
+			printf("%s%s: guessed %s\n", names[type], be ? "be" : "le",
+				guess(samples, type, be, name));
+		}
+	}
+	char name[6];
+	printf("white noise: guessed %s\n", guess(white, sample_s32, 0, name));
+
+	for (int i = 0; i < ARRAY_SIZE(files); i++) {
+		static const char *const containers[] = { "raw", "wav", "aiff", "caf", "flac", "ogg", "mp3", "mp4", "zstd" };
+		unsigned char hdr[16] = { 0 };
+
+		memcpy(hdr, files[i].magic, 8);
+		printf("%s header: %s\n", files[i].name, containers[audio_sniff(hdr, sizeof(hdr))]);
+	}
+	chunk_test();
+
+	// Over 4GB turns the header into RF64
+	struct audio_format wav = { sample_s24, 0, 2, 48000 };
+	unsigned char hdr[WAV_HEADER_SIZE];
+	const unsigned char *ds64;
+	size_t ds64_size;
+
+	for (u64 bytes = 6000000; bytes < 10000000000ull; bytes *= 1000) {
+		wav_header(hdr, &wav, bytes);
+		ds64 = riff_chunk(hdr, sizeof(hdr), "ds64", &ds64_size);
+		printf("Header for %llu bytes: %.4s, data size %llu\n", bytes, hdr,
+			ds64 ? load_bytes(ds64 + 8, 8, 0) : load_bytes(hdr + 76, 4, 0));
+	}
+
+	// 44100 as an 80-bit extended float, the way AIFF has it
+	static const unsigned char rate[10] = { 0x40, 0x0e, 0xac, 0x44 };
+	printf("AIFF rate: %g\n", aiff_rate(rate));
+
+	// Reference xxHash values: ef46db3751d8e999, 44bc2cf5ad770999, fbcea83c8a378bf1
+	static const char *const strings[] = { "", "abc", "Nobody inspects the spammish repetition" };
+	for (int i = 0; i < ARRAY_SIZE(strings); i++)
+		printf("XXH64 '%s': %016llx\n", strings[i], xxh64(strings[i], strlen(strings[i]), 0));
+
+	memcpy(white, samples, sizeof(white));
+	white[3000] = nextafterf(white[3000], 1);
+	printf("First difference: %d\n", first_difference(samples, NR, white, NR));
+
+	static const char *const sidecars[] = {
+		"{ \"rate\": 44100, \"channels\": 2, \"format\": \"s24be\", \"notes\": \"\\\"DI\\\"\\tneck\" }",
+		"{ \"format\": \"f32le\", \"gain\": -3.5 }",
+		"{ \"rate\": -1 }",
+		"{ \"format\": \"s16le\" ",
+	};
+	for (int i = 0; i < ARRAY_SIZE(sidecars); i++) {
+		struct audio_format fmt = AUDIO_FORMAT_RAW;
+		char notes[SIDECAR_NOTES] = "", name[6];
+		int ret = sidecar_parse(sidecars[i], &fmt, notes, sizeof(notes));
+
+		printf("Sidecar %d: %s %s %d channels, %g Hz, notes '%s'\n", i,
+			ret < 0 ? "bad," : "ok,", raw_format_name(&fmt, name), fmt.channels, fmt.rate, notes);
+	}
+
+	// A fake archive of three frames: the seek table should come back
+	struct zstd_frame frames[3] = { { 0, 100, 0, 4000 }, { 0, 50, 0, 4000 }, { 0, 70, 0, 1000 } }, *table;
+	size_t table_len;
+	unsigned char *seek = zstd_seek_table_build(frames, 3, &table_len);
+	int first, last;
+
+	memset(buf, 0, 220);
+	memcpy(buf + 220, seek, table_len);
+	free(seek);
+	int nr = zstd_seek_table(buf, 220 + table_len, &table);
+	printf("Seek table: %d frames\n", nr);
+	for (int i = 0; i < nr; i++)
+		printf("  %llu+%llu: %llu+%llu\n", table[i].offset, table[i].size, table[i].start, table[i].len);
+	zstd_frames_for(table, nr, 3000, 5000, &first, &last);
+	printf("Bytes 3000-5000 in frames %d-%d\n", first, last);
+	zstd_frames_for(table, nr, 8000, ~0ull, &first, &last);
+	printf("Bytes 8000- in frames %d-%d\n", first, last);
+	printf("Truncated table: %d\n", zstd_seek_table(buf, 219 + table_len, &table));
+
+	// Too many samples for an 'int' has to be refused before
+	// anything gets read (so 'buf' doesn't have to be that big)
+	struct audio_format stereo = { sample_s16, 0, 2, 48000 };
+	int got;
+	float *huge = decode_samples(buf, (size_t) INT_MAX * 2 + 4, &stereo, &got);
+	printf("INT_MAX+1 stereo samples: %s, frames %d\n", huge ? "decoded" : "refused", got);
+	return 0;
+}
@@ -0,0 +1,49 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../fft.h"
+#include "../correlate.h"
+
+#define NR 48000
+
+static float left[NR], right[NR], inverted[NR], unrelated[NR];
+
+// Uniform white noise, -1 .. 1
+static float noise(u32 *state)
+{
+	*state = *state * 1664525 + 1013904223;
+	return (float) ((s32) *state / 2147483648.0);
+}
+
+// The block correlation, and where the streaming meter ended up
+static void report(const char *name, const float *r, float expect)
+{
+	struct correlation_meter m;
+	float c = 0;
+
+	correlation_meter_init(&m, 300);
+	for (int i = 0; i < NR; i++)
+		c = correlation_meter_step(&m, left[i], r[i]);
+	printf("%s: correlation %+.3f, meter %+.3f (expected %+g)\n", name,
+		phase_correlation(left, r, NR), c, expect);
+}
+
+int main(int argc, char **argv)
+{
+	u32 a = 1, b = 12345;
+
+	for (int i = 0; i < NR; i++) {
+		left[i] = right[i] = 0.5f * noise(&a);
+		inverted[i] = -left[i];
+		unrelated[i] = 0.5f * noise(&b);
+	}
+	report("L=R", right, 1);
+	report("L=-R", inverted, -1);
+	report("unrelated noise", unrelated, 0);
+	return 0;
+}
@@ -0,0 +1,51 @@
+#include <stdio.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../biquad.h"
+#include "../filter.h"
+
+static float db(struct filter_coeff *f, float freq)
+{
+	return 20 * log10f(filter_response(f, freq));
+}
+
+int main(int argc, char **argv)
+{
+	struct filter_coeff f;
+
+	// -3dB at the cutoff, and 6dB/octave/order beyond it
+	for (int order = 1; order <= 8; order++) {
+		butterworth_lowpass(&f, 1000, order);
+		printf("Butterworth LP%d: %6.2f dB at 1kHz, %7.2f dB at 4kHz (%d sections)\n",
+			order, db(&f, 1000), db(&f, 4000), f.nr);
+	}
+	butterworth_highpass(&f, 1000, 3);
+	printf("Butterworth HP3: %6.2f dB at 1kHz, %7.2f dB at 250Hz, %6.2f dB at 10kHz\n",
+		db(&f, 1000), db(&f, 250), db(&f, 10000));
+
+	// The ripple, and passing through -ripple at the cutoff
+	for (int order = 3; order <= 4; order++) {
+		float min = 0, max = -100;
+		chebyshev_lowpass(&f, 1000, order, 1);
+		for (float freq = 10; freq < 1000; freq += 10) {
+			min = fminf(min, db(&f, freq));
+			max = fmaxf(max, db(&f, freq));
+		}
+		printf("Chebyshev LP%d 1dB: passband %.2f .. %.2f dB, %.2f dB at 1kHz\n",
+			order, min, max, db(&f, 1000));
+	}
+	chebyshev_highpass(&f, 1000, 4, 0.5);
+	printf("Chebyshev HP4 0.5dB: %.2f dB at 1kHz, %.2f dB at 250Hz\n",
+		db(&f, 1000), db(&f, 250));
+
+	// Both halves -6dB at the crossover
+	linkwitz_riley_lowpass(&f, 2000, 4);
+	printf("Linkwitz-Riley LR4: LP %.2f dB", db(&f, 2000));
+	linkwitz_riley_highpass(&f, 2000, 4);
+	printf(", HP %.2f dB at the 2kHz crossover\n", db(&f, 2000));
+	return 0;
+}
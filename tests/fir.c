@@ -0,0 +1,51 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../fft.h"
+#include "../fir.h"
+
+#define NR 20000
+#define TAPS 511
+static float samples[NR], h[TAPS];
+
+static float db(float freq)
+{
+	return 20 * log10f(fir_response(h, TAPS, freq) + 1e-20f);
+}
+
+int main(int argc, char **argv)
+{
+	double max = 0;
+	float *out;
+	int out_nr;
+
+	fir_lowpass(h, TAPS, 1000);
+	printf("Lowpass 1kHz:  %6.2f dB at 500Hz, %6.2f dB at 1kHz, %6.1f dB at 2kHz\n",
+		db(500), db(1000), db(2000));
+	fir_highpass(h, TAPS, 1000);
+	printf("Highpass 1kHz: %6.1f dB at 500Hz, %6.2f dB at 1kHz, %6.2f dB at 2kHz\n",
+		db(500), db(1000), db(2000));
+	fir_bandpass(h, TAPS, 1000, 4000);
+	printf("Bandpass 1-4kHz: %6.1f dB at 300Hz, %6.2f dB at 2kHz, %6.1f dB at 10kHz\n",
+		db(300), db(2000), db(10000));
+
+	// Compare the FFT convolution against the direct one
+	for (int i = 0; i < NR; i++)
+		samples[i] = (float) sin(i * 0.01) * (float) (i % 37) / 37;
+	out = fft_convolve(samples, NR, h, TAPS, &out_nr);
+	for (int i = 0; i < out_nr; i++) {
+		double sum = 0;
+		for (int j = 0; j < TAPS; j++)
+			if (i - j >= 0 && i - j < NR)
+				sum += (double) samples[i - j] * h[j];
+		max = fmax(max, fabs(out[i] - sum));
+	}
+	printf("FFT convolution: %d samples, max error %.1f dB\n", out_nr, 20 * log10(max));
+	free(out);
+	return 0;
+}
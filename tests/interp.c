@@ -0,0 +1,84 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+
+float sample_array[SAMPLE_ARRAY_SIZE];
+int sample_array_index;
+
+#define SINC_HALF 16
+#define LEN 4800
+
+static float samples[LEN];
+
+// A band-limited test signal: a few tones well below Nyquist
+static double signal(double t)
+{
+	return 0.4 * sin(2*M_PI*440*t/SAMPLES_PER_SEC) +
+		0.3 * sin(2*M_PI*2500*t/SAMPLES_PER_SEC + 1) +
+		0.2 * sin(2*M_PI*6000*t/SAMPLES_PER_SEC + 2);
+}
+
+static void update(double *max, double val)
+{
+	*max = fmax(*max, fabs(val));
+}
+
+static double db(double val)
+{
+	return 20*log10(val);
+}
+
+int main(int argc, char **argv)
+{
+	double lin = 0, herm = 0, sinc = 0, lin_herm = 0, herm_sinc = 0, reads = 0;
+
+	for (int i = 0; i < LEN; i++)
+		samples[i] = (float) signal(i);
+
+	// Max error against the exact signal in between samples,
+	// and how far the three helpers are from each other
+	for (int i = SINC_HALF; i < LEN - SINC_HALF; i++) {
+		const float *x = samples + i;
+
+		for (int f = 1; f < 10; f++) {
+			float frac = f / 10.0f;
+			double exact = signal(i + frac);
+			double a = interp_linear(x[0], x[1], frac);
+			double b = interp_hermite(x[-1], x[0], x[1], x[2], frac);
+			double c = interp_sinc(x, SINC_HALF, frac);
+
+			update(&lin, a - exact);
+			update(&herm, b - exact);
+			update(&sinc, c - exact);
+			update(&lin_herm, a - b);
+			update(&herm_sinc, b - c);
+		}
+	}
+
+	printf("interpolation error: linear %.1f dB (expected ~-30), hermite %.1f dB (expected ~-55), sinc %.1f dB (expected < -80)\n",
+		db(lin), db(herm), db(sinc));
+	printf("difference: linear vs hermite %.1f dB, hermite vs sinc %.1f dB\n",
+		db(lin_herm), db(herm_sinc));
+
+	// The delay line reads have to agree on which way 'frac' goes,
+	// so a delay of 'd + frac' is the same signal whichever one is
+	// used (and that's 'd - frac' samples back, see util.h)
+	for (int i = 0; i < LEN; i++)
+		sample_array_write(samples[i]);
+	for (int d = 2; d < 100; d++) {
+		for (int f = 0; f < 10; f++) {
+			float delay = d + f / 10.0f;
+			double exact = signal(LEN - 1 - d + f / 10.0);
+
+			update(&reads, sample_array_read(delay) - exact);
+			update(&reads, sample_array_read_cubic(delay) - exact);
+		}
+	}
+	printf("delay line reads: max error %.1f dB (expected ~-30)\n", db(reads));
+	return 0;
+}
@@ -0,0 +1,93 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../measure.h"
+
+// Two seconds worth of samples
+#define NR 96000
+static float samples[NR];
+
+// 0.5 amplitude 1kHz tone with 1% second and 0.5% third harmonic
+// and a bit of DC offset and noise on top
+static void make_tone(void)
+{
+	srandom(1);
+	for (int i = 0; i < NR; i++) {
+		double t = 2*M_PI*1000*i/SAMPLES_PER_SEC;
+		double noise = (random() / (double) RAND_MAX - 0.5) * 0.001;
+		samples[i] = 0.5*sin(t) + 0.005*sin(2*t) + 0.0025*sin(3*t) + 0.01 + noise;
+	}
+}
+
+int main(int argc, char **argv)
+{
+	struct thd thd;
+
+	make_tone();
+	measure_thd(samples, NR, 1000, &thd);
+	printf("THD %.4f%% (expected %.4f%%), THD+N %.4f%%\n",
+		100 * thd.harmonics / thd.fundamental,
+		100 * sqrt(0.01*0.01 + 0.005*0.005),
+		100 * thd.residual / thd.fundamental);
+	printf("SNR %.1f dB (expected %.1f dB), SINAD %.1f dB\n",
+		linear_to_db(thd_snr(&thd), -200),
+		linear_to_db(0.5 / M_SQRT2 / (0.0005 / sqrt(3)), -200),
+		linear_to_db(thd_sinad(&thd), -200));
+
+	printf("Zero crossing rate %.1f/s, first at %d, last before %d at %d\n",
+		zero_crossing_rate(samples, NR),
+		next_zero_crossing(samples, NR, 0, 1),
+		NR / 2, next_zero_crossing(samples, NR, NR / 2, -1));
+	printf("Last crossing %d, searching back from past the end %d\n",
+		next_zero_crossing(samples, NR, NR - 1, -1),
+		next_zero_crossing(samples, NR, NR + 100, -1));
+
+	// A full-scale s32 square wave with a 1 LSB wobble: the
+	// double path sees the wobble, the float conversion can't
+	static double dsamples[NR];
+	for (int i = 0; i < NR; i++) {
+		s32 v = (i & 64) ? 0x7ffffffe + (i & 1) : -0x7ffffffe - (i & 1);
+		dsamples[i] = s32_to_f64(v);
+		samples[i] = (float) dsamples[i];
+	}
+	printf("RMS f64 %.12f (%.9f dBFS), float %.12f, peak f64 %.12f\n",
+		rms_f64(dsamples, NR), dbfs_f64(rms_f64(dsamples, NR), -200),
+		rms(samples, NR), peak_f64(dsamples, NR));
+
+	// The tone's DC offset, gone either way (and nothing to do
+	// with nothing, without looking at x[0])
+	make_tone();
+	remove_dc(samples, NR, 0);
+	printf("DC after remove_dc %.6f (was 0.01)", dc_offset(samples, NR));
+	make_tone();
+	remove_dc(samples, NR, 5);
+	remove_dc(NULL, 0, 5);
+	printf(", after a 5Hz highpass %.6f\n", dc_offset(samples + NR / 2, NR / 2));
+
+	// The vector loops against the plain double ones, on six
+	// minutes of noise with an offset and every 7th sample
+	// clipped either way
+	int long_nr = 1 << 24;
+	float *noise = malloc(long_nr * sizeof(float));
+	double sum = 0, sum2 = 0;
+	struct stats st;
+
+	srandom(2);
+	for (int i = 0; i < long_nr; i++) {
+		noise[i] = i % 7 ? 0.1 + 0.3 * (random() / (double) RAND_MAX - 0.5) : (i & 1 ? -1.5 : 1.5);
+		sum += noise[i];
+		sum2 += (double) noise[i] * noise[i];
+	}
+	measure_stats(noise, long_nr, &st);
+	printf("Long noise: rms %.1e, stats rms %.1e, dc %.1e off the double loop, %d clipped (expected %d)\n",
+		fabs(rms(noise, long_nr) / sqrt(sum2 / long_nr) - 1),
+		fabs(st.rms / sqrt(sum2 / long_nr) - 1),
+		fabs(st.dc / (sum / long_nr) - 1), st.clipped, (long_nr + 6) / 7);
+	free(noise);
+	return 0;
+}
@@ -0,0 +1,57 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../fft.h"
+#include "../ola.h"
+
+#define NR 48000
+static float samples[NR];
+
+// Zero everything above 'data' Hz
+static void brickwall(float *re, float *im, int size, void *data)
+{
+	int cutoff = (int) (*(float *) data * size / SAMPLES_PER_SEC);
+
+	for (int k = cutoff + 1; k <= size - cutoff - 1; k++)
+		re[k] = im[k] = 0;
+}
+
+// Max difference (in dB) between the output and the delayed input
+static double ola_error(int size, int hop, int weighted, ola_fn fn, void *data, const float *expect)
+{
+	struct ola o;
+	double max = 0;
+
+	if (ola_init(&o, size, hop, weighted, fn, data) < 0)
+		return 0;
+	for (int i = 0; i < NR; i++) {
+		float out = ola_step(&o, samples[i]);
+		if (i >= 2 * size)
+			max = fmax(max, fabs(out - expect[i - size]));
+	}
+	ola_free(&o);
+	return 20 * log10(max + 1e-20);
+}
+
+int main(int argc, char **argv)
+{
+	static float low[NR];
+	float cutoff = 2000;
+
+	for (int i = 0; i < NR; i++) {
+		samples[i] = (float) (0.5 * sin(2*M_PI*440*i/SAMPLES_PER_SEC) +
+			0.25 * sin(2*M_PI*5000*i/SAMPLES_PER_SEC));
+		low[i] = (float) (0.5 * sin(2*M_PI*440*i/SAMPLES_PER_SEC));
+	}
+
+	printf("Identity, OLA  1024/512: %6.1f dB\n", ola_error(1024, 512, 0, NULL, NULL, samples));
+	printf("Identity, WOLA 1024/256: %6.1f dB\n", ola_error(1024, 256, 1, NULL, NULL, samples));
+	printf("Identity, WOLA 4096/1024: %6.1f dB\n", ola_error(4096, 1024, 1, NULL, NULL, samples));
+	printf("2kHz brickwall, WOLA 1024/256: %6.1f dB\n", ola_error(1024, 256, 1, brickwall, &cutoff, low));
+	return 0;
+}
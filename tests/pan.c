@@ -0,0 +1,41 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+
+typedef void (*pan_fn)(float pos, float *left, float *right);
+
+static void report(const char *name, pan_fn pan, float center_db)
+{
+	float l, r, el, er;
+	double worst = 0;
+
+	pan(0, &l, &r);
+	printf("%s: center %.2f / %.2f dB (expected %.1f),", name,
+		linear_to_db(l, -200), linear_to_db(r, -200), center_db);
+
+	// Unity on the side it's panned to, silence on the other
+	pan(-1, &l, &r);
+	pan(1, &el, &er);
+	printf(" hard left %.4f / %.4f, hard right %.4f / %.4f", l, r, el, er);
+
+	// And the two sides should mirror each other
+	for (float pos = -1; pos <= 1; pos += 0.01f) {
+		pan(pos, &l, &r);
+		pan(-pos, &el, &er);
+		worst = fmax(worst, fmax(fabsf(l - er), fabsf(r - el)));
+	}
+	printf(", asymmetry %.4f\n", worst);
+}
+
+int main(int argc, char **argv)
+{
+	report("linear", pan_linear, -6.0);
+	report("constant power", pan_constant_power, -3.0);
+	report("compromise", pan_compromise, -4.5);
+	return 0;
+}
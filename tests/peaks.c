@@ -0,0 +1,290 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+#include <limits.h>
+#include <unistd.h>
+#include <fcntl.h>
+#include <sys/stat.h>
+#include <sys/mman.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../measure.h"
+#include "../noise.h"
+#include "../audiofile.h"
+#include "../hash.h"
+#include "../peaks.h"
+#include "../render.h"
+
+#define NR (10 * 48000)
+static float samples[NR];
+static const struct peak_samples floats = peak_floats(samples);
+
+//
+// Look up random ranges of 'len' samples: the min and max should
+// all be exact, and the mean and RMS only off by rounding.
+//
+static void range_test(const struct peak_pyramid *p, struct noise_gen *gen, size_t len)
+{
+	double error = 0;
+	int wrong = 0;
+
+	for (int i = 0; i < 1000; i++) {
+		size_t from = (size_t) ((noise_next(gen, noise_white) + 1) * 0.5 * (NR - len));
+		struct peak exact = peak_scan(&floats, from, len);
+		struct peak got = peak_range(p, &floats, from, from + len);
+
+		if (got.min != exact.min || got.max != exact.max)
+			wrong++;
+		error = fmax(error, fabs(peak_mean(&got, len) - peak_mean(&exact, len)));
+		error = fmax(error, fabs(peak_rms(&got, len) - peak_rms(&exact, len)));
+	}
+	printf("%7zu samples: %d of 1000 wrong, mean/rms error %.1f dB\n", len, wrong, 20 * log10(error + 1e-20));
+}
+
+//
+// The same samples in some raw format (as the second channel of
+// two): the pyramid should be the same, give or take quantization.
+//
+static void raw_test(const struct peak_pyramid *p, enum sample_type type, int big_endian)
+{
+	struct audio_format fmt = { type, big_endian, 2, 0 };
+	static float stereo[2 * NR];
+	unsigned char *buf = malloc((size_t) NR * frame_bytes(&fmt));
+	struct peak_samples s = { buf, &fmt, 1, 0 };
+	struct peak_pyramid raw;
+	struct peak a[100], b[100];
+	double error = 0;
+	char name[6];
+
+	if (!buf)
+		return;
+	for (int i = 0; i < NR; i++) {
+		stereo[2*i] = 0;
+		stereo[2*i+1] = samples[i];
+	}
+	encode_samples(buf, stereo, 2 * NR, &fmt);
+	if (peak_pyramid_build(&raw, &s, NR) < 0)
+		return;
+
+	peak_columns(p, &floats, 1000, NR, 100, a);
+	peak_columns(&raw, &s, 1000, NR, 100, b);
+	for (int c = 0; c < 100; c++) {
+		error = fmax(error, fabs(a[c].min - b[c].min));
+		error = fmax(error, fabs(a[c].max - b[c].max));
+		error = fmax(error, fabs(peak_rms(a + c, NR / 100) - peak_rms(b + c, NR / 100)));
+	}
+	printf("%s: max error %.1f dB\n", raw_format_name(&fmt, name), 20 * log10(error + 1e-20));
+	peak_pyramid_free(&raw);
+	free(buf);
+}
+
+// The samples as the second of three interleaved float channels
+static void interleaved_test(const struct peak_pyramid *p)
+{
+	float *x = calloc(3 * (size_t) NR, sizeof(float));
+	struct peak_samples s = peak_interleaved(x, 3, 1);
+	struct peak_pyramid lane;
+	int same = 1;
+
+	if (!x)
+		return;
+	for (int i = 0; i < NR; i++) {
+		x[3*i] = 1;
+		x[3*i+1] = samples[i];
+		x[3*i+2] = -1;
+	}
+	if (peak_pyramid_build(&lane, &s, NR) < 0)
+		return;
+	for (int l = 0; l < p->levels; l++)
+		same &= !memcmp(p->level[l], lane.level[l], p->len[l] * sizeof(struct peak));
+	printf("Channel 1 of 3: %s\n", same ? "same" : "different");
+	peak_pyramid_free(&lane);
+	free(x);
+}
+
+// Growing a pyramid in uneven steps should end up with the same one
+static void extend_test(const struct peak_pyramid *p, struct noise_gen *gen)
+{
+	struct peak_pyramid grown;
+	size_t nr = 0;
+	int steps = 0, same;
+
+	memset(&grown, 0, sizeof(grown));
+	while (nr < NR) {
+		nr += (size_t) ((noise_next(gen, noise_white) + 1) * 10000);
+		if (nr > NR)
+			nr = NR;
+		if (peak_pyramid_extend(&grown, &floats, nr) < 0)
+			return;
+		steps++;
+	}
+	same = grown.levels == p->levels;
+	for (int l = 0; same && l < p->levels; l++)
+		same = grown.len[l] == p->len[l] &&
+			!memcmp(grown.level[l], p->level[l], p->len[l] * sizeof(struct peak));
+	printf("Extended in %d steps: %s\n", steps, same ? "same" : "different");
+	peak_pyramid_free(&grown);
+}
+
+// Pushing random-sized chunks through a bucketer, vs scanning buckets
+static void bucketer_test(struct noise_gen *gen, size_t size)
+{
+	struct peak_bucketer b;
+	struct peak out[100], last;
+	size_t pos = 0;
+	int buckets = 0, wrong = 0;
+
+	peak_bucketer_init(&b, size);
+	while (pos < NR) {
+		size_t len = (size_t) ((noise_next(gen, noise_white) + 1) * 3000);
+		int done;
+
+		if (len > NR - pos)
+			len = NR - pos;
+		done = peak_bucketer_push(&b, samples + pos, len, out, len / size + 1);
+		for (int i = 0; i < done; i++, buckets++) {
+			struct peak exact = peak_scan(&floats, buckets * size, size);
+
+			// The sums get added up in a different order
+			if (exact.min != out[i].min || exact.max != out[i].max ||
+			    fabs(exact.sum - out[i].sum) > 1e-9 || fabs(exact.squares - out[i].squares) > 1e-9)
+				wrong++;
+		}
+		pos += len;
+	}
+	size_t left = peak_bucketer_flush(&b, &last);
+	printf("Bucketer, %zu samples: %d buckets (%d wrong) and %zu left over\n", size, buckets, wrong, left);
+}
+
+// A tiny rendering as text: '#' is peak, '+' is RMS, '!' is hot
+static void render_test(const struct peak_pyramid *p, float db_range, float hot_db)
+{
+	struct render_options opt = { 24, 9, 0, 1, 2, db_range, 3, hot_db };
+	struct render_column c[24];
+	struct peak col[24];
+	unsigned char rgb[24 * 9 * 3];
+
+	peak_columns(p, &floats, 0, NR, 24, col);
+	for (int i = 0; i < 24; i++)
+		c[i] = (struct render_column) { col[i].min, col[i].max, peak_rms(col + i, NR / 24) };
+	render_raster(c, &opt, rgb);
+
+	printf("Rendered, %s%s:\n", db_range ? "dB" : "linear", hot_db ? ", hot above -6dBFS" : "");
+	for (int y = 0; y < 9; y++) {
+		printf("  |");
+		for (int x = 0; x < 24; x++)
+			putchar(" #+!"[rgb[(y * 24 + x) * 3 + 2]]);
+		printf("|\n");
+	}
+}
+
+// The same as text, in each style
+static void text_test(const struct peak_pyramid *p, enum render_style style)
+{
+	struct render_options opt = RENDER_OPTIONS;
+	int cells = 24, cols = cells * render_dots_x(style);
+	struct render_column c[48];
+	struct peak col[48];
+
+	peak_columns(p, &floats, 0, NR, cols, col);
+	for (int i = 0; i < cols; i++)
+		c[i] = (struct render_column) { col[i].min, col[i].max, peak_rms(col + i, NR / cols) };
+	printf("Drawn as text, style %d:\n", style);
+	render_text(stdout, c, cells, 3, style, &opt);
+}
+
+int main(int argc, char **argv)
+{
+	struct peak_pyramid p;
+	struct peak col[8];
+	struct noise_gen gen;
+
+	// A decaying 100Hz tone, with a single spike in the middle
+	for (int i = 0; i < NR; i++)
+		samples[i] = (float) (0.5 * exp(-i / (2.0 * SAMPLES_PER_SEC)) * sin(2*M_PI*100*i/SAMPLES_PER_SEC));
+	samples[NR / 2 + 1234] = 0.9f;
+
+	if (peak_pyramid_build(&p, &floats, NR) < 0)
+		return 1;
+	printf("Pyramid: %d levels for %zu samples (%zu + %zu entries)\n", p.levels, p.nr, p.len[0], p.len[1]);
+
+	noise_seed(&gen, 1);
+	range_test(&p, &gen, 100);
+	range_test(&p, &gen, 5000);
+	range_test(&p, &gen, 48000);
+	range_test(&p, &gen, NR - 1);
+	extend_test(&p, &gen);
+	bucketer_test(&gen, 1000);
+	bucketer_test(&gen, 4801);
+
+	peak_columns(&p, &floats, 0, NR, 8, col);
+	for (int c = 0; c < 8; c++)
+		printf("Column %d: %.4f .. %.4f, mean %.4f, rms %.4f (%.1f/%.1f dBFS)\n", c, col[c].min, col[c].max,
+			peak_mean(col + c, NR / 8), peak_rms(col + c, NR / 8),
+			peak_dbfs(col + c, -200), peak_rms_dbfs(col + c, NR / 8, -200));
+
+	render_test(&p, 0, 0);
+	render_test(&p, 48, 0);
+	render_test(&p, 0, -6);
+	text_test(&p, render_ascii);
+	text_test(&p, render_blocks);
+	text_test(&p, render_braille);
+	printf("PNG CRC of IEND: %08x\n", png_crc(0, (const unsigned char *) "IEND", 4));
+
+	interleaved_test(&p);
+	raw_test(&p, sample_s16, 1);
+	raw_test(&p, sample_s24, 0);
+	raw_test(&p, sample_f32, 0);
+	raw_test(&p, sample_f64, 1);
+
+	// Save it, and it should only load back for the same samples
+	char name[] = "/tmp/peaks-test-XXXXXX", path[PATH_MAX];
+	u64 fingerprint = peak_fingerprint(&floats, NR);
+	struct peak_pyramid cached;
+	int fd = mkstemp(name), same = 1;
+
+	if (fd < 0 || peak_cache_save(&p, name, fingerprint) < 0)
+		return 1;
+	if (peak_cache_load(&cached, name, &floats, NR) < 0) {
+		printf("Cache: didn't load\n");
+	} else {
+		for (int l = 0; l < p.levels; l++)
+			same &= !memcmp(p.level[l], cached.level[l], p.len[l] * sizeof(struct peak));
+		printf("Cache: %d levels, %s\n", cached.levels, same ? "same" : "different");
+		peak_pyramid_free(&cached);
+	}
+	printf("Cache for fewer samples: %s\n",
+		peak_cache_load(&cached, name, &floats, NR - 1) < 0 ? "stale" : "loaded");
+
+	// A cache for the first part of the samples gets the rest added
+	struct peak_pyramid part;
+	int ret;
+
+	if (peak_pyramid_build(&part, &floats, NR / 3) < 0 ||
+	    peak_cache_save(&part, name, peak_fingerprint(&floats, NR / 3)) < 0)
+		return 1;
+	peak_pyramid_free(&part);
+	ret = peak_cache_load(&cached, name, &floats, NR);
+	same = ret == 1 && cached.levels == p.levels;
+	for (int l = 0; same && l < p.levels; l++)
+		same = cached.len[l] == p.len[l] &&
+			!memcmp(p.level[l], cached.level[l], p.len[l] * sizeof(struct peak));
+	printf("Cache for the first third: %s, %s\n", ret == 1 ? "extended" : ret ? "stale" : "loaded",
+		same ? "same" : "different");
+	if (ret >= 0)
+		peak_pyramid_free(&cached);
+
+	samples[NR / 4] = 0.5f;
+	printf("Cache after changing a sample in that third: %s\n",
+		peak_cache_load(&cached, name, &floats, NR) < 0 ? "stale" : "loaded");
+
+	peak_cache_name(name, path);
+	unlink(path);
+	unlink(name);
+	close(fd);
+	peak_pyramid_free(&p);
+	return 0;
+}
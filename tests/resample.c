@@ -0,0 +1,86 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+
+#define SAMPLES_PER_SEC (48000.0)
+
+#include "../util.h"
+#include "../resample.h"
+
+// One second at up to 96kHz
+static float samples[96000];
+
+// Max error (in dB relative to full scale) of a resampled
+// tone compared to the exact one, ignoring the edges
+static double tone_error(double in_rate, double out_rate, double freq, int quality)
+{
+	int out_nr, edge = 1000;
+	double max = 0;
+	float *out;
+
+	for (int i = 0; i < in_rate; i++)
+		samples[i] = (float) (0.5 * sin(2*M_PI*freq*i/in_rate));
+
+	out = resample(samples, (int) in_rate, in_rate, out_rate, quality, &out_nr);
+	for (int i = edge; i < out_nr - edge; i++) {
+		double exact = 0.5 * sin(2*M_PI*freq*i/out_rate);
+		max = fmax(max, fabs(out[i] - exact));
+	}
+	free(out);
+	return 20*log10(max);
+}
+
+// Level of an out-of-band tone that should be filtered out
+static double alias_level(double in_rate, double out_rate, double freq, int quality)
+{
+	int out_nr;
+	double sum = 0;
+	float *out;
+
+	for (int i = 0; i < in_rate; i++)
+		samples[i] = (float) sin(2*M_PI*freq*i/in_rate);
+
+	out = resample(samples, (int) in_rate, in_rate, out_rate, quality, &out_nr);
+	for (int i = 1000; i < out_nr - 1000; i++)
+		sum += out[i] * out[i];
+	free(out);
+	return 10*log10(2 * sum / (out_nr - 2000));
+}
+
+// The push-based stream should give the same samples as the
+// offline conversion, just delayed
+static double stream_difference(double in_rate, double out_rate)
+{
+	struct resample_stream s;
+	int out_nr, count = 0;
+	float *ref, buf[8];
+	double max = 0;
+
+	for (int i = 0; i < in_rate; i++)
+		samples[i] = (float) (0.5 * sin(2*M_PI*440*i/in_rate));
+	ref = resample(samples, (int) in_rate, in_rate, out_rate, 1, &out_nr);
+
+	resample_stream_init(&s, in_rate, out_rate, 1);
+	for (int i = 0; i < in_rate; i++) {
+		int n = resample_stream_push(&s, samples[i], buf);
+		for (int j = 0; j < n && count < out_nr; j++, count++)
+			max = fmax(max, fabs(buf[j] - ref[count]));
+	}
+	resample_stream_free(&s);
+	free(ref);
+	return max;
+}
+
+int main(int argc, char **argv)
+{
+	for (int q = 0; q < 3; q++) {
+		printf("quality %d: 44.1k->48k 1kHz error %.1f dB, 48k->44.1k 10kHz error %.1f dB, 96k->48k 30kHz alias %.1f dB\n", q,
+			tone_error(44100, 48000, 1000, q),
+			tone_error(48000, 44100, 10000, q),
+			alias_level(96000, 48000, 30000, q));
+	}
+	printf("stream vs offline: 44.1k->48k max difference %g, 48k->44.1k %g\n",
+		stream_difference(44100, 48000), stream_difference(48000, 44100));
+	return 0;
+}
@@ -0,0 +1,69 @@
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define SAMPLES_PER_SEC 48000.0f
+
+#include "../util.h"
+#include "../biquad.h"
+#include "../weighting.h"
+#include "../measure.h"
+#include "../loudness.h"
+
+#define NR (20 * 48000)
+static float samples[NR];
+
+// IEC 61672 nominal A and C weighting at the octave centers
+static const struct {
+	float f, a, c;
+} nominal[] = {
+	{    31.5, -39.4, -3.0 },
+	{    63,   -26.2, -0.8 },
+	{   125,   -16.1, -0.2 },
+	{   250,    -8.6,  0.0 },
+	{   500,    -3.2,  0.0 },
+	{  1000,     0.0,  0.0 },
+	{  2000,     1.2, -0.2 },
+	{  4000,     1.0, -0.8 },
+	{  8000,    -1.1, -3.0 },
+	{ 16000,    -6.6, -8.5 },
+};
+
+int main(int argc, char **argv)
+{
+	struct weighting a, c;
+
+	weighting_a(&a);
+	weighting_c(&c);
+
+	for (int i = 0; i < ARRAY_SIZE(nominal); i++) {
+		float f = nominal[i].f;
+		float da = 20*log10f(weighting_response(&a, f));
+		float dc = 20*log10f(weighting_response(&c, f));
+		printf("%6.0f Hz: A %6.1f dB (%+5.1f)  C %6.1f dB (%+5.1f)\n",
+			f, da, da - nominal[i].a, dc, dc - nominal[i].c);
+	}
+
+	// BS.1770: a full scale 997Hz sine reads -3.01 LUFS
+	for (int i = 0; i < NR; i++)
+		samples[i] = sinf(2*M_PI*997*i/SAMPLES_PER_SEC);
+	printf("997 Hz sine at 0 dBFS: %.2f LUFS (expected -3.01)\n",
+		integrated_loudness(samples, NR));
+
+	// .. and the relative gate ignores the quiet part of this
+	for (int i = 0; i < NR/2; i++)
+		samples[i] *= 0.01f;
+	printf("Gain to -23 LUFS: %+.2f dB (expected about -20)\n",
+		normalize_to_lufs(samples, NR, -23));
+	printf("After normalizing: %.2f LUFS\n", integrated_loudness(samples, NR));
+
+	normalize_to_rms(samples, NR, -20);
+	printf("After RMS normalizing: %.2f dBFS RMS (expected -20.00)\n",
+		20*log10(rms(samples, NR)));
+
+	normalize_to_peak(samples, NR, -1);
+	printf("After peak normalizing: %.2f dBFS peak (expected -1.00)\n",
+		20*log10(peak(samples, NR)));
+	return 0;
+}
@@ -0,0 +1,79 @@
+//
+// Time positions: sample counts, seconds and "hh:mm:ss.mmm"
+//
+// Positions are kept as sample counts (64-bit, so nothing
+// wraps even for silly long captures), and only turned into
+// seconds or a timestamp for people to read.
+//
+// Parsing accepts the same things ffmpeg does for '-ss':
+//
+//	90		seconds
+//	1.5s 250ms 100us	seconds, milliseconds, microseconds
+//	1:30 0:01:30.250	[hh:]mm:ss[.fraction]
+//
+// and a leading '-' for negative positions.
+//
+#define TIMEPOS_LEN 32
+
+static inline double samples_to_secs(s64 samples)
+{
+	return samples / SAMPLES_PER_SEC;
+}
+
+static inline s64 secs_to_samples(double secs)
+{
+	return llrint(secs * SAMPLES_PER_SEC);
+}
+
+// Rounded to the nearest millisecond
+static inline char *format_timepos(s64 samples, char buf[TIMEPOS_LEN])
+{
+	s64 ms = llrint(fabs(samples_to_secs(samples)) * 1000);
+
+	snprintf(buf, TIMEPOS_LEN, "%s%02lld:%02lld:%02lld.%03lld",
+		samples < 0 ? "-" : "",
+		ms / 3600000, ms / 60000 % 60, ms / 1000 % 60, ms % 1000);
+	return buf;
+}
+
+// Returns 0 on success, -1 if it doesn't look like a time
+static inline int parse_timepos(const char *s, s64 *samples)
+{
+	double secs = 0, sign = 1;
+	int fields = 0;
+	char *end;
+
+	if (*s == '-') {
+		sign = -1;
+		s++;
+	}
+
+	for (;;) {
+		double val = strtod(s, &end);
+
+		if (end == s || val < 0 || *s == '+' || *s == '-')
+			return -1;
+		secs = secs * 60 + val;
+		fields++;
+		s = end;
+		if (*s != ':')
+			break;
+		s++;
+	}
+
+	if (fields > 3)
+		return -1;
+	if (fields == 1) {
+		if (!strcmp(s, "ms"))
+			secs /= 1000;
+		else if (!strcmp(s, "us"))
+			secs /= 1000000;
+		else if (*s && strcmp(s, "s"))
+			return -1;
+	} else if (*s) {
+		return -1;
+	}
+
+	*samples = secs_to_samples(sign * secs);
+	return 0;
+}
@@ -31,6 +31,32 @@ typedef unsigned long long u64;
 // "reasonable frequency range": 220Hz - 6.5kHz with pot center at 1kHz
 #define pot_frequency(pot)	cubic(pot, 220, 6460)
 
+//
+// Decibels. 'linear_to_db()' takes a floor, so that silence
+// (or a tiny value) doesn't turn into -inf or some silly
+// number like -700dB.
+//
+static inline float db_to_linear(float db)
+{
+	return powf(10, db * 0.05f);
+}
+
+static inline float linear_to_db(float val, float floor_db)
+{
+	val = fabsf(val);
+	if (val <= db_to_linear(floor_db))
+		return floor_db;
+	return 20 * log10f(val);
+}
+
+static inline void apply_gain_db(float *x, int nr, float db)
+{
+	float gain = db_to_linear(db);
+
+	for (int i = 0; i < nr; i++)
+		x[i] *= gain;
+}
+
 //
 // Smoothly limit x to -1 .. 1
 //
@@ -49,6 +75,82 @@ static inline u32 fraction_to_u32(float val)
 	return (u32) (val * TWO_POW_32);
 }
 
+//
+// Fractional interpolation between samples. 'frac' is 0 .. 1
+// between x0 and x1, and the cubic one also wants the samples
+// on either side (x_1 before x0, x2 after x1).
+//
+// Linear is cheap but dulls the top end a bit when the delay
+// is being modulated, the 4-point Hermite is what you want for
+// chorus/vibrato, and the windowed sinc is for when it really
+// needs to be flat (it's way too slow for the audio path).
+//
+static inline float interp_linear(float x0, float x1, float frac)
+{
+	return x0 + (x1 - x0) * frac;
+}
+
+static inline float interp_hermite(float x_1, float x0, float x1, float x2, float frac)
+{
+	float c1 = 0.5f * (x1 - x_1);
+	float c2 = x_1 - 2.5f * x0 + 2 * x1 - 0.5f * x2;
+	float c3 = 0.5f * (x2 - x_1) + 1.5f * (x0 - x1);
+
+	return ((c3 * frac + c2) * frac + c1) * frac + x0;
+}
+
+//
+// Modified Bessel function of the first kind, order zero, for
+// the Kaiser window. The series converges quickly for the
+// beta values we care about.
+//
+static inline double kaiser_i0(double x)
+{
+	double sum = 1, term = 1;
+
+	for (int k = 1; k < 50; k++) {
+		term *= (x / (2*k)) * (x / (2*k));
+		sum += term;
+		if (term < sum * 1e-12)
+			break;
+	}
+	return sum;
+}
+
+//
+// Kaiser-windowed sinc tap 't' samples from the center of a
+// kernel that is 'half' samples wide on each side. 'fc' is the
+// cutoff relative to Nyquist. The resampler builds its phase
+// tables from this too.
+//
+static inline double windowed_sinc(double t, int half, double fc, double beta)
+{
+	double x = t / half;
+	double w = fabs(x) < 1 ? kaiser_i0(beta * sqrt(1 - x*x)) / kaiser_i0(beta) : 0;
+	double s = t ? sin(M_PI * fc * t) / (M_PI * t) : fc;
+
+	return s * w;
+}
+
+//
+// 'x' points at x0, and x[1-half] .. x[half] have to be valid.
+// Normalized to unity gain at DC.
+//
+#define INTERP_SINC_BETA 8.6
+
+static inline float interp_sinc(const float *x, int half, float frac)
+{
+	double sum = 0, gain = 0;
+
+	for (int j = 1 - half; j <= half; j++) {
+		double k = windowed_sinc(j - frac, half, 1, INTERP_SINC_BETA);
+
+		sum += x[j] * k;
+		gain += k;
+	}
+	return (float) (sum / gain);
+}
+
 // Max ~1.25s delays at ~52kHz
 #define SAMPLE_ARRAY_SIZE 65536
 #define SAMPLE_ARRAY_MASK (SAMPLE_ARRAY_SIZE-1)
@@ -61,6 +163,11 @@ static inline void sample_array_write(float val)
 	sample_array[idx] = val;
 }
 
+//
+// Note that 'frac' goes towards the newer sample, so a delay of
+// 2.3 actually reads 1.7 samples back. The flanger has always
+// worked that way, so it stays.
+//
 static inline float sample_array_read(float delay)
 {
 	int i = (int) delay;
@@ -69,7 +176,23 @@ static inline float sample_array_read(float delay)
 
 	float a = sample_array[SAMPLE_ARRAY_MASK & idx];
 	float b = sample_array[SAMPLE_ARRAY_MASK & ++idx];
-	return a + (b-a)*frac;
+	return interp_linear(a, b, frac);
+}
+
+// Same thing, with the 4-point Hermite interpolation. Note
+// that x2 is one sample newer than the linear read touches,
+// so this wants a delay of at least 2.
+static inline float sample_array_read_cubic(float delay)
+{
+	int i = (int) delay;
+	float frac = delay - i;
+	int idx = sample_array_index - i;
+
+	float x_1 = sample_array[SAMPLE_ARRAY_MASK & (idx-1)];
+	float x0 = sample_array[SAMPLE_ARRAY_MASK & idx];
+	float x1 = sample_array[SAMPLE_ARRAY_MASK & (idx+1)];
+	float x2 = sample_array[SAMPLE_ARRAY_MASK & (idx+2)];
+	return interp_hermite(x_1, x0, x1, x2, frac);
 }
 
 // We can calculate sin/cos at the same time using
@@ -115,3 +238,36 @@ struct sincos fastsincos(float phase)
 
 	return (struct sincos) { x, y };
 }
+
+//
+// Pan laws: 'pos' goes from -1 (left) to 1 (right), and the
+// laws differ in how loud the center is:
+//
+//  - linear: -6dB in the center, sums to unity in mono
+//  - constant power: -3dB in the center, same loudness everywhere
+//  - the -4.5dB compromise between the two
+//
+static inline void pan_linear(float pos, float *left, float *right)
+{
+	*left = (1 - pos) / 2;
+	*right = (1 + pos) / 2;
+}
+
+static inline void pan_constant_power(float pos, float *left, float *right)
+{
+	// A quarter turn from all-left to all-right
+	struct sincos sc = fastsincos((pos + 1) / 8);
+
+	*left = sc.cos;
+	*right = sc.sin;
+}
+
+static inline void pan_compromise(float pos, float *left, float *right)
+{
+	float l1, r1, l2, r2;
+
+	pan_linear(pos, &l1, &r1);
+	pan_constant_power(pos, &l2, &r2);
+	*left = sqrtf(l1 * l2);
+	*right = sqrtf(r1 * r2);
+}
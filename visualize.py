@@ -2,54 +2,652 @@ import sys
 import numpy as np
 import matplotlib.pyplot as plt
 import matplotlib.ticker as ticker
-from matplotlib.widgets import RangeSlider, RectangleSelector, RadioButtons
+from matplotlib.widgets import RangeSlider, RectangleSelector, RadioButtons, TextBox
+from matplotlib.colors import to_rgb
+from cycler import cycler
 
 import os
+import json
+import re
+import time
 import argparse
+import threading
+import subprocess
+import struct
+import shutil
+from collections import OrderedDict
+try:
+    import tomllib
+except ImportError:     # Before Python 3.11: there's no config file then
+    tomllib = None
 
 # --- Constants ---
 INITIAL_WINDOW_SEC = 3600.0
-BYTES_PER_SAMPLE = 4
+# Raw sample formats (same names as ffmpeg and 'analyze --format'):
+# numpy dtype, and what full scale is in those units. No s24, since
+# numpy can't map that directly.
+SAMPLE_FORMATS = {
+    's16le': ('<i2', 32768.0),
+    's16be': ('>i2', 32768.0),
+    's32le': ('<i4', 2147483648.0),
+    's32be': ('>i4', 2147483648.0),
+    'f32le': ('<f4', 1.0),
+    'f32be': ('>f4', 1.0),
+    'f64le': ('<f8', 1.0),
+    'f64be': ('>f8', 1.0),
+}
+# K-weighting (BS.1770) at 48kHz, as b0 b1 b2 a1 a2 for each biquad: the
+# same as weighting_k() in weighting.h
+K_WEIGHTING = (
+    (1.53512485958697, -2.69169618940638, 1.19839281085285, -1.69065929318241, 0.73248077421585),
+    (1.0, -2.0, 1.0, -1.99004745483398, 0.99007225036621),
+)
+METER_MODES = ('off', 'focus', 'all')
+# What export can write as WAV (the same as convert's --wav=), and full scale
+WAV_FORMATS = {
+    '16': ('<i2', 32768.0),
+    '24': ('<i4', 8388608.0),  # written as the low three bytes
+    '32': ('<i4', 2147483648.0),
+    'float': ('<f4', 1.0),
+}
 # MAX_WIDTH_SEC removed, utilizing self.max_samples instead
 MAX_PLOT_POINTS = 5000   # Maximum points to plot per line
+TILE_POINTS = 1024       # Points per cached tile of a zoomed out trace
+TILE_CACHE_BYTES = 64 << 20 # Most that the cached tiles take up together
+FOLLOW_INTERVAL_MS = 500 # How often to check for growing files in --follow mode
+RELOAD_INTERVAL_MS = 1000 # How often to check for files being rewritten
+PLAYHEAD_INTERVAL_MS = 50 # How often to move the playhead
+PLAY_CHUNK = 4096        # Samples per write to the player
+MEASURE_CHUNK = 1 << 20  # Samples at a time when measuring a selection
+MARKERS_SUFFIX = ".markers.json"
+ONSET_MARKER = "onset"  # What 'T' names the markers it puts on the hits
+SPEC_FFT = 1024          # Spectrogram FFT size
+SPEC_COLUMNS = 600       # Maximum spectrogram columns (one FFT each) across the view
+SPEC_FLOOR_DB = -120     # Bottom of the spectrogram color scale (and the spectrum)
+SPECTRUM_FFT = 4096      # Spectrum panel FFT size
+LANE_FILL = 0.9          # How much of its lane a file's trace gets
+STDIN_READ = 65536       # Bytes per read from stdin
+DB_RANGE = 60            # dB mode goes down to -DB_RANGE dBFS
+SHORT_TERM_SEC = 3.0     # How much the short-term loudness meter listens to
+SNAP_SEC = 0.01          # How far either way a selection edge looks for a zero crossing
+PALETTE_LINES = 12       # Most matches the command palette lists
+BROWSER_LINES = 30       # Directory entries the file browser shows at a time
+METER_SPAN = 1 << 22     # Most samples a meter reads exactly, wider views are sampled
+NULL_ALIGN = 1 << 17     # Samples to cross-correlate when aligning two files
+NULL_MAX_LAG = 1.0       # Seconds either way that alignment looks
+Y_MODES = ('Raw', 'Scaled', 'Volt', 'dB')
+X_MODES = ('Time', 'Samples')
+SESSION_FILE = "audionoise-session.json" # Where 'W' saves without --session
+CONVERT = os.path.join(os.path.dirname(os.path.abspath(__file__)), "convert") # Runs effects ('make convert')
+ANALYZE = os.path.join(os.path.dirname(os.path.abspath(__file__)), "analyze") # Draws screenshots ('make analyze')
+EFFECT_RATE = 48000      # The only rate convert runs effects at (without resampling)
+EFFECT_INTERVAL_MS = 100 # How often to check whether effects have finished running
+
+# What the keys do: a keymap binds actions to keys (matplotlib's names
+# for them), and the config file can pick one and change any of it:
+#
+#     keymap = "vim"
+#
+#     [keys]
+#     play = ["space", "p"]
+#     spectrum = "f"
+DEFAULT_KEYS = {
+    'pan_left': 'left', 'pan_right': 'right', 'pan_up': 'up', 'pan_down': 'down',
+    'zoom_in': 'pagedown', 'zoom_out': 'pageup',
+    'y_zoom_in': ['+', '='], 'y_zoom_out': '-', 'y_lock': 'y', 'y_auto': 'a',
+    'cursor_left': 'shift+left', 'cursor_right': 'shift+right',
+    'mark_in': '[', 'mark_out': ']', 'clear_selection': 'escape',
+    'add_marker': 'm', 'delete_marker': 'delete', 'list_markers': 'M',
+    'prev_marker': ',', 'next_marker': '.',
+    'play': 'space', 'loop': 'enter',
+    'lanes': 'w', 'spectrogram': 'S', 'spectrum': 'F',
+    'save_session': 'W', 'command': ':',
+    'next_over': 'x', 'next_silence': 'd', 'skip_silence': 'D',
+    'null': 'Z', 'effects': 'e', 'export': 'E', 'undo': ['u', 'ctrl+z'],
+    'next_trace': 'tab', 'trace_up': 'shift+up', 'trace_down': 'shift+down',
+    'trace_bigger': '*', 'trace_smaller': '/', 'trace_reset': '0',
+    'meters': 'V', 'snap_zero': 'z', 'palette': 'ctrl+p', 'open': 'o', 'screenshot': 'P',
+    'onsets': 'T',
+    # '1'..'9' hide and mute that file (or channel), alt makes it the only one
+    **{f'mute_{n}': str(n) for n in range(1, 10)},
+    **{f'solo_{n}': f'alt+{n}' for n in range(1, 10)},
+}
+KEYMAPS = {
+    'default': DEFAULT_KEYS,
+    'vim': {
+        **DEFAULT_KEYS,
+        'pan_left': ['left', 'h'], 'pan_right': ['right', 'l'],
+        'pan_up': ['up', 'k'], 'pan_down': ['down', 'j'],
+        'zoom_in': ['pagedown', 'i'], 'zoom_out': ['pageup', 'o'],
+        'cursor_left': ['shift+left', 'H'], 'cursor_right': ['shift+right', 'L'],
+        'prev_marker': [',', 'N'], 'next_marker': ['.', 'n'],
+        'open': 'O',
+    },
+    'daw': {
+        **DEFAULT_KEYS,
+        'zoom_in': ['pagedown', 'ctrl+='], 'zoom_out': ['pageup', 'ctrl+-'],
+        'y_zoom_in': ['+', '=', 'ctrl+up'], 'y_zoom_out': ['-', 'ctrl+down'],
+        'mark_in': ['[', 'i'], 'mark_out': [']', 'o'],
+        'add_marker': ['m', 'insert'],
+        'loop': ['enter', 'L'],
+        'open': 'O',
+    },
+}
+
+# Color themes: any of these can be changed in the config file's [colors]
+THEMES = {
+    'light': {
+        'background': 'white', 'foreground': 'black', 'grid': '#b0b0b0',
+        'traces': None,  # matplotlib's default colors
+        'cursor': 'gray', 'playhead': 'red', 'selection': 'orange', 'marker': 'green',
+    },
+    'dark': {
+        'background': '#1e1e1e', 'foreground': '#d0d0d0', 'grid': '#505050',
+        'traces': ['#4fc3f7', '#ffb74d', '#81c784', '#e57373', '#ba68c8', '#fff176'],
+        'cursor': '#a0a0a0', 'playhead': '#ff5252', 'selection': '#ffb74d', 'marker': '#69f0ae',
+    },
+    'monochrome': {
+        'background': 'white', 'foreground': 'black', 'grid': '#c0c0c0',
+        'traces': ['black', '#606060', '#a0a0a0'],
+        'cursor': '#808080', 'playhead': 'black', 'selection': '#c0c0c0', 'marker': '#404040',
+    },
+}
+
+def read_config():
+    """
+    ~/.config/audionoise/config.toml, if there is one, like
+
+        theme = "dark"          # light, dark or monochrome
+        rate = 44100            # defaults when there's no sidecar
+        format = "s16le"
+        channels = 2
+        layout = "lanes"        # or overlay
+        amplitude = "dB"        # Raw, Scaled, Volt or dB
+        snap_zero = true        # selections start and end at zero crossings
+        reload = false          # don't re-read files when they're rewritten
+
+        [colors]
+        playhead = "#00ff00"
+    """
+    base = os.environ.get('XDG_CONFIG_HOME') or os.path.expanduser('~/.config')
+    name = os.path.join(base, 'audionoise', 'config.toml')
+    if tomllib is None:
+        if os.path.exists(name):
+            print(f"Ignoring {name}: reading it needs Python 3.11")
+        return {}
+    try:
+        with open(name, 'rb') as f:
+            return tomllib.load(f)
+    except FileNotFoundError:
+        return {}
+    except (OSError, tomllib.TOMLDecodeError) as e:
+        print(f"Ignoring {name}: {e}")
+        return {}
+
+def fuzzy_score(query, name):
+    """
+    How well what's typed matches a name when its letters are found in order
+    (lower is better: fewer letters skipped), or None if they aren't.
+    Spaces stand for underscores.
+    """
+    pos, score = -1, 0
+    for c in query.strip().lower().replace(' ', '_'):
+        found = name.find(c, pos + 1)
+        if found < 0:
+            return None
+        score += found - pos - 1
+        pos = found
+    return score
+
+def read_session(f):
+    """A saved session (see WaveformVisualizer.save_session), or nothing if there's none yet."""
+    try:
+        with open(f) as session:
+            state = json.load(session)
+        return state if isinstance(state, dict) else {}
+    except FileNotFoundError:
+        return {}
+    except (OSError, ValueError) as e:
+        print(f"Can't restore session {f}: {e}")
+        return {}
+
+def theme_colors(config):
+    """The theme from the config, with its [colors] on top."""
+    theme = config.get('theme', 'light')
+    if theme not in THEMES:
+        print(f"Unknown theme {theme}, using light")
+        theme = 'light'
+    return {**THEMES[theme], **config.get('colors', {})}
+
+def read_sidecar(f):
+    """The 'capture.raw.json' metadata for 'capture.raw' (see sidecar.h), if any."""
+    if f == '-':
+        return {}
+    try:
+        with open(f + ".json") as sidecar:
+            meta = json.load(sidecar)
+        return meta if isinstance(meta, dict) else {}
+    except (OSError, ValueError):
+        return {}
+
+def format_time(t):
+    """Seconds, or m:ss.sss / h:mm:ss.sss once there are minutes or hours of them."""
+    # Rounded first, so 59.9999 doesn't come out as "60.000" (or "59:60.000"),
+    # and without a "-0.000"
+    t = round(t, 3) or 0.0
+    sign, t = ("-", -t) if t < 0 else ("", t)
+    if t < 60:
+        return f"{sign}{t:.3f}"
+    if t < 3600:
+        return f"{sign}{int(t // 60)}:{t % 60:06.3f}"
+    return f"{sign}{int(t // 3600)}:{int(t % 3600 // 60):02d}:{t % 60:06.3f}"
+
+def parse_time(s):
+    """Seconds from '90', '1.5s', '250ms', '100us' or '[hh:]mm:ss[.fraction]' (like timepos.h), or None."""
+    m = re.fullmatch(r'(-?)(?:(\d+):)?(\d+):(\d+(?:\.\d*)?)', s)
+    if m:
+        secs = int(m[2] or 0) * 3600 + int(m[3]) * 60 + float(m[4])
+        return -secs if m[1] else secs
+    m = re.fullmatch(r'(-?\d+(?:\.\d*)?|-?\.\d+)(s|ms|us)?', s)
+    if m:
+        return float(m[1]) / {None: 1, 's': 1, 'ms': 1e3, 'us': 1e6}[m[2]]
+    return None
+
+def dbfs(level):
+    """A level (as a fraction of full scale) in dBFS, as text."""
+    return f"{20 * np.log10(level):.1f}" if level > 0 else "-inf"
+
+def read_markers(f):
+    """Markers for 'capture.raw' from 'capture.raw.markers.json', as [(sample, name)] in order."""
+    if f == '-':
+        return []
+    try:
+        with open(f + MARKERS_SUFFIX) as markers:
+            return sorted((int(m['sample']), str(m['name'])) for m in json.load(markers))
+    except (OSError, ValueError, TypeError, KeyError):
+        return []
+
+def write_markers(f, markers):
+    """Replace the markers file (or remove it when there are none left)."""
+    if f == '-':
+        print("Markers on stdin don't get saved")
+        return
+    name = f + MARKERS_SUFFIX
+    try:
+        if not markers:
+            if os.path.exists(name):
+                os.remove(name)
+            return
+        tmp = name + ".tmp"
+        with open(tmp, 'w') as out:
+            json.dump([{'sample': pos, 'name': n} for pos, n in markers], out, indent=1)
+            out.write("\n")
+        os.replace(tmp, name)
+    except OSError as e:
+        print(f"Can't save markers: {e}")
+
+class StdinReader:
+    """Read stdin in the background into a buffer that grows, like a file being captured to."""
+    def __init__(self):
+        self.buf = np.zeros(1 << 20, dtype=np.uint8)
+        self.len = 0
+        threading.Thread(target=self.run, daemon=True).start()
+
+    def run(self):
+        while True:
+            chunk = sys.stdin.buffer.read1(STDIN_READ)
+            if not chunk:
+                break
+            n = len(chunk)
+            if self.len + n > self.buf.size:
+                buf = np.zeros(max(2 * self.buf.size, self.len + n), dtype=np.uint8)
+                buf[:self.len] = self.buf[:self.len]
+                self.buf = buf
+            self.buf[self.len:self.len + n] = np.frombuffer(chunk, dtype=np.uint8)
+            # Only after the data is there
+            self.len += n
+
+    def data(self):
+        """Everything read so far (the length first: any buffer has at least that much)."""
+        n = self.len
+        return self.buf[:n]
+
+class EffectChain:
+    """
+    Samples through a chain of effects in the background, each stage a 'convert'
+    of its own (like './convert echo 0.3 0.3 0.3 0.3 - -') piped into the next.
+    It's fed and read a chunk at a time, so nothing but the result is ever
+    the whole length.
+    """
+    def __init__(self, mm, chain, full_scale):
+        self.mm, self.full_scale = mm, full_scale
+        self.out = np.zeros(mm.size, dtype=np.float32)
+        self.error, self.done = None, False
+        self.procs = []
+        try:
+            for stage in chain:
+                stdin = self.procs[-1].stdout if self.procs else subprocess.PIPE
+                self.procs.append(subprocess.Popen([CONVERT, *stage, '-', '-'], stdin=stdin,
+                                                   stdout=subprocess.PIPE, stderr=subprocess.PIPE))
+                # Only the next stage reads it, or it would never see the end
+                if self.procs[:-1]:
+                    stdin.close()
+        except OSError:
+            self.cancel()
+            raise
+        self.stages = chain
+        threading.Thread(target=self.feed, daemon=True).start()
+        threading.Thread(target=self.read, daemon=True).start()
+
+    def feed(self):
+        try:
+            for pos in range(0, self.mm.size, MEASURE_CHUNK):
+                chunk = self.mm[pos:pos + MEASURE_CHUNK].astype(np.float64) * (2**31 / self.full_scale)
+                self.procs[0].stdin.write(np.clip(np.round(chunk), -2**31, 2**31 - 1).astype('<i4').tobytes())
+            self.procs[0].stdin.close()
+        except (OSError, ValueError):
+            # A stage died (read() says why) or we were cancelled
+            pass
+
+    def read(self):
+        # Same length as what went in, in the same units
+        pos, out = 0, self.procs[-1].stdout
+        while True:
+            data = out.read(4 * MEASURE_CHUNK)
+            if not data:
+                break
+            n = min(len(data) // 4, self.mm.size - pos)
+            if n > 0:
+                self.out[pos:pos + n] = np.frombuffer(data, dtype='<i4', count=n) * (self.full_scale / 2**31)
+                pos += n
+        # The stages before a failed one die of a broken pipe, so it's
+        # the last one that failed that has something to say
+        for stage, proc in reversed(list(zip(self.stages, self.procs))):
+            if proc.wait() and self.error is None:
+                error = proc.stderr.read().decode(errors='replace').strip()
+                self.error = f"{' '.join(stage)}: {error or f'failed ({proc.returncode})'}"
+        self.done = True
+
+    def cancel(self):
+        for proc in self.procs:
+            proc.kill()
+
+class TileCache:
+    """
+    What's drawn of each entry when zoomed out, every step'th sample, in tiles
+    of TILE_POINTS keyed by (entry, step, tile). Panning at the same zoom then
+    only has to read the tiles that come into view, rather than stride through
+    the whole view of the file again. The least recently used tiles go once
+    they take up more than the budget.
+    """
+    def __init__(self, budget):
+        self.tiles = OrderedDict()
+        self.budget, self.bytes = budget, 0
+
+    def forget(self, i):
+        for key in [key for key in self.tiles if key[0] == i]:
+            self.bytes -= self.tiles.pop(key)[1].nbytes
+
+    def tile(self, i, mm, step, t):
+        key = (i, step, t)
+        cached = self.tiles.get(key)
+        if cached is not None and cached[0] is mm:
+            self.tiles.move_to_end(key)
+            return cached[1]
+        # Edited, reloaded, grown or its effects turned on or off: all of it is stale
+        if cached is not None:
+            self.forget(i)
+        start = t * TILE_POINTS * step
+        points = np.array(mm[start:start + TILE_POINTS * step:step])
+        self.tiles[key] = (mm, points)
+        self.bytes += points.nbytes
+        while self.bytes > self.budget and len(self.tiles) > 1:
+            _, (_, old) = self.tiles.popitem(last=False)
+            self.bytes -= old.nbytes
+        return points
+
+    def points(self, i, mm, start, end, step):
+        """
+        The samples of [start, end) of entry i (which is mm) at the multiples
+        of step, and the first of those sample positions.
+        """
+        span = TILE_POINTS * step
+        first, last = start // span, (end - 1) // span
+        chunk = np.concatenate([self.tile(i, mm, step, t) for t in range(first, last + 1)])
+        skip = start // step - first * TILE_POINTS
+        return start // step * step, chunk[skip:(end - 1) // step - first * TILE_POINTS + 1]
 
 class WaveformVisualizer:
-    def __init__(self, filenames, rate, min_zoom_samples=100):
+    def __init__(self, filenames, rate, min_zoom_samples=100, follow=False, sample_format='s32le', channels=None,
+                 config=None, session_file=None, session=None):
         self.rate = rate
+        self.channels = channels
+        self.session_file = session_file
+        self.session = session or {}
+        self.config = config or {}
+        self.colors = theme_colors(self.config)
+        self.sample_format = sample_format
+        dtype, self.full_scale = SAMPLE_FORMATS[sample_format]
+        self.dtype = np.dtype(dtype)
         self.navigating = False
         self.filenames = filenames
         self.min_zoom_samples = min_zoom_samples
+        # Stdin only ever grows, so always follow it
+        self.stdin = StdinReader() if '-' in filenames else None
+        self.follow = follow or self.stdin is not None
 
         # X-Axis Mode
         self.x_mode = 'Time'
 
+        # One entry per channel of each file, with the file and channel it is
         self.mapped_files = []
+        self.paths = []
+        self.layout = []
         self.lines = []
         self.max_samples = 0
 
-        # Load files
+        # Y range: auto-scaled to the view, unless locked
+        self.y_locked = False
+
+        # Layout: all files overlaid, or each in its own lane (first on top)
+        self.lanes = False
+
+        # Cursor (a sample index), if one has been placed
+        self.cursor = None
+
+        # Level (dBFS) that 'x' looks for the next sample at or above
+        self.over_db = 0.0
+
+        # Entries (by index) hidden and not played, or the only one shown and played
+        self.muted = set()
+        self.solo = None
+
+        # Entries (by index) with an effect chain: {i: (chain text, processed samples)},
+        # and the chains still running: {i: EffectChain}
+        self.effects = {}
+        self.show_effects = True
+        self.effect_jobs = {}
+
+        # Display-only gain and offset (in Y axis units) of overlaid entries: {i: (gain, offset)},
+        # for the entry that has the focus
+        self.trace_adjust = {}
+        self.focus = 0
+
+        # Level meters on the view for no entries, the focused one or all of them
+        self.meters = 'off'
+        self.view_range = (0, 0)
+
+        # Selection edges (so loops, edits and exports) on zero crossings
+        self.snap_zero = bool(self.config.get('snap_zero', False))
+
+        # Edits so far, last one last: (entry, samples before it, selection before it, what it was)
+        self.undo_stack = []
+
+        # Null test of two files: A - gain * B(n + lag), when on
+        self.null = None
+        self.null_align = False
+        self.null_gain = False
+        self.null_info = []
+
+        # Silence (in the first file) for 'd' and 'D': under this dBFS, for this long
+        self.silence_db = -60.0
+        self.silence_secs = 0.1
+
+        # Selection: [in, out) in samples, set with '[' and ']' at the cursor
+        # or by dragging with the right button
+        self.sel_in = None
+        self.sel_out = None
+        self.sel_span = None
+        self.drag_from = None
+        self.drag_x = None
+
+        # Spectrogram (of the focused entry) under the waveform, if on
+        self.spectrogram = False
+        self.spec_image = None
+
+        # Spectrum panel (a window of its own), if open
+        self.spectrum_fig = None
+
+        # Markers (kept with the first file), and what draws them
+        self.markers = []
+        self.marker_artists = []
+
+        # Playback (of the first file)
+        self.player = None
+        self.play_pos = None
+
+        # File browser ('o'), a window of its own when open
+        self.browser_fig = None
+        self.browse_dir = os.getcwd()
+        if filenames and filenames[0] != '-':
+            self.browse_dir = os.path.dirname(os.path.abspath(filenames[0]))
+        self.browse_items = []
+        self.browse_index = 0
+
         for f in filenames:
             try:
-                fsize = os.path.getsize(f)
-                samples = fsize // BYTES_PER_SAMPLE
-                mm = np.memmap(f, dtype=np.int32, mode='r', shape=(samples,))
-                self.mapped_files.append((mm, os.path.basename(f)))
-                self.max_samples = max(self.max_samples, samples)
+                self.load_file(f, channels)
             except Exception as e:
                 print(f"Error opening {f}: {e}")
 
         if not self.mapped_files:
             return
 
+        # Interleaved channels are unreadable overlaid, so start with lanes
+        self.lanes = any(nr > 1 for _, nr in self.layout) or self.config.get('layout') == 'lanes'
+
+        self.markers = read_markers(self.paths[0])
+
         # Pre-allocate X-axis buffer to avoid allocations during plot updates
+        # (one more point than that, for a view that doesn't start on a multiple of the step)
 
-        self.t_buffer = np.zeros(MAX_PLOT_POINTS, dtype=np.float64)
+        self.t_buffer = np.zeros(MAX_PLOT_POINTS + 1, dtype=np.float64)
         # Pre-allocate index buffer 0..N-1
-        self.index_buffer = np.arange(MAX_PLOT_POINTS, dtype=np.float64)
+        self.index_buffer = np.arange(MAX_PLOT_POINTS + 1, dtype=np.float64)
+
+        # What's drawn when zoomed out, for panning
+        self.tile_cache = TileCache(TILE_CACHE_BYTES)
 
         self.setup_ui()
 
+    def load_file(self, f, channels=None):
+        """Map each channel of a file as an entry (the channel count given, or the sidecar's, or the config's)."""
+        nr = channels or int(read_sidecar(f).get('channels', self.config.get('channels', 1)))
+        for ch in range(nr):
+            mm = self.map_file(f, ch, nr)
+            name = (os.path.basename(f) if f != '-' else "stdin") + (f" ch{ch + 1}" if nr > 1 else "")
+            self.mapped_files.append((mm, name))
+            self.paths.append(f)
+            self.layout.append((ch, nr))
+            self.max_samples = max(self.max_samples, mm.size)
+
+    def file_id(self, f):
+        """What changes when a file is rewritten or replaced: inode, size and modification time."""
+        st = os.stat(f)
+        return st.st_ino, st.st_size, st.st_mtime_ns
+
+    def check_reload(self):
+        """
+        Reload timer: map files again once they've been rewritten or replaced
+        (and then left alone until the next check, so not halfway through
+        being written), staying where we are in them.
+        """
+        if self.navigating: return
+        for f in dict.fromkeys(self.paths):
+            if f == '-':
+                continue
+            try:
+                now = self.file_id(f)
+            except OSError:
+                # Being replaced, try again next time
+                continue
+            old = self.file_ids.setdefault(f, now)
+            if now == old:
+                self.reload_pending.pop(f, None)
+                continue
+            # Just growing is what follow mode is for
+            if self.follow and now[0] == old[0] and now[1] >= old[1]:
+                self.file_ids[f] = now
+                continue
+            if self.reload_pending.get(f) != now:
+                self.reload_pending[f] = now
+                continue
+            del self.reload_pending[f]
+            self.file_ids[f] = now
+            self.reload_file(f)
+
+    def reload_file(self, f):
+        """Map every entry from file f again, except any with edits that would get lost."""
+        edited = {edit[0] for edit in self.undo_stack}
+        for i, path in enumerate(self.paths):
+            if path != f:
+                continue
+            name = self.mapped_files[i][1]
+            if i in edited:
+                print(f"{name} changed on disk, but has edits here, so stays as it is")
+                continue
+            ch, nr = self.layout[i]
+            try:
+                mm = self.map_file(f, ch, nr)
+            except (OSError, ValueError) as e:
+                print(f"Can't reload {name}: {e}")
+                continue
+            self.mapped_files[i] = (mm, name)
+            self.after_edit(i, self.selection())
+            print(f"Reloaded {name}")
+
+    def file_frames(self, f, channels=1):
+        """Whole frames in a file (or read from stdin) so far."""
+        size = self.stdin.len if f == '-' else os.path.getsize(f)
+        return size // (self.dtype.itemsize * channels)
+
+    def map_file(self, f, channel=0, channels=1):
+        """Map the whole frames in a file (numpy can't map an empty one), and pick one channel."""
+        frames = self.file_frames(f, channels)
+        if frames == 0:
+            return np.zeros(0, dtype=self.dtype)
+        if f == '-':
+            data = self.stdin.data()[:frames * channels * self.dtype.itemsize]
+            return data.view(self.dtype).reshape(frames, channels)[:, channel]
+        mm = np.memmap(f, dtype=self.dtype, mode='r', shape=(frames, channels))
+        return mm[:, channel]
+
+    def apply_theme(self):
+        """Make the theme the default for everything drawn from here on."""
+        c = self.colors
+        plt.rcParams.update({
+            'figure.facecolor': c['background'], 'axes.facecolor': c['background'],
+            'savefig.facecolor': c['background'], 'legend.facecolor': c['background'],
+            'axes.edgecolor': c['foreground'], 'axes.labelcolor': c['foreground'],
+            'text.color': c['foreground'], 'xtick.color': c['foreground'],
+            'ytick.color': c['foreground'], 'grid.color': c['grid'],
+        })
+        if c['traces']:
+            plt.rcParams['axes.prop_cycle'] = cycler(color=c['traces'])
+
     def setup_ui(self):
+        self.apply_theme()
+        self.bind_keys()
         self.fig, self.ax = plt.subplots(figsize=(12, 6))
         # Manual "tight layout" to maximize space but keep room for slider
         plt.subplots_adjust(left=0.08, right=0.95, top=0.95, bottom=0.20)
@@ -58,30 +656,47 @@ class WaveformVisualizer:
         for _, name in self.mapped_files:
             line, = self.ax.plot([], [], linewidth=0.8, label=name)
             self.lines.append(line)
+        # The focus for the trace keys
+        if len(self.lines) > 1:
+            self.lines[self.focus].set_linewidth(1.6)
 
         self.ax.grid(True, which='both', linestyle=':', alpha=0.5)
-        self.ax.set_xlabel("Time (s)")
+        self.ax.set_xlabel("Time")
         self.ax.set_ylabel("Amplitude")
         self.ax.legend(loc='upper right', fontsize='x-small')
 
+        # What's left of A - B in null mode
+        self.null_line, = self.ax.plot([], [], linewidth=0.8, label='_nolegend_')
+
         # --- X-Axis Formatter ---
         def x_fmt(x, pos):
             if self.x_mode == 'Samples':
                 return f"{int(x)}"
             # Time mode
-            t = x / self.rate
-            return f"{t:.3f}"
+            return format_time(x / self.rate)
 
         self.ax.xaxis.set_major_formatter(ticker.FuncFormatter(x_fmt))
 
         # --- Y-Axis Formatter ---
-        self.y_mode = 'Volt'
+        self.y_mode = self.config.get('amplitude', 'Volt')
+        if self.y_mode not in Y_MODES:
+            print(f"Unknown amplitude mode {self.y_mode}, using Volt")
+            self.y_mode = 'Volt'
         def y_fmt(x, pos):
+            if self.y_mode == 'dB':
+                # Mirrored around zero, see db_scale()
+                if abs(x) > 1:
+                    return ""
+                return f"{-DB_RANGE * (1 - abs(x)):.0f}dB"
             if self.y_mode == 'Raw':
-                val = np.uint32(x)
-                return f"{val:09_X}"
+                if self.dtype.kind == 'f':
+                    return f"{x:.6g}"
+                # Two's complement hex, in groups of four digits
+                digits = 2 * self.dtype.itemsize
+                val = int(x) & ((1 << (4 * digits)) - 1)
+                return f"{val:0{digits + digits // 4 - 1}_X}"
             # Do "Engineering mode" by hand
-            x = x / 2147483648
+            x = x / self.full_scale
             suffix, milli = "", "ᴇ-3"
             if self.y_mode == 'Volt':
                 # Assuming 1Vrms full-range signal
@@ -94,7 +709,8 @@ class WaveformVisualizer:
                 suffix = milli
             return f"{x:.2f}"+suffix
 
-        self.ax.yaxis.set_major_formatter(ticker.FuncFormatter(y_fmt))
+        self.y_formatter = ticker.FuncFormatter(y_fmt)
+        self.ax.yaxis.set_major_formatter(self.y_formatter)
 
         # RangeSlider setup
         ax_slider = plt.axes([0.15, 0.05, 0.50, 0.03])
@@ -114,6 +730,7 @@ class WaveformVisualizer:
 
         def set_x_mode(label):
             self.x_mode = label
+            self.ax.set_xlabel("Time" if label == 'Time' else "Samples")
             self.ax.xaxis.set_major_formatter(ticker.FuncFormatter(x_fmt))
 
             # Update Slider View
@@ -127,36 +744,16 @@ class WaveformVisualizer:
             current_start_sample = xlim[0]
             current_end_sample = xlim[1]
 
-            max = self.max_samples
             if self.x_mode == 'Time':
                 # Switching TO Time
-                max, suffix = max / self.rate, "s"
-
-                # Reconfigure slider for Seconds
-                self.slider.valmin = 0.0
-                self.slider.valmax = max
-                # RangeSlider doesn't support valfmt directly for two values in typical API,
-                # but we can try to update axes if needed. standard RangeSlider doesn't show text value.
-
                 new_start = current_start_sample / self.rate
                 new_end = current_end_sample / self.rate
             else:
                 # Switching TO Samples
-                max, suffix = max / 1000, "kSamples"
-                if max > 10000:
-                        max, suffix = max / 1000, "MSamples"
-
-                # Reconfigure slider for Samples
-                self.slider.valmin = 0
-                self.slider.valmax = self.max_samples
-
                 new_start = current_start_sample
                 new_end = current_end_sample
 
-            self.slider.label.set_text(f"({max:.1f} {suffix})")
-
-            # Reset slider axis limits
-            self.slider.ax.set_xlim(self.slider.valmin, self.slider.valmax)
+            self.update_slider_range()
 
             old_eventson = self.slider.eventson
             self.slider.eventson = False
@@ -175,23 +772,23 @@ class WaveformVisualizer:
 
         # RadioButtons for Y-axis Mode
         ax_radio_y = plt.axes([0.85, 0.05, 0.12, 0.10])
-        self.radio = RadioButtons(ax_radio_y, ('Raw', 'Scaled', 'Volt'), active=2)
+        self.radio = RadioButtons(ax_radio_y, Y_MODES, active=Y_MODES.index(self.y_mode))
 
         def set_y_mode(label):
+            # dB mode plots different values, the others just label them differently
+            redraw = 'dB' in (label, self.y_mode)
             self.y_mode = label
-            self.ax.yaxis.set_major_formatter(ticker.FuncFormatter(y_fmt))
-
-            # Update labels
-            if label == 'Raw':
-                 self.ax.set_ylabel("Amplitude (Raw)")
-            elif label == 'Scaled':
-                 self.ax.set_ylabel("Amplitude (Normalized)")
-            elif label == 'Volt':
-                 self.ax.set_ylabel("Amplitude (Volts)")
-
+            self.y_formatter = ticker.FuncFormatter(y_fmt)
+            if not self.lanes:
+                self.ax.yaxis.set_major_formatter(self.y_formatter)
+            self.update_ylabel()
+            if redraw:
+                xlim = self.ax.get_xlim()
+                self.update_view(xlim[0], xlim[1] - xlim[0])
             self.fig.canvas.draw_idle()
 
         self.radio.on_clicked(set_y_mode)
+        self.set_lane_ticks()
 
         # Scroll Zoom setup
         self.fig.canvas.mpl_connect('scroll_event', self.on_scroll)
@@ -208,8 +805,79 @@ class WaveformVisualizer:
             interactive=False
         )
 
-        # Initial View
-        self.update_view(0, INITIAL_WINDOW_SEC * self.rate)
+        # Cursor, and the readout of what's under it (and the selection) in the corner
+        self.cursor_line = self.ax.axvline(0, color=self.colors['cursor'], linestyle='--', linewidth=1)
+        self.cursor_line.set_visible(False)
+        self.status = self.ax.text(0.01, 0.98, "", transform=self.ax.transAxes, va='top',
+                                   fontsize='x-small', family='monospace',
+                                   bbox=dict(facecolor=self.colors['background'], alpha=0.7, edgecolor='none'))
+        self.fig.canvas.mpl_connect('button_press_event', self.on_click)
+        self.fig.canvas.mpl_connect('motion_notify_event', self.on_drag)
+        self.fig.canvas.mpl_connect('button_release_event', self.on_release)
+
+        # Spectrogram axes, sharing the time axis, hidden until 'S'
+        self.wave_pos = self.ax.get_position()
+        self.spec_ax = self.fig.add_axes(self.wave_pos.bounds, sharex=self.ax)
+        self.spec_ax.set_ylabel("Frequency (Hz)")
+        # The image extent must not move the (shared) view
+        self.spec_ax.set_autoscalex_on(False)
+        self.spec_ax.set_visible(False)
+
+        # Markers, and the name for the next one dropped with 'm'
+        ax_marker = plt.axes([0.15, 0.10, 0.20, 0.035])
+        self.marker_name = TextBox(ax_marker, 'Marker ')
+
+        # Commands typed after ':'
+        ax_command = plt.axes([0.45, 0.10, 0.20, 0.035])
+        self.command = TextBox(ax_command, ':')
+        self.command.on_submit(self.run_command)
+        self.running_command = False
+
+        # The command palette (ctrl+p): every action, by typing some of its name
+        ax_palette = plt.axes([0.70, 0.16, 0.27, 0.03])
+        self.palette = TextBox(ax_palette, 'Do ')
+        self.palette.on_text_change(self.update_palette)
+        self.palette.on_submit(self.run_palette)
+        self.palette_list = self.fig.text(0.70, 0.195, "", va='bottom', fontsize='x-small', family='monospace',
+                                          bbox=dict(facecolor=self.colors['background'], alpha=0.9))
+        self.palette_list.set_visible(False)
+        self.running_palette = False
+        self.draw_markers()
+
+        # Playhead, hidden until something plays
+        self.playhead = self.ax.axvline(0, color=self.colors['playhead'], linewidth=1)
+        self.playhead.set_visible(False)
+        self.playhead_timer = self.fig.canvas.new_timer(interval=PLAYHEAD_INTERVAL_MS)
+        self.playhead_timer.add_callback(self.update_playhead)
+
+        # Effects run in the background, and show up when they're done
+        self.effects_timer = self.fig.canvas.new_timer(interval=EFFECT_INTERVAL_MS)
+        self.effects_timer.add_callback(self.check_effects)
+
+        # Follow mode: keep checking whether the files have grown
+        if self.follow:
+            self.follow_timer = self.fig.canvas.new_timer(interval=FOLLOW_INTERVAL_MS)
+            self.follow_timer.add_callback(self.check_growth)
+            self.follow_timer.start()
+
+        # Notice files being rendered again
+        self.file_ids = {}
+        self.reload_pending = {}
+        if self.config.get('reload', True):
+            self.check_reload()
+            self.reload_timer = self.fig.canvas.new_timer(interval=RELOAD_INTERVAL_MS)
+            self.reload_timer.add_callback(self.check_reload)
+            self.reload_timer.start()
+
+        # Save the session when done, if there's one
+        if self.session_file:
+            self.fig.canvas.mpl_connect('close_event', lambda event: self.save_session())
+
+        # Initial View, or where the session left off
+        if self.session:
+            self.restore_session()
+        else:
+            self.update_view(0, INITIAL_WINDOW_SEC * self.rate)
 
         # Explicitly sync slider after initial view to ensure it matches the plot limits
         # We read back the actual limits because update_view might have clamped the window
@@ -228,6 +896,1352 @@ class WaveformVisualizer:
              self.update_slider_text((start_samp, end_samp))
 
         plt.show()
+        self.stop()
+
+    def save_session(self):
+        """Write out what's loaded and where we're looking, to pick up from with --session."""
+        name = self.session_file or SESSION_FILE
+        sel = self.selection()
+        # Entries numbered as they will be without stdin
+        kept = [i for i, f in enumerate(self.paths) if f != '-']
+        state = {
+            'files': [os.path.abspath(f) for f in dict.fromkeys(self.paths) if f != '-'],
+            'rate': self.rate,
+            'format': self.sample_format,
+            'channels': self.channels,
+            'view': list(self.ax.get_xlim()),
+            'y_range': list(self.ax.get_ylim()) if self.y_locked else None,
+            'x_mode': self.x_mode,
+            'y_mode': self.y_mode,
+            'lanes': self.lanes,
+            'cursor': self.cursor,
+            'selection': list(sel) if sel else None,
+            'markers': self.markers,
+            'traces': [[n, *self.trace_adjust[i]] for n, i in enumerate(kept) if i in self.trace_adjust],
+        }
+        if '-' in self.paths:
+            print("Stdin can't be part of a saved session")
+        try:
+            tmp = name + ".tmp"
+            with open(tmp, 'w') as out:
+                json.dump(state, out, indent=1)
+                out.write("\n")
+            os.replace(tmp, name)
+            print(f"Saved the session to {name}")
+        except OSError as e:
+            print(f"Can't save the session: {e}")
+
+    def restore_session(self):
+        """Modes, view, cursor and selection from the session (the files are already loaded)."""
+        s = self.session
+        if s.get('x_mode') in X_MODES:
+            self.radio_x.set_active(X_MODES.index(s['x_mode']))
+        if s.get('y_mode') in Y_MODES:
+            self.radio.set_active(Y_MODES.index(s['y_mode']))
+        if 'lanes' in s and bool(s['lanes']) != self.lanes:
+            self.lanes = bool(s['lanes'])
+            self.set_lane_ticks()
+
+        # Markers live next to the capture too, so keep both
+        try:
+            markers = {(int(pos), str(name)) for pos, name in s.get('markers', [])}
+        except (TypeError, ValueError):
+            markers = set()
+        if markers - set(self.markers):
+            self.markers = sorted(set(self.markers) | markers)
+            self.draw_markers()
+
+        # Display-only gains and offsets of the traces (see adjust_trace)
+        try:
+            adjust = {int(i): (float(gain), float(offset)) for i, gain, offset in s.get('traces', [])}
+        except (TypeError, ValueError):
+            adjust = {}
+        adjust = {i: a for i, a in adjust.items() if 0 <= i < len(self.mapped_files)}
+        if adjust:
+            self.trace_adjust = adjust
+            self.update_traces()
+
+        try:
+            start, end = s['view']
+            self.update_view(start, end - start)
+        except (KeyError, TypeError, ValueError):
+            self.update_view(0, INITIAL_WINDOW_SEC * self.rate)
+        if s.get('y_range'):
+            self.ax.set_ylim(*s['y_range'])
+            self.set_y_locked(True)
+        if s.get('cursor') is not None:
+            self.set_cursor(s['cursor'])
+        if s.get('selection'):
+            self.set_selection(*s['selection'])
+
+    def update_slider_range(self):
+        """Set the slider range (and its label) from max_samples for the current X mode."""
+        max = self.max_samples
+        if self.x_mode == 'Time':
+            max, suffix = max / self.rate, "s"
+
+            # Reconfigure slider for Seconds
+            # RangeSlider doesn't support valfmt directly for two values in typical API,
+            # but we can try to update axes if needed. standard RangeSlider doesn't show text value.
+            self.slider.valmin = 0.0
+            self.slider.valmax = max
+        else:
+            max, suffix = max / 1000, "kSamples"
+            if max > 10000:
+                    max, suffix = max / 1000, "MSamples"
+
+            # Reconfigure slider for Samples
+            self.slider.valmin = 0
+            self.slider.valmax = self.max_samples
+
+        self.slider.label.set_text(f"({max:.1f} {suffix})")
+
+        # Reset slider axis limits
+        self.slider.ax.set_xlim(self.slider.valmin, self.slider.valmax)
+
+    def check_growth(self):
+        """Follow mode timer: remap any files that have grown since last time."""
+        if self.navigating: return
+
+        old_max = self.max_samples
+        old_sizes = [mm.size for mm, _ in self.mapped_files]
+        edited = {edit[0] for edit in self.undo_stack}
+        for i, (mm, name) in enumerate(self.mapped_files):
+            # Edits live in memory, the file growing under them doesn't change them
+            if i in edited:
+                continue
+            try:
+                ch, nr = self.layout[i]
+                frames = self.file_frames(self.paths[i], nr)
+                if frames > mm.size:
+                    mm = self.map_file(self.paths[i], ch, nr)
+                    self.mapped_files[i] = (mm, name)
+            except OSError:
+                # Being rotated or recreated, try again next time
+                continue
+            self.max_samples = max(self.max_samples, mm.size)
+
+        # A file that isn't the longest can grow too, and needs redrawing
+        if all(mm.size == old for old, (mm, _) in zip(old_sizes, self.mapped_files)):
+            return
+
+        # Redo the readouts if a file grew into the cursor or the selection
+        sel = self.selection()
+        reach = max(self.cursor + 1 if self.cursor is not None else 0, sel[1] if sel else 0)
+        if any(old < min(reach, mm.size) for old, (mm, _) in zip(old_sizes, self.mapped_files)):
+            self.update_status()
+
+        # If we were looking at the end, keep looking at the end
+        xlim = self.ax.get_xlim()
+        width = xlim[1] - xlim[0]
+        start = xlim[0]
+        if self.max_samples != old_max:
+            self.update_slider_range()
+            if xlim[1] >= old_max:
+                start = max(0, self.max_samples - width)
+        self.update_view(start, width)
+        self.sync_slider()
+
+    def update_ylabel(self):
+        """Y axis label for the mode, and whether the range is locked."""
+        if self.lanes and self.null is None:
+            self.ax.set_ylabel("Each file scaled to fit")
+            return
+        label = {'Raw': "Amplitude (Raw)", 'Scaled': "Amplitude (Normalized)",
+                 'Volt': "Amplitude (Volts)", 'dB': "Level (dBFS)"}[self.y_mode]
+        if self.y_locked:
+            label += " [locked]"
+        self.ax.set_ylabel(label)
+
+    def set_y_locked(self, locked):
+        self.y_locked = locked
+        self.update_ylabel()
+
+    def toggle_lanes(self):
+        """'w': switch between overlaying the files and giving each a lane of its own."""
+        self.lanes = not self.lanes
+        self.set_lane_ticks()
+        xlim = self.ax.get_xlim()
+        self.update_view(xlim[0], xlim[1] - xlim[0])
+
+    def set_lane_ticks(self):
+        """Y ticks and label: a name per lane, or amplitudes when overlaid (or nulled)."""
+        if self.lanes and self.null is None:
+            names = [name for _, name in self.mapped_files]
+            self.ax.yaxis.set_major_locator(ticker.FixedLocator(range(len(names))))
+            self.ax.yaxis.set_major_formatter(ticker.FixedFormatter(names[::-1]))
+        else:
+            self.ax.yaxis.set_major_locator(ticker.AutoLocator())
+            self.ax.yaxis.set_major_formatter(self.y_formatter)
+        self.update_ylabel()
+
+    def sync_slider(self):
+        """Set the slider from the current plot limits, without triggering it."""
+        xlim = self.ax.get_xlim()
+        old_eventson = self.slider.eventson
+        self.slider.eventson = False
+        if self.x_mode == 'Time':
+            val = (xlim[0] / self.rate, xlim[1] / self.rate)
+        else:
+            val = (xlim[0], xlim[1])
+        self.slider.set_val(val)
+        self.update_slider_text(val)
+        self.slider.eventson = old_eventson
+
+    def cursor_readout(self):
+        """Sample index, time, raw value and dBFS under the cursor, for each file."""
+        pos = self.cursor
+        lines = []
+        for mm, name in self.mapped_files:
+            if pos >= mm.size:
+                lines.append(f"{name}: sample {pos}  {pos / self.rate:.6f}s  (past the end)")
+                continue
+            raw = mm[pos].item()
+            value = f"{raw:.6g}" if self.dtype.kind == 'f' else f"{raw}"
+            lines.append(f"{name}: sample {pos}  {pos / self.rate:.6f}s  raw {value}"
+                         f"  {dbfs(abs(raw) / self.full_scale)} dBFS")
+        return lines
+
+    def selection(self):
+        """The selected (start, end) samples, or None. What export, normalize and loop work on."""
+        if self.sel_in is None or self.sel_out is None:
+            return None
+        return min(self.sel_in, self.sel_out), max(self.sel_in, self.sel_out)
+
+    def measure(self, mm, start, end):
+        """
+        Stats of samples [start, end) in one pass, like measure_stats() in
+        measure.h: count, min, max, peak, RMS, crest factor and DC (as
+        fractions of full scale), and how many samples are clipped.
+        """
+        end = min(end, mm.size)
+        st = dict(count=max(0, end - start), min=0.0, max=0.0, peak=0.0, rms=0.0, crest=0.0, dc=0.0, clipped=0)
+        if start >= end:
+            return st
+        # Integers clip at the most positive value, which is one short of full scale
+        top = self.full_scale - 1 if mm.dtype.kind == 'i' else self.full_scale
+        lo, hi, total, squares, clipped = np.inf, -np.inf, 0.0, 0.0, 0
+        for pos in range(start, end, MEASURE_CHUNK):
+            raw = mm[pos:min(pos + MEASURE_CHUNK, end)]
+            clipped += int(np.count_nonzero((raw >= top) | (raw <= -self.full_scale)))
+            chunk = raw.astype(np.float64) / self.full_scale
+            lo, hi = min(lo, float(chunk.min())), max(hi, float(chunk.max()))
+            total += float(chunk.sum())
+            squares += float(np.dot(chunk, chunk))
+        rms, peak = np.sqrt(squares / st['count']), max(-lo, hi)
+        st.update(min=lo, max=hi, peak=peak, rms=rms, dc=total / st['count'],
+                  crest=peak / rms if rms > 0 else 0.0, clipped=clipped)
+        return st
+
+    def selection_readout(self):
+        """Duration of the selection, and the stats summary of it for each file."""
+        start, end = self.selection()
+        lines = [f"selection {start}..{end}  {(end - start) / self.rate:.6f}s"]
+        for mm, name in self.mapped_files:
+            st = self.measure(mm, start, end)
+            crest = f"{20 * np.log10(st['crest']):.2f}" if st['crest'] > 0 else "-inf"
+            lines.append(f"{name}: {st['count']} samples  RMS {st['rms']:.6f} ({dbfs(st['rms'])} dBFS)"
+                         f"  peak {st['peak']:.6f} ({dbfs(st['peak'])} dBFS)")
+            lines.append(f"    min {st['min']:+.6f}  max {st['max']:+.6f}  crest {st['crest']:.2f} ({crest} dB)"
+                         f"  DC {st['dc']:+.6f}  clipped {st['clipped']}")
+        return lines
+
+    def update_status(self):
+        """Cursor and selection readouts, in the corner of the plot."""
+        lines = []
+        if self.cursor is not None:
+            lines += self.cursor_readout()
+        if self.selection() is not None:
+            lines += self.selection_readout()
+        lines += self.null_info
+        if self.meters != 'off':
+            lines += self.meter_readout()
+        self.status.set_text("\n".join(lines))
+        self.fig.canvas.draw_idle()
+
+    def short_term_lufs(self, mm, start, end):
+        """
+        Loudness (BS.1770, like loudness.h) of SHORT_TERM_SEC in the middle of
+        [start, end), or all of it if that's less. The K-weighting is done on
+        the spectrum, with the 48kHz filters whatever the rate.
+        """
+        mid, half = (start + end) // 2, int(SHORT_TERM_SEC * self.rate / 2)
+        start, end = max(start, mid - half), min(end, mid + half, mm.size)
+        if end - start < 2:
+            return None
+        x = mm[start:end].astype(np.float64) / self.full_scale
+        freqs = np.minimum(np.fft.rfftfreq(x.size, 1 / self.rate), 24000)
+        z = np.exp(-2j * np.pi * freqs / 48000)
+        response = 1
+        for b0, b1, b2, a1, a2 in K_WEIGHTING:
+            response = response * (b0 + b1 * z + b2 * z * z) / (1 + a1 * z + a2 * z * z)
+        y = np.fft.irfft(np.fft.rfft(x) * response, x.size)
+        meansq = float(np.mean(y * y))
+        return -0.691 + 10 * np.log10(meansq) if meansq > 0 else None
+
+    def meter_readout(self):
+        """RMS, peak and short-term loudness of what's in view, for the focused entry or all of them."""
+        start, end = self.view_range
+        entries = range(len(self.mapped_files)) if self.meters == 'all' else [self.focus]
+        lines = []
+        for i in entries:
+            mm, name = self.samples(i), self.mapped_files[i][1]
+            end_i = min(end, mm.size)
+            if start >= end_i:
+                lines.append(f"{name}: nothing in view")
+                continue
+            step = -(-(end_i - start) // METER_SPAN)
+            view = mm[start:end_i:step]
+            st = self.measure(view, 0, view.size)
+            rms, peak = st['rms'], st['peak']
+            loudness = self.short_term_lufs(mm, start, end_i)
+            loudness = f"{loudness:.1f}" if loudness is not None else "-inf"
+            lines.append(f"{name}: RMS {dbfs(rms)} dBFS  peak {dbfs(peak)} dBFS  {loudness} LUFS short-term"
+                         + (" (sampled)" if step > 1 else ""))
+        return lines
+
+    def toggle_meters(self):
+        """'V': meters on what's in view for the focused file (see Tab), all the files, or none."""
+        self.meters = METER_MODES[(METER_MODES.index(self.meters) + 1) % len(METER_MODES)]
+        self.update_status()
+
+    def snap(self, pos):
+        """
+        The nearest zero crossing in the focused entry within SNAP_SEC of
+        pos (the first sample that's zero or has the other sign from the one
+        before), when snapping is on and there is one. Otherwise just pos.
+        """
+        if not self.snap_zero or pos is None:
+            return pos
+        mm = self.samples(self.focus)
+        reach = max(1, int(SNAP_SEC * self.rate))
+        lo, hi = max(1, pos - reach), min(mm.size, pos + reach + 1)
+        if lo >= hi:
+            return pos
+        sign = np.sign(mm[lo - 1:hi].astype(np.float64))
+        crossings = lo + np.flatnonzero(sign[:-1] * sign[1:] <= 0)
+        if not crossings.size:
+            return pos
+        return int(crossings[np.argmin(np.abs(crossings - pos))])
+
+    def snap_selection(self, sel_in, sel_out):
+        """Set the selection with both edges snapped, unless that leaves nothing between them."""
+        snap_in, snap_out = self.snap(sel_in), self.snap(sel_out)
+        if snap_in is not None and snap_out is not None and snap_in >= snap_out:
+            snap_in, snap_out = sel_in, sel_out
+        self.set_selection(snap_in, snap_out)
+
+    def toggle_snap(self):
+        """'z': selection edges on zero crossings (from here on), or where they're put."""
+        self.snap_zero = not self.snap_zero
+        print(f"Zero crossing snapping {'on' if self.snap_zero else 'off'}")
+
+    def set_selection(self, sel_in, sel_out):
+        """Set the in/out points (either can be None), and shade what's between them."""
+        self.sel_in, self.sel_out = sel_in, sel_out
+        self.shade_selection(self.selection())
+        self.update_status()
+
+    def shade_selection(self, sel):
+        if self.sel_span is not None:
+            self.sel_span.remove()
+            self.sel_span = None
+        if sel is not None:
+            self.sel_span = self.ax.axvspan(sel[0], sel[1], color=self.colors['selection'], alpha=0.2)
+        self.fig.canvas.draw_idle()
+
+    def draw_markers(self):
+        """A line and a label for each marker."""
+        for artist in self.marker_artists:
+            artist.remove()
+        self.marker_artists = []
+        for pos, name in self.markers:
+            self.marker_artists.append(self.ax.axvline(pos, color=self.colors['marker'], linewidth=1))
+            self.marker_artists.append(self.ax.text(pos, 0.01, f" {name}", color=self.colors['marker'], fontsize='x-small',
+                                                    transform=self.ax.get_xaxis_transform(), clip_on=True))
+        self.fig.canvas.draw_idle()
+
+    def update_markers(self, markers):
+        self.markers = sorted(markers)
+        write_markers(self.paths[0], self.markers)
+        self.draw_markers()
+
+    def add_marker(self):
+        """'m': drop a marker at the cursor, named from the text box (or numbered)."""
+        if self.cursor is None:
+            return
+        name = self.marker_name.text.strip() or f"M{len(self.markers) + 1}"
+        self.update_markers([m for m in self.markers if m[0] != self.cursor] + [(self.cursor, name)])
+        self.marker_name.set_val("")
+
+    def delete_marker(self):
+        """Delete: remove the marker at the cursor."""
+        self.update_markers([m for m in self.markers if m[0] != self.cursor])
+
+    def list_markers(self):
+        """'M': print the markers."""
+        for pos, name in self.markers:
+            print(f"{pos:12d}  {pos / self.rate:12.6f}s  {name}")
+
+    def jump_marker(self, direction):
+        """',' and '.': move the cursor to the previous/next marker, and bring it into view."""
+        here = self.cursor if self.cursor is not None else self.ax.get_xlim()[0]
+        if direction > 0:
+            targets = [pos for pos, _ in self.markers if pos > here]
+        else:
+            targets = [pos for pos, _ in self.markers if pos < here]
+        if not targets:
+            return
+        self.set_cursor(min(targets) if direction > 0 else max(targets))
+        self.show_cursor()
+
+    def next_run(self, mm, pos, level, above, min_len=1):
+        """
+        The first run of at least 'min_len' samples from 'pos' on whose
+        level (as a fraction of full scale) is at/above 'level' (or below
+        it, if not 'above'), as (start, end), or None. The same as
+        find_runs() in measure.h, a chunk at a time.
+        """
+        # Integer full scale is one step short of it on the positive side
+        limit = level * self.full_scale - (1 if self.dtype.kind == 'i' and above else 0)
+        run = None
+        for start in range(max(0, int(pos)), mm.size, MEASURE_CHUNK):
+            chunk = np.abs(mm[start:start + MEASURE_CHUNK].astype(np.float64))
+            mask = chunk >= limit if above else chunk < limit
+            edges = np.diff(np.concatenate(([0], mask.astype(np.int8), [0])))
+            starts = np.flatnonzero(edges == 1) + start
+            ends = np.flatnonzero(edges == -1) + start
+            # A run from the last chunk either goes on, or ended right there
+            if run is not None:
+                if starts.size and starts[0] == start:
+                    starts[0] = run
+                elif start - run >= min_len:
+                    return run, start
+            run = None
+            for s, e in zip(starts, ends):
+                if e == start + chunk.size and e < mm.size:
+                    run = s
+                elif e - s >= min_len:
+                    return int(s), int(e)
+        return None
+
+    def show_range(self, start, end):
+        """Cursor at 'start', and the view around [start, end) (wider if it has to be)."""
+        xlim = self.ax.get_xlim()
+        width = max(xlim[1] - xlim[0], (end - start) * 1.2)
+        self.set_cursor(start)
+        self.update_view((start + end) / 2 - width / 2, width)
+        self.sync_slider()
+
+    def next_over(self):
+        """'x': the next samples after the cursor (or the start of the view) at or above over_db, in any file."""
+        pos = self.cursor + 1 if self.cursor is not None else self.ax.get_xlim()[0]
+        level = 10 ** (self.over_db / 20)
+        found = [run for mm, _ in self.mapped_files if (run := self.next_run(mm, pos, level, True))]
+        if not found:
+            print(f"Nothing at or above {self.over_db:g} dBFS after here")
+            return
+        self.show_range(*min(found))
+
+    def null_bounds(self, start, end, lag):
+        """Where both A[n] and B[n + lag] exist, within [start, end)."""
+        a, b = self.samples(0), self.samples(1)
+        return max(start, -lag, 0), min(end, a.size, b.size - lag)
+
+    def null_chunks(self, start, end, lag):
+        """A and B(n + lag), a chunk at a time (as fractions of full scale)."""
+        a, b = self.samples(0), self.samples(1)
+        start, end = self.null_bounds(start, end, lag)
+        for pos in range(start, end, MEASURE_CHUNK):
+            stop = min(pos + MEASURE_CHUNK, end)
+            yield (a[pos:stop].astype(np.float64) / self.full_scale,
+                   b[pos + lag:stop + lag].astype(np.float64) / self.full_scale)
+
+    def find_lag(self, start, end):
+        """
+        How many samples B lags A by (within NULL_MAX_LAG), from cross-correlating
+        them: B[n] ~ A[n - lag], like cross_correlate() in correlate.h.
+        """
+        a, b = self.samples(0), self.samples(1)
+        n = min(end - start, NULL_ALIGN, a.size - start, b.size - start)
+        if n < 2:
+            return 0
+        x = a[start:start + n].astype(np.float64)
+        y = b[start:start + n].astype(np.float64)
+        size = 1 << (2 * n - 1).bit_length()
+        # corr[k] is the sum of y[i + k] * x[i], lags below zero wrap around
+        corr = np.fft.irfft(np.fft.rfft(y, size) * np.conj(np.fft.rfft(x, size)), size)
+        max_lag = min(n - 1, int(NULL_MAX_LAG * self.rate))
+        lags = np.arange(-max_lag, max_lag + 1)
+        return int(lags[np.argmax(corr[lags % size])])
+
+    def set_null(self, align, gain):
+        """
+        Null test the two files over the selection (or the view): aligned and
+        gain matched if asked to, and how much is left of A after taking B away.
+        """
+        if len(self.mapped_files) != 2:
+            print("Null testing needs exactly two (mono) files")
+            return
+        sel = self.selection()
+        xlim = self.ax.get_xlim()
+        start, end = sel if sel else (int(max(0, xlim[0])), int(xlim[1]))
+        lag = self.find_lag(start, end) if align else 0
+
+        g = 1.0
+        if gain:
+            ab = bb = 0.0
+            for x, y in self.null_chunks(start, end, lag):
+                ab += float(np.dot(x, y))
+                bb += float(np.dot(y, y))
+            g = ab / bb if bb > 0 else 1.0
+
+        count, aa, rr = 0, 0.0, 0.0
+        for x, y in self.null_chunks(start, end, lag):
+            r = x - g * y
+            count += x.size
+            aa += float(np.dot(x, x))
+            rr += float(np.dot(r, r))
+        if not count:
+            print("The files don't overlap there")
+            return
+
+        self.null_align, self.null_gain = align, gain
+        self.null = (lag, g)
+        rms, residual = np.sqrt(aa / count), np.sqrt(rr / count)
+        below = f"{20 * np.log10(rms / residual):.1f} dB below A" if residual > 0 else "a perfect null"
+        gain_db = dbfs(abs(g))
+        self.null_info = [
+            f"null {self.mapped_files[0][1]} - {self.mapped_files[1][1]} over {start}..{end}",
+            f"  lag {lag} samples, gain {g:.6f} ({gain_db} dB)",
+            f"  residual RMS {residual:.6g} ({dbfs(residual)} dBFS), {below}",
+        ]
+        self.refresh_null()
+
+    def toggle_null(self):
+        """'Z': null test on or off (with the last alignment and gain options)."""
+        if self.null is None:
+            self.set_null(self.null_align, self.null_gain)
+            return
+        self.null = None
+        self.null_info = []
+        self.null_line.set_data([], [])
+        self.refresh_null()
+
+    def refresh_null(self):
+        self.set_lane_ticks()
+        xlim = self.ax.get_xlim()
+        self.update_view(xlim[0], xlim[1] - xlim[0])
+        self.update_status()
+
+    def null_chunk(self, start, end, step):
+        """The plot data in null mode: just the residual, on the same sample steps as get_chunk()."""
+        for line in self.lines:
+            line.set_data([], [])
+        lag, g = self.null
+        lo, hi = self.null_bounds(start, end, lag)
+        lo = start + -(-(lo - start) // step) * step
+        if lo >= hi:
+            self.null_line.set_data([], [])
+            return False, np.inf, -np.inf
+        a, b = self.samples(0), self.samples(1)
+        res = a[lo:hi:step].astype(np.float64) - g * b[lo + lag:hi + lag:step].astype(np.float64)
+        y = self.db_scale(res) if self.y_mode == 'dB' else res
+        self.null_line.set_data(lo + np.arange(res.size) * step, y)
+        return True, float(np.min(res)), float(np.max(res))
+
+    def find_silence(self, pos):
+        """The next silence in the first file from 'pos' on, as (start, end), or None."""
+        mm = self.mapped_files[0][0]
+        min_len = max(1, int(self.silence_secs * self.rate))
+        return self.next_run(mm, pos, 10 ** (self.silence_db / 20), False, min_len)
+
+    def next_silence(self):
+        """'d': the next silence after the one we're in (if we are)."""
+        pos = self.cursor if self.cursor is not None else int(self.ax.get_xlim()[0])
+        run = self.find_silence(pos)
+        if run is not None and run[0] == pos:
+            run = self.find_silence(run[1])
+        if run is None:
+            print(f"No more silence (under {self.silence_db:g} dBFS for {self.silence_secs:g}s)")
+            return
+        self.show_range(*run)
+
+    def skip_silence(self):
+        """'D': to where the silence we're in (or the next one) ends."""
+        pos = self.cursor if self.cursor is not None else int(self.ax.get_xlim()[0])
+        run = self.find_silence(pos)
+        if run is None or run[1] >= self.mapped_files[0][0].size:
+            print("Nothing but silence from here on")
+            return
+        self.show_range(run[1], run[1])
+
+    def set_cursor(self, pos):
+        """Put the cursor on the sample nearest 'pos' and show what's there."""
+        self.cursor = int(max(0, min(round(pos), self.max_samples - 1)))
+        self.cursor_line.set_xdata([self.cursor, self.cursor])
+        self.cursor_line.set_visible(True)
+        self.update_status()
+
+    def move_cursor(self, step):
+        """Shift+left/right: move the cursor a sample."""
+        xlim = self.ax.get_xlim()
+        if self.cursor is None:
+            self.set_cursor((xlim[0] + xlim[1]) / 2)
+        else:
+            self.set_cursor(self.cursor + step)
+        self.show_cursor()
+
+    def show_cursor(self):
+        """If the cursor is out of view, center the view on it."""
+        xlim = self.ax.get_xlim()
+        if not xlim[0] <= self.cursor < xlim[1]:
+            width = xlim[1] - xlim[0]
+            self.update_view(self.cursor - width / 2, width)
+            self.sync_slider()
+
+    def on_click(self, event):
+        """Right click: place the cursor (and move the playhead there, if playing)."""
+        if event.inaxes != self.ax or event.button != 3 or event.xdata is None:
+            return
+        self.set_cursor(event.xdata)
+        self.drag_from, self.drag_x = self.cursor, event.x
+
+    def drag_range(self, event):
+        """The samples between where a right drag started and 'event', or None if it's just a click."""
+        if event.inaxes != self.ax or event.xdata is None or abs(event.x - self.drag_x) < 5:
+            return None
+        pos = int(max(0, min(round(event.xdata), self.max_samples)))
+        if pos == self.drag_from:
+            return None
+        return min(self.drag_from, pos), max(self.drag_from, pos)
+
+    def on_drag(self, event):
+        """Right drag: shade the selection as it's made (measuring waits for the release)."""
+        if self.drag_from is None:
+            return
+        self.shade_selection(self.drag_range(event) or self.selection())
+
+    def on_release(self, event):
+        """End of a right drag makes the selection, a plain click moves the playhead."""
+        if self.drag_from is None or event.button != 3:
+            return
+        sel = self.drag_range(event)
+        self.drag_from = None
+        if sel is not None:
+            self.snap_selection(*sel)
+            return
+        self.shade_selection(self.selection())
+        if self.player is not None and not self.play_loop:
+            self.play(self.cursor, self.max_samples)
+
+    def audible(self):
+        """Which entries are shown and played: just the soloed one, or all but the muted ones."""
+        if self.solo is not None:
+            return [self.solo]
+        return [i for i in range(len(self.mapped_files)) if i not in self.muted]
+
+    def toggle_mute(self, i):
+        """'1'..'9': hide and mute that entry, or bring it back."""
+        if i < len(self.mapped_files):
+            self.muted ^= {i}
+            self.update_traces()
+
+    def toggle_solo(self, i):
+        """alt+'1'..'9': show and play only that entry, or everything that isn't muted again."""
+        if i < len(self.mapped_files):
+            self.solo = None if self.solo == i else i
+            self.update_traces()
+
+    def update_traces(self):
+        """The legend says what's muted or has effects on, and the view follows."""
+        audible = self.audible()
+        for i, (line, (_, name)) in enumerate(zip(self.lines, self.mapped_files)):
+            if self.show_effects and i in self.effects:
+                name += f" + {self.effects[i][0]}"
+            gain, offset = self.trace_adjust.get(i, (1.0, 0.0))
+            if gain != 1.0:
+                name += f" x{gain:g}"
+            if offset:
+                name += " (moved)"
+            line.set_label(name if i in audible else f"{name} (muted)")
+            line.set_linewidth(1.6 if i == self.focus and len(self.lines) > 1 else 0.8)
+        self.ax.legend(loc='upper right', fontsize='x-small')
+        xlim = self.ax.get_xlim()
+        self.update_view(xlim[0], xlim[1] - xlim[0])
+
+    def next_trace(self):
+        """Tab: give the next entry the focus (drawn thicker) for the trace keys."""
+        self.focus = (self.focus + 1) % len(self.mapped_files)
+        print(f"Focus on {self.mapped_files[self.focus][1]}")
+        self.update_traces()
+
+    def adjust_trace(self, gain, offset):
+        """
+        Scale the focused trace by 'gain' and move it by 'offset' of the
+        view's height (or put it back, with None), just on screen, so quiet
+        and loud files overlaid can both be seen.
+        """
+        if gain is None:
+            self.trace_adjust.pop(self.focus, None)
+        else:
+            old_gain, old_offset = self.trace_adjust.get(self.focus, (1.0, 0.0))
+            ylim = self.ax.get_ylim()
+            self.trace_adjust[self.focus] = (old_gain * gain, old_offset + offset * (ylim[1] - ylim[0]))
+        # Stay put, rather than autoscaling to follow the trace
+        self.set_y_locked(True)
+        self.update_traces()
+
+    def samples(self, i):
+        """What entry i shows and plays: with its effects on, if it has any and they're shown."""
+        if self.show_effects and i in self.effects:
+            return self.effects[i][1]
+        return self.mapped_files[i][0]
+
+    def check_effects(self):
+        """Effects timer: show the chains that have finished running."""
+        for i, job in list(self.effect_jobs.items()):
+            if not job.done:
+                continue
+            del self.effect_jobs[i]
+            name = self.mapped_files[i][1]
+            if job.error:
+                print(job.error)
+            elif self.mapped_files[i][0] is not job.mm:
+                print(f"{name} changed while its effects were running, run them again")
+            else:
+                self.effects[i] = (" | ".join(" ".join(stage) for stage in job.stages), job.out)
+                self.show_effects = True
+                print(f"{name} has its effects on")
+                self.update_traces()
+        if not self.effect_jobs:
+            self.effects_timer.stop()
+
+    def set_effects(self, i, spec):
+        """
+        Put a chain of effects on entry i (or take it off, with None): stages
+        split by '|' or lines, like 'echo 0.3 0.3 0.3 0.3 | phaser', or a
+        preset file of them ('#' starts a comment). Nothing gets written out.
+        """
+        if i >= len(self.mapped_files):
+            print(f"There's no file {i + 1}")
+            return
+        job = self.effect_jobs.pop(i, None)
+        if job:
+            job.cancel()
+        if spec is None:
+            self.effects.pop(i, None)
+            self.update_traces()
+            return
+        if self.rate != EFFECT_RATE:
+            print(f"Effects only run at {EFFECT_RATE} Hz")
+            return
+        if os.path.isfile(spec):
+            with open(spec) as f:
+                spec = f.read()
+        chain = []
+        for stage in re.split(r'[|\n]', spec):
+            words = stage.split('#')[0].split()
+            if words:
+                chain.append(words)
+        if not chain:
+            print("No effects")
+            return
+
+        try:
+            self.effect_jobs[i] = EffectChain(self.mapped_files[i][0], chain, self.full_scale)
+        except OSError as e:
+            print(f"Can't run effects: {e}")
+            return
+        print(f"Running {self.mapped_files[i][1]} through {len(chain)} effect(s)...")
+        self.effects_timer.start()
+
+    def toggle_effects(self):
+        """'e': show and play the files as they are, or with their effects on."""
+        if self.effects:
+            self.show_effects = not self.show_effects
+            self.update_traces()
+
+    def export(self, i, name, fmt=None, whole=False):
+        """
+        Write the selection (or the view, or all of it) of entry i to a new
+        file, as it's shown (so with any edits and effects): WAV if the name
+        ends in '.wav', raw otherwise, with a sidecar saying what it is. The
+        format defaults to what the entry is in.
+        """
+        if i >= len(self.mapped_files):
+            print(f"There's no file {i + 1}")
+            return
+        wav = name.lower().endswith('.wav')
+        if fmt is None and wav:
+            fmt = {'i2': '16', 'i4': '32'}.get(self.dtype.str[1:], 'float')
+        formats = WAV_FORMATS if wav else SAMPLE_FORMATS
+        fmt = fmt or self.sample_format
+        if fmt not in formats:
+            print(f"Bad {'WAV' if wav else 'raw'} format {fmt}, use one of {', '.join(formats)}")
+            return
+
+        mm = self.samples(i)
+        sel = self.selection()
+        xlim = self.ax.get_xlim()
+        start, end = sel if sel else (int(max(0, xlim[0])), int(xlim[1]))
+        if whole:
+            start, end = 0, mm.size
+        end = min(end, mm.size)
+        if start >= end:
+            print("Nothing there to export")
+            return
+
+        dtype, full_scale = formats[fmt]
+        dtype = np.dtype(dtype)
+        size = 3 if fmt == '24' else dtype.itemsize
+        data_bytes = (end - start) * size
+        # Chunks are padded to an even length, and the RIFF size has to fit in 32 bits
+        pad = data_bytes & 1
+        if wav and 36 + data_bytes + pad > 0xffffffff:
+            print(f"{end - start} samples of {fmt} is too big for a WAV file, export it as raw")
+            return
+        if not wav and os.path.exists(name + ".json"):
+            print(f"Can't export: {name}.json is already there")
+            return
+        try:
+            # Never over anything
+            with open(name, 'xb') as out:
+                if wav:
+                    out.write(struct.pack('<4sI4s4sIHHIIHH4sI', b'RIFF', 36 + data_bytes + pad, b'WAVE',
+                                          b'fmt ', 16, 3 if dtype.kind == 'f' else 1, 1,
+                                          self.rate, self.rate * size, size, 8 * size,
+                                          b'data', data_bytes))
+                for pos in range(start, end, MEASURE_CHUNK):
+                    x = mm[pos:min(pos + MEASURE_CHUNK, end)].astype(np.float64) * (full_scale / self.full_scale)
+                    if dtype.kind == 'i':
+                        x = np.clip(np.round(x), -full_scale, full_scale - 1)
+                    x = x.astype(dtype)
+                    if size != dtype.itemsize:
+                        x = x.view(np.uint8).reshape(-1, dtype.itemsize)[:, :size]
+                    out.write(x.tobytes())
+                if wav and pad:
+                    out.write(b'\0')
+            if not wav:
+                with open(name + ".json", 'x') as sidecar:
+                    notes = f"{self.mapped_files[i][1]} samples {start}..{end}"
+                    json.dump({'rate': self.rate, 'format': fmt, 'notes': notes}, sidecar, indent=1)
+                    sidecar.write("\n")
+        except OSError as e:
+            print(f"Can't export: {e}")
+            return
+        print(f"Wrote {end - start} samples of {self.mapped_files[i][1]} to {name} ({fmt})")
+
+    def scaled(self, x, gain):
+        """Samples times gain, back in the file's format (clipped, if that's integers)."""
+        x = x.astype(np.float64) * gain
+        if self.dtype.kind == 'i':
+            info = np.iinfo(self.dtype)
+            x = np.clip(np.round(x), info.min, info.max)
+        return x.astype(self.dtype)
+
+    def edit(self, what, i, args):
+        """
+        Edit the selection of entry i in memory: 'trim' to it, 'delete' it,
+        'gain' it by some dB or 'normalize' its peak (to 0 dBFS, or some other
+        level). Nothing changes on disk until 'save' writes the entry out.
+        """
+        if i >= len(self.mapped_files):
+            print(f"There's no file {i + 1}")
+            return
+        mm, name = self.mapped_files[i]
+        if what == 'save':
+            if 1 <= len(args) <= 2:
+                self.export(i, *args, whole=True)
+            else:
+                print("Save it as what?")
+            return
+
+        sel = self.selection()
+        if sel is None:
+            print(f"Select what to {what} first")
+            return
+        start, end = sel[0], min(sel[1], mm.size)
+        if start >= end:
+            print(f"{name} doesn't go that far")
+            return
+        try:
+            db = [float(arg) for arg in args]
+        except ValueError:
+            db = None
+        takes = 1 if what in ('gain', 'normalize') else 0
+        if db is None or len(db) > takes or (what == 'gain' and not db):
+            print(f"Bad {what} {' '.join(args)}")
+            return
+
+        if what == 'trim':
+            edited, sel = np.array(mm[start:end]), (0, end - start)
+        elif what == 'delete':
+            edited, sel = np.concatenate((mm[:start], mm[end:])), None
+        else:
+            gain = 10 ** ((db[0] if db else 0.0) / 20)
+            if what == 'normalize':
+                peak = self.measure(mm, start, end)['peak']
+                if peak == 0:
+                    print("Nothing to normalize, it's silent")
+                    return
+                gain /= peak
+            edited = np.array(mm)
+            edited[start:end] = self.scaled(mm[start:end], gain)
+            what = f"{what} {20 * np.log10(gain):+.2f} dB"
+
+        self.undo_stack.append((i, mm, self.selection(), what))
+        self.mapped_files[i] = (edited, name)
+        self.after_edit(i, sel)
+        print(f"{what} on {name} ('u' undoes it)")
+
+    def undo(self):
+        """'u': take back the last edit."""
+        if not self.undo_stack:
+            print("Nothing to undo")
+            return
+        i, mm, sel, what = self.undo_stack.pop()
+        name = self.mapped_files[i][1]
+        self.mapped_files[i] = (mm, name)
+        self.after_edit(i, sel)
+        print(f"Undid {what} on {name}")
+
+    def after_edit(self, i, sel):
+        """The view, selection and readouts for entry i changing length or content."""
+        if self.effects.pop(i, None):
+            print("Its effects came off, put them back on with 'effect'")
+        if self.null is not None:
+            self.toggle_null()
+        self.max_samples = max(mm.size for mm, _ in self.mapped_files)
+        self.update_slider_range()
+        self.set_selection(*(sel or (None, None)))
+        self.update_traces()
+        self.sync_slider()
+
+    def screenshot(self, name, style='braille'):
+        """
+        Draw what's in view of the focused entry with analyze's overview (see
+        render.h): a PNG or SVG image the size of the plot, in its colors,
+        or text ('ascii', 'blocks' or 'braille') the size of the terminal.
+        """
+        mm, label = self.samples(self.focus), self.mapped_files[self.focus][1]
+        xlim = self.ax.get_xlim()
+        start, end = int(max(0, xlim[0])), int(min(xlim[1], mm.size))
+        if end - start < 2:
+            print(f"{label} has nothing in view")
+            return
+        image = name.lower().endswith(('.png', '.svg'))
+        cmd = ['overview']
+        if image:
+            box = self.ax.get_window_extent()
+            background = to_rgb(self.colors['background'])
+            peak = to_rgb(self.lines[self.focus].get_color())
+            rms = [(p + b) / 2 for p, b in zip(peak, background)]
+            colors = ",".join("%02x%02x%02x" % tuple(round(255 * c) for c in rgb) for rgb in (background, peak, rms))
+            cmd += [f'--output={name}', f'--size={int(box.width)}x{int(box.height)}', f'--colors={colors}']
+            if self.y_mode == 'dB':
+                cmd.append(f'--db={DB_RANGE}')
+        elif style in ('ascii', 'blocks', 'braille'):
+            size = shutil.get_terminal_size()
+            cmd += [f'--draw={style}', str(size.columns), str(size.lines - 1)]
+        else:
+            print(f"Bad drawing style {style}, use ascii, blocks or braille")
+            return
+
+        out = self.run_analyze(mm, start, end, cmd)
+        if out is None:
+            return
+        if not image:
+            try:
+                with open(name, 'wb') as f:
+                    f.write(out)
+            except OSError as e:
+                print(f"Can't save the screenshot: {e}")
+                return
+        print(f"Drew {label} {start}..{end} to {name}")
+
+    def run_analyze(self, mm, start, end, args):
+        """
+        Run analyze with these arguments on samples [start, end) of an entry,
+        fed to it as f32le on stdin. Returns what it printed, or None after
+        saying what went wrong.
+        """
+        try:
+            proc = subprocess.Popen([ANALYZE, *args, '--format=f32le', '-'],
+                                    stdin=subprocess.PIPE, stdout=subprocess.PIPE, stderr=subprocess.PIPE)
+        except OSError as e:
+            print(f"Can't run analyze: {e}")
+            return None
+        # It reads all of it before writing anything, so this can't get stuck
+        try:
+            for pos in range(start, end, MEASURE_CHUNK):
+                chunk = mm[pos:min(pos + MEASURE_CHUNK, end)].astype(np.float64) / self.full_scale
+                proc.stdin.write(chunk.astype('<f4').tobytes())
+        except BrokenPipeError:
+            pass
+        out, err = proc.communicate()
+        if proc.returncode:
+            print(f"analyze: {err.decode(errors='replace').strip() or 'failed'}")
+            return None
+        return out
+
+    def find_onsets(self, i=None):
+        """
+        'T': mark the onsets (see onset.h) of the focused entry, or entry i,
+        in the selection or else all of it, so ',' and '.' go from hit to hit.
+        """
+        i = self.focus if i is None else i
+        if i >= len(self.mapped_files):
+            print(f"There's no file {i + 1}")
+            return
+        mm, label = self.samples(i), self.mapped_files[i][1]
+        start, end = self.selection() or (0, mm.size)
+        end = min(end, mm.size)
+        if end - start < 2:
+            print(f"{label} has nothing there")
+            return
+        out = self.run_analyze(mm, start, end, ['onsets'])
+        if out is None:
+            return
+        found = [start + int(n) for n in re.findall(rb'\(sample (\d+)\)', out)]
+        taken = {pos for pos, _ in self.markers}
+        self.update_markers(self.markers + [(pos, ONSET_MARKER) for pos in found if pos not in taken])
+        print(f"{len(found)} onsets in {label} {start}..{end}")
+
+    def clear_onsets(self):
+        """':onsets off': take the onset markers away again."""
+        self.update_markers([m for m in self.markers if m[1] != ONSET_MARKER])
+
+    def prompt_screenshot(self):
+        """'P': start a screenshot command, to fill in and change."""
+        # Setting the text submits it too, so make that a no-op
+        self.running_command = True
+        try:
+            self.command.set_val("screenshot view.png")
+        finally:
+            self.running_command = False
+        self.command.begin_typing()
+
+    def prompt_export(self):
+        """'E': start an export command for the first file shown, to fill in and change."""
+        audible = self.audible()
+        i = audible[0] if audible else 0
+        stem = os.path.splitext(os.path.basename(self.paths[i]))[0]
+        if stem == '-':
+            stem = 'stdin'
+        # Setting the text submits it too, so make that a no-op
+        self.running_command = True
+        try:
+            self.command.set_val(f"export {i + 1} {stem}-export.wav")
+        finally:
+            self.running_command = False
+        self.command.begin_typing()
+
+    def mix(self, start, end):
+        """What's audible over [start, end), averaged together as f32le (full scale is 1.0)."""
+        out = np.zeros(end - start, dtype='<f4')
+        audible = self.audible()
+        for i in audible:
+            # Files can end early
+            chunk = self.samples(i)[start:end]
+            out[:chunk.size] += chunk / self.full_scale
+        return out / max(1, len(audible))
+
+    def play(self, start, end, loop=False):
+        """
+        Play samples [start, end) with ffplay (like 'make play'), maybe over and
+        over. With several files that's a mix of what isn't muted, and muting
+        or soloing while playing is heard straight away.
+        """
+        self.stop()
+        start, end = int(max(0, start)), int(min(end, self.max_samples))
+        if start >= end:
+            return
+        cmd = ['ffplay', '-v', 'fatal', '-nodisp', '-autoexit',
+               '-f', 'f32le', '-ar', str(self.rate), '-ch_layout', 'mono', '-i', 'pipe:0']
+        try:
+            self.player = subprocess.Popen(cmd, stdin=subprocess.PIPE)
+        except OSError as e:
+            print(f"Can't play: {e}")
+            return
+
+        # One continuous stream, so looping wraps around without a gap
+        def feed(player):
+            try:
+                while True:
+                    for pos in range(start, end, PLAY_CHUNK):
+                        player.stdin.write(self.mix(pos, min(pos + PLAY_CHUNK, end)).tobytes())
+                    if not loop:
+                        break
+                player.stdin.close()
+            except (OSError, ValueError):
+                # Stopped, so the pipe is gone
+                pass
+
+        threading.Thread(target=feed, args=(self.player,), daemon=True).start()
+        self.play_start, self.play_end, self.play_loop = start, end, loop
+        self.play_time = time.monotonic()
+        self.playhead.set_visible(True)
+        self.playhead_timer.start()
+
+    def stop(self):
+        """Stop playing, and remember where we got to."""
+        if self.player is None:
+            return
+        self.play_pos = self.playhead.get_xdata()[0]
+        self.player.terminate()
+        self.player.wait()
+        self.player = None
+        self.playhead_timer.stop()
+
+    def toggle_playback(self):
+        """Space: pause, or play from where we paused (if that's in view) or the start of the view."""
+        if self.player is not None:
+            self.stop()
+            return
+        xlim = self.ax.get_xlim()
+        start = xlim[0]
+        if self.play_pos is not None and xlim[0] <= self.play_pos < xlim[1]:
+            start = self.play_pos
+        self.play(start, self.max_samples)
+
+    def toggle_loop(self):
+        """Enter: play the selection over and over, or stop."""
+        if self.player is not None:
+            self.stop()
+            return
+        sel = self.selection()
+        if sel is not None:
+            self.play(sel[0], sel[1], loop=True)
+
+    def update_playhead(self):
+        """Playhead timer: move the line, and keep it in view."""
+        if self.player is None:
+            return
+        played = (time.monotonic() - self.play_time) * self.rate
+        if self.play_loop:
+            played %= self.play_end - self.play_start
+        pos = self.play_start + played
+        if pos >= self.play_end or self.player.poll() is not None:
+            self.stop()
+            self.playhead.set_visible(False)
+            self.play_pos = None
+            self.fig.canvas.draw_idle()
+            return
+        self.playhead.set_xdata([pos, pos])
+        self.update_spectrum()
+
+        # Auto-follow: when the playhead runs off the right, turn the page
+        xlim = self.ax.get_xlim()
+        if not self.navigating and not xlim[0] <= pos < xlim[1]:
+            width = xlim[1] - xlim[0]
+            self.update_view(pos, width)
+            self.sync_slider()
+        self.fig.canvas.draw_idle()
+
+    def toggle_spectrogram(self):
+        """'S': split the plot, with the spectrogram of the focused entry under the waveform."""
+        self.spectrogram = not self.spectrogram
+        x, y, w, h = self.wave_pos.bounds
+        if self.spectrogram:
+            gap = 0.05
+            self.ax.set_position([x, y + (h + gap) / 2, w, (h - gap) / 2])
+            self.spec_ax.set_position([x, y, w, (h - gap) / 2])
+            xlim = self.ax.get_xlim()
+            self.update_spectrogram(xlim[0], xlim[1] - xlim[0])
+        else:
+            self.ax.set_position(self.wave_pos)
+        self.spec_ax.set_visible(self.spectrogram)
+        self.fig.canvas.draw_idle()
+
+    def stft(self, start, end, size):
+        """
+        Magnitudes (as a fraction of full scale) of windowed FFTs of the focused
+        entry, as it's shown (see samples()), over [start, end): one column per
+        frame, at most SPEC_COLUMNS of them spread evenly over a wide range.
+        None if there's not enough data.
+        """
+        mm = self.samples(self.focus)
+        start, end = int(max(0, start)), int(min(end, mm.size))
+        if end - start < size:
+            return None
+        columns = max(1, min(SPEC_COLUMNS, (end - start) // (size // 2)))
+        window = np.hanning(size)
+        # Full scale sine (windowed) comes out at 1
+        scale = 2 / (window.sum() * self.full_scale)
+        mags = np.empty((size // 2 + 1, columns))
+        positions = np.linspace(start, end - size, columns).astype(np.int64)
+        for i, pos in enumerate(positions):
+            frame = mm[pos:pos + size].astype(np.float64) * window
+            mags[:, i] = np.abs(np.fft.rfft(frame)) * scale
+        return mags
+
+    def update_spectrogram(self, start_sample, window_samples):
+        """STFT magnitude (dBFS) of the view."""
+        start, end = max(0, start_sample), start_sample + window_samples
+        mags = self.stft(start, end, SPEC_FFT)
+        if mags is None:
+            return
+        spec = 20 * np.log10(np.maximum(mags, 10 ** (SPEC_FLOOR_DB / 20)))
+        extent = (start, min(end, self.samples(self.focus).size), 0, self.rate / 2)
+        if self.spec_image is None:
+            self.spec_image = self.spec_ax.imshow(spec, origin='lower', aspect='auto', extent=extent,
+                                                  cmap='magma', vmin=SPEC_FLOOR_DB, vmax=0,
+                                                  interpolation='nearest')
+        else:
+            self.spec_image.set_data(spec)
+            self.spec_image.set_extent(extent)
+        self.spec_ax.set_ylim(0, self.rate / 2)
+
+    def toggle_spectrum(self):
+        """'F': open (or close) the spectrum panel."""
+        if self.spectrum_fig is not None:
+            plt.close(self.spectrum_fig)
+            return
+        self.spectrum_fig, ax = plt.subplots(figsize=(6, 4))
+        self.spectrum_fig.canvas.manager.set_window_title("Spectrum")
+        self.spectrum_line, = ax.semilogx([], [], linewidth=0.8)
+        ax.set_xlim(self.rate / SPECTRUM_FFT, self.rate / 2)
+        ax.set_ylim(SPEC_FLOOR_DB, 0)
+        ax.set_xlabel("Frequency (Hz)")
+        ax.set_ylabel("Level (dBFS)")
+        ax.grid(True, which='both', linestyle=':', alpha=0.5)
+
+        def closed(event):
+            self.spectrum_fig = None
+        self.spectrum_fig.canvas.mpl_connect('close_event', closed)
+        self.update_spectrum()
+        self.spectrum_fig.show()
+
+    def add_file(self, f):
+        """Add a file to what's shown, as if it had been on the command line."""
+        meta = read_sidecar(f)
+        if meta.get('format', self.sample_format) != self.sample_format or meta.get('rate', self.rate) != self.rate:
+            print(f"{f} says it's {meta.get('format', self.sample_format)} at {meta.get('rate', self.rate)} Hz, "
+                  f"showing it as {self.sample_format} at {self.rate} Hz like the rest")
+        first = len(self.mapped_files)
+        try:
+            self.load_file(f)
+        except Exception as e:
+            # Nothing half added
+            del self.mapped_files[first:], self.paths[first:], self.layout[first:]
+            print(f"Error opening {f}: {e}")
+            return
+        for _, name in self.mapped_files[first:]:
+            line, = self.ax.plot([], [], linewidth=0.8, label=name)
+            self.lines.append(line)
+        self.update_slider_range()
+        self.set_lane_ticks()
+        self.update_traces()
+        self.sync_slider()
+        print(f"Added {f}")
+
+    def open_browser(self):
+        """'o': a window listing a directory to add files from (see browser_key)."""
+        if self.browser_fig is not None:
+            plt.close(self.browser_fig)
+            return
+        self.browser_fig = plt.figure(figsize=(7, 7))
+        self.browser_fig.canvas.manager.set_window_title("Open")
+        ax = self.browser_fig.add_axes([0, 0, 1, 1])
+        ax.set_axis_off()
+        self.browser_text = ax.text(0.02, 0.98, "", va='top', family='monospace', fontsize='small',
+                                    transform=ax.transAxes)
+
+        def closed(event):
+            self.browser_fig = None
+        self.browser_fig.canvas.mpl_connect('close_event', closed)
+        self.browser_fig.canvas.mpl_connect('key_press_event', self.browser_key)
+        self.browse(self.browse_dir)
+        self.browser_fig.show()
+
+    def browse(self, path):
+        """List a directory in the browser: subdirectories first, then files (no hidden ones)."""
+        try:
+            names = sorted(n for n in os.listdir(path) if not n.startswith('.'))
+        except OSError as e:
+            print(f"Can't list {path}: {e}")
+            return
+        dirs = [n + "/" for n in names if os.path.isdir(os.path.join(path, n))]
+        files = [n for n in names if not os.path.isdir(os.path.join(path, n))]
+        self.browse_dir = path
+        self.browse_items = ["../"] + dirs + files
+        self.browse_index = 0
+        self.draw_browser()
+
+    def file_preview(self, path):
+        """How big a file is, and how long, in its sidecar's format and rate or what we're showing."""
+        size = os.path.getsize(path)
+        meta = read_sidecar(path)
+        fmt = meta.get('format', self.sample_format)
+        rate = meta.get('rate', self.rate)
+        channels = int(meta.get('channels', 1))
+        text = f"{size} bytes"
+        if fmt in SAMPLE_FORMATS and rate and channels > 0:
+            frames = size // (np.dtype(SAMPLE_FORMATS[fmt][0]).itemsize * channels)
+            text += f", {format_time(frames / rate)} as {fmt} at {rate} Hz"
+            if channels > 1:
+                text += f", {channels} channels"
+        return text
+
+    def draw_browser(self):
+        """The listing around the highlighted entry, and what that entry is."""
+        first = max(0, min(self.browse_index - BROWSER_LINES // 2, len(self.browse_items) - BROWSER_LINES))
+        lines = [self.browse_dir, ""]
+        for i, name in enumerate(self.browse_items[first:first + BROWSER_LINES], first):
+            lines.append(("> " if i == self.browse_index else "  ") + name)
+        path = os.path.join(self.browse_dir, self.browse_items[self.browse_index])
+        lines.append("")
+        try:
+            lines.append("" if os.path.isdir(path) else self.file_preview(path))
+        except OSError as e:
+            lines.append(str(e))
+        lines.append("up/down pick, enter opens, backspace goes up, escape closes")
+        self.browser_text.set_text("\n".join(lines))
+        self.browser_fig.canvas.draw_idle()
+
+    def browser_key(self, event):
+        """Keys in the browser window."""
+        moves = {'up': -1, 'down': 1, 'pageup': -BROWSER_LINES, 'pagedown': BROWSER_LINES}
+        if event.key in moves:
+            self.browse_index = max(0, min(self.browse_index + moves[event.key], len(self.browse_items) - 1))
+            self.draw_browser()
+        elif event.key == 'backspace':
+            self.browse(os.path.dirname(self.browse_dir.rstrip(os.sep)) or os.sep)
+        elif event.key == 'enter':
+            path = os.path.normpath(os.path.join(self.browse_dir, self.browse_items[self.browse_index]))
+            if os.path.isdir(path):
+                self.browse(path)
+            else:
+                self.add_file(path)
+        elif event.key == 'escape':
+            plt.close(self.browser_fig)
+
+    def update_spectrum(self, start=None, end=None):
+        """Average power spectrum of [start, end) (the view by default), or of what's around the playhead while playing."""
+        if self.spectrum_fig is None:
+            return
+        if self.player is not None:
+            pos = self.playhead.get_xdata()[0]
+            start, end = pos - 2 * SPECTRUM_FFT, pos + 2 * SPECTRUM_FFT
+        elif start is None:
+            start, end = self.ax.get_xlim()
+        mags = self.stft(start, end, SPECTRUM_FFT)
+        if mags is None:
+            return
+        power = np.mean(mags * mags, axis=1)
+        db = 10 * np.log10(np.maximum(power, 10 ** (SPEC_FLOOR_DB / 10)))
+        freqs = np.fft.rfftfreq(SPECTRUM_FFT, 1 / self.rate)
+        # No DC on a log axis
+        self.spectrum_line.set_data(freqs[1:], db[1:])
+        self.spectrum_fig.canvas.draw_idle()
+
+    def db_scale(self, chunk):
+        """Samples on a dB scale mirrored around zero: 0 dBFS at +-1, -DB_RANGE dBFS (or less) at 0."""
+        x = chunk.astype(np.float64)
+        with np.errstate(divide='ignore'):
+            db = 20 * np.log10(np.abs(x) / self.full_scale)
+        return np.sign(x) * np.clip(1 + db / DB_RANGE, 0, 1)
 
     def get_chunk(self, start_sample, window_samples):
         # start_sample is a float from matplotlib axes often, cast to int
@@ -239,17 +2253,28 @@ class WaveformVisualizer:
 
         end_sample = start_sample + window_samples
 
+        # The meters follow the view
+        self.view_range = (start_sample, end_sample)
+        if self.meters != 'off':
+            self.update_status()
+
         # Determine downsampling step to keep plot fast
         total_samples = window_samples
         step = 1
         if total_samples > MAX_PLOT_POINTS:
             step = int(np.ceil(total_samples / MAX_PLOT_POINTS))
 
-        global_min_y, global_max_y = 2147483647, -2147483648
+        if self.null is not None:
+            self.update_panels(start_sample, end_sample)
+            return self.null_chunk(start_sample, end_sample, step)
+
+        global_min_y, global_max_y = np.inf, -np.inf
         has_data = False
 
-        for line, (mm, _) in zip(self.lines, self.mapped_files):
-            if start_sample >= mm.size:
+        audible = self.audible()
+        for i, line in enumerate(self.lines):
+            mm = self.samples(i)
+            if start_sample >= mm.size or i not in audible:
                 line.set_data([], [])
                 continue
 
@@ -259,8 +2284,12 @@ class WaveformVisualizer:
                  line.set_data([], [])
                  continue
 
-            # Strided slice (View into memory map - very fast)
-            chunk = mm[start_sample:safe_end:step]
+            # Zoomed out, at the same multiples of step whatever the view (so
+            # panning doesn't make it shimmer), out of the tile cache
+            if step > 1:
+                first, chunk = self.tile_cache.points(i, mm, start_sample, safe_end, step)
+            else:
+                first, chunk = start_sample, mm[start_sample:safe_end]
 
             if chunk.size > 0:
                 # Generate X Axis without allocation using pre-allocated buffer
@@ -284,9 +2313,25 @@ class WaveformVisualizer:
 
                 # Apply scaling and offset in-place
                 target_buffer *= step
-                target_buffer += start_sample
+                target_buffer += first
 
-                line.set_data(target_buffer, chunk)
+                # Lanes scale everything to fit anyway
+                gain, offset = (1.0, 0.0) if self.lanes else self.trace_adjust.get(i, (1.0, 0.0))
+                if gain != 1.0:
+                    chunk = chunk.astype(np.float64) * gain
+
+                lo, hi = float(np.min(chunk)), float(np.max(chunk))
+                if self.y_mode == 'dB':
+                    y, peak = self.db_scale(chunk), 1
+                else:
+                    y, peak = chunk, max(abs(lo), abs(hi), 0.00078 * self.full_scale)
+                if self.lanes:
+                    # Lane 0 at the bottom, each file scaled to its own peak (dB is already to scale)
+                    lane = len(self.mapped_files) - 1 - i
+                    line.set_data(target_buffer, lane + y.astype(np.float64) * (LANE_FILL / 2 / peak))
+                else:
+                    line.set_data(target_buffer, y + offset if offset else y)
+                lo, hi = lo + offset, hi + offset
 
                 # Show markers if zooming in enough (step must be 1 to show true samples)
                 if step == 1 and chunk.size < 300:
@@ -295,14 +2340,22 @@ class WaveformVisualizer:
                 else:
                     line.set_marker("")
 
-                global_min_y = min(global_min_y, np.min(chunk))
-                global_max_y = max(global_max_y, np.max(chunk))
+                global_min_y = min(global_min_y, lo)
+                global_max_y = max(global_max_y, hi)
                 has_data = True
             else:
                 line.set_data([], [])
 
+        self.update_panels(start_sample, end_sample)
+
         return has_data, global_min_y, global_max_y
 
+    def update_panels(self, start_sample, end_sample):
+        """The spectrogram and spectrum follow the view."""
+        if self.spectrogram:
+            self.update_spectrogram(start_sample, end_sample - start_sample)
+        self.update_spectrum(start_sample, end_sample)
+
     def update_view(self, start_sample, width_samples):
         """Core update logic: loads data and sets limits (Constrained Mode)."""
         if self.navigating: return
@@ -319,11 +2372,17 @@ class WaveformVisualizer:
             # IMPORTANT: Set limits on the MAIN axes, explicitly using self.ax
             self.ax.set_xlim(start_sample, start_sample + width_samples)
 
-            # Tight Y-axis scaling logic
-            if has_data and max_y > min_y:
+            # Tight Y-axis scaling logic, unless the range is locked
+            if self.lanes and self.null is None:
+                self.ax.set_ylim(-0.5, len(self.mapped_files) - 0.5)
+            elif self.y_locked:
+                pass
+            elif self.y_mode == 'dB':
+                self.ax.set_ylim(-1.05, 1.05)
+            elif has_data and max_y > min_y:
                 # Symmetric zoom centered at 0
                 max_val = max(abs(min_y), abs(max_y))
-                min_val = 1670000 # very approximately 1.1mV
+                min_val = 0.00078 * self.full_scale # very approximately 1.1mV
 
                 if max_val < min_val:
                     max_val = min_val
@@ -333,7 +2392,7 @@ class WaveformVisualizer:
                 self.ax.set_ylim(-max_val, max_val)
             else:
                  # Default fallback if no data (-1.0 to 1.0 equivalent)
-                 self.ax.set_ylim(-2147483648, 2147483648)
+                 self.ax.set_ylim(-self.full_scale, self.full_scale)
 
             self.fig.canvas.draw_idle()
         finally:
@@ -379,7 +2438,7 @@ class WaveformVisualizer:
 
     def on_scroll(self, event):
         """Handle zoom."""
-        if event.inaxes != self.ax: return
+        if event.inaxes not in (self.ax, self.spec_ax): return
 
         xlim = self.ax.get_xlim()
         cur_width = xlim[1] - xlim[0]
@@ -440,86 +2499,311 @@ class WaveformVisualizer:
             self.navigating = False
 
     def on_key(self, event):
-        """Handle keyboard shortcuts (Independent Navigation)."""
+        """Handle keyboard shortcuts: whatever action the key is bound to (see KEYMAPS)."""
         if self.navigating: return
-        self.navigating = True
+        # Typing a marker name or a command
+        if any(box.capturekeystrokes for box in (self.marker_name, self.command, self.palette)): return
+        action = self.keys.get(event.key)
+        if action is not None:
+            self.actions[action]()
+
+    def bind_keys(self):
+        """The key -> action table from the config's keymap and [keys], and what each action does."""
+        self.actions = {
+            'pan_left': lambda: self.navigate(self.pan_x, -0.25),
+            'pan_right': lambda: self.navigate(self.pan_x, 0.25),
+            'pan_up': lambda: self.navigate(self.pan_y, 0.25),
+            'pan_down': lambda: self.navigate(self.pan_y, -0.25),
+            'zoom_in': lambda: self.navigate(self.zoom_x, 0.5),
+            'zoom_out': lambda: self.navigate(self.zoom_x, 2.0),
+            'y_zoom_in': lambda: self.navigate(self.zoom_y, 0.5),
+            'y_zoom_out': lambda: self.navigate(self.zoom_y, 2.0),
+            'y_lock': self.toggle_y_lock,
+            'y_auto': self.auto_y,
+            'cursor_left': lambda: self.move_cursor(-1),
+            'cursor_right': lambda: self.move_cursor(1),
+            'mark_in': self.mark_in,
+            'mark_out': self.mark_out,
+            'clear_selection': lambda: self.set_selection(None, None),
+            'add_marker': self.add_marker,
+            'delete_marker': self.delete_marker,
+            'list_markers': self.list_markers,
+            'prev_marker': lambda: self.jump_marker(-1),
+            'next_marker': lambda: self.jump_marker(1),
+            'play': self.toggle_playback,
+            'loop': self.toggle_loop,
+            'lanes': self.toggle_lanes,
+            'spectrogram': self.toggle_spectrogram,
+            'spectrum': self.toggle_spectrum,
+            'save_session': self.save_session,
+            'command': lambda: self.command.begin_typing(),
+            'next_over': self.next_over,
+            'next_silence': self.next_silence,
+            'skip_silence': self.skip_silence,
+            'null': self.toggle_null,
+            'effects': self.toggle_effects,
+            'export': self.prompt_export,
+            'undo': self.undo,
+            'next_trace': self.next_trace,
+            'trace_up': lambda: self.adjust_trace(1.0, 0.1),
+            'trace_down': lambda: self.adjust_trace(1.0, -0.1),
+            'trace_bigger': lambda: self.adjust_trace(2.0, 0.0),
+            'trace_smaller': lambda: self.adjust_trace(0.5, 0.0),
+            'trace_reset': lambda: self.adjust_trace(None, None),
+            'meters': self.toggle_meters,
+            'snap_zero': self.toggle_snap,
+            'palette': self.open_palette,
+            'open': self.open_browser,
+            'screenshot': self.prompt_screenshot,
+            'onsets': self.find_onsets,
+            **{f'mute_{n}': lambda i=n - 1: self.toggle_mute(i) for n in range(1, 10)},
+            **{f'solo_{n}': lambda i=n - 1: self.toggle_solo(i) for n in range(1, 10)},
+        }
+
+        name = self.config.get('keymap', 'default')
+        if name not in KEYMAPS:
+            print(f"Unknown keymap {name}, using default")
+            name = 'default'
+        bindings = {**KEYMAPS[name], **self.config.get('keys', {})}
+
+        self.keys = {}
+        for action, keys in bindings.items():
+            if action not in self.actions:
+                print(f"Unknown action {action} in [keys]")
+                continue
+            for key in [keys] if isinstance(keys, str) else keys:
+                self.keys[' ' if key == 'space' else key] = action
+
+        # Our keys win over matplotlib's own (like 'left' for "back")
+        for name in plt.rcParams:
+            if name.startswith('keymap.'):
+                plt.rcParams[name] = [k for k in plt.rcParams[name] if k not in self.keys]
+
+    def palette_matches(self, text):
+        """The actions matching what's typed into the palette, best first, and their keys."""
+        keys = {}
+        for key, action in self.keys.items():
+            keys.setdefault(action, []).append('space' if key == ' ' else key)
+        scored = [(fuzzy_score(text, action), action) for action in self.actions]
+        scored = sorted((s for s in scored if s[0] is not None), key=lambda s: s[0])
+        return [(action, keys.get(action, [])) for _, action in scored]
+
+    def open_palette(self):
+        """ctrl+p: type to find an action, Enter does the first one listed."""
+        self.palette.begin_typing()
+        self.update_palette(self.palette.text)
+
+    def update_palette(self, text):
+        """List what matches as it's typed."""
+        if self.running_palette:
+            return
+        matches = self.palette_matches(text)
+        lines = [f"{action:<16} {', '.join(keys)}" for action, keys in matches[:PALETTE_LINES]]
+        if len(matches) > PALETTE_LINES:
+            lines.append(f"and {len(matches) - PALETTE_LINES} more")
+        self.palette_list.set_text("\n".join(lines) or "nothing matches")
+        self.palette_list.set_visible(True)
+        self.fig.canvas.draw_idle()
+
+    def run_palette(self, text):
+        """Do the best match for what was typed (after putting the palette away)."""
+        # Clearing the box submits it again
+        if self.running_palette:
+            return
+        self.running_palette = True
         try:
-            # Get properties
+            matches = self.palette_matches(text) if text.strip() else []
+            self.palette.set_val("")
+            self.palette_list.set_visible(False)
+            self.fig.canvas.draw_idle()
+        finally:
+            self.running_palette = False
+        if matches:
+            self.actions[matches[0][0]]()
+        elif text.strip():
+            print(f"No action like '{text}'")
+
+    def run_command(self, text):
+        """
+        What's typed after ':'
+
+            goto 1:23.5         put the cursor there (any time parse_time() takes)
+            goto sample 123456  or on that sample
+            width 50ms          view that much around the middle
+            width sample 4800
+            over -0.1           find the next samples at or above this dBFS (default 0, like 'x')
+            silence -50 200ms   what 'd' and 'D' take for silence (default -60 dBFS for 100ms)
+            null align gain     null test the two files, aligning them and matching gain if asked to
+            null off
+            effect echo 0.3     run the first file through effects (convert's, '|' between them)
+            effect 2 fx.txt     or the second file, through the effects listed in fx.txt
+            effect 2 off        ('e' switches between with and without effects)
+            export cut.wav      write the selection (or the view) of the first file ('E' starts one)
+            export 2 cut.wav    or of the second file
+            export a.raw s16le  as raw (or WAV 16, 24, 32 or float) rather than the file's format
+            trim                edit the first file down to the selection ('u' undoes edits)
+            delete 2            take the selection out of the second file
+            gain -6             turn the selection down
+            normalize -1        make the selection's peak -1 dBFS (default 0)
+            save 2 new.wav      write all of the second file out (like export)
+            onsets 2            mark where the hits are in the second file ('T' does the focused one)
+            onsets off          and take those markers away again
+            screenshot a.svg    draw the view of the focused file as .png or .svg ('P' starts one)
+            screenshot a.txt    or as text, braille unless 'ascii' or 'blocks' comes after the name
+        """
+        # Clearing the box submits it again
+        if self.running_command or not text.strip():
+            return
+        self.running_command = True
+        try:
+            words = text.split()
+            pos = None
+            if words[0] == 'over' and len(words) <= 2:
+                try:
+                    self.over_db = float(words[1]) if len(words) == 2 else 0.0
+                except ValueError:
+                    print(f"Bad level '{words[1]}'")
+                    return
+                self.next_over()
+                return
+            if words[0] == 'null':
+                options = set(words[1:])
+                if options == {'off'}:
+                    if self.null is not None:
+                        self.toggle_null()
+                elif options <= {'align', 'gain'}:
+                    self.set_null('align' in options, 'gain' in options)
+                else:
+                    print(f"Don't know how to '{text}'")
+                return
+            if words[0] in ('trim', 'delete', 'gain', 'normalize', 'save'):
+                args = words[1:]
+                i = 0
+                # A file number first (unless it's all gain gets)
+                if args and re.fullmatch(r'[1-9]', args[0]) and (len(args) >= 2 or words[0] != 'gain'):
+                    i = int(args.pop(0)) - 1
+                self.edit(words[0], i, args)
+                return
+            if words[0] == 'onsets' and len(words) <= 2:
+                if words[1:] == ['off']:
+                    self.clear_onsets()
+                elif len(words) == 1 or re.fullmatch(r'[1-9]', words[1]):
+                    self.find_onsets(int(words[1]) - 1 if len(words) == 2 else None)
+                else:
+                    print(f"Don't know how to '{text}'")
+                return
+            if words[0] == 'screenshot' and 2 <= len(words) <= 3:
+                self.screenshot(*words[1:])
+                return
+            if words[0] == 'export' and 2 <= len(words) <= 4:
+                args = words[1:]
+                i = 0
+                if re.fullmatch(r'[1-9]', args[0]) and len(args) >= 2:
+                    i = int(args.pop(0)) - 1
+                if len(args) > 2:
+                    print(f"Don't know how to '{text}'")
+                else:
+                    self.export(i, *args)
+                return
+            if words[0] == 'effect' and len(words) >= 2:
+                i = 0
+                spec = text.split(None, 1)[1]
+                if re.fullmatch(r'[1-9]', words[1]):
+                    i = int(words[1]) - 1
+                    spec = text.split(None, 2)[2] if len(words) > 2 else ''
+                self.set_effects(i, None if spec.strip() == 'off' else spec.strip())
+                return
+            if words[0] == 'silence' and 2 <= len(words) <= 3:
+                try:
+                    level = float(words[1])
+                except ValueError:
+                    level = None
+                secs = parse_time(words[2]) if len(words) == 3 else self.silence_secs
+                if level is None or secs is None or secs <= 0:
+                    print(f"Bad silence '{text}'")
+                else:
+                    self.silence_db, self.silence_secs = level, secs
+                    self.next_silence()
+                return
+            if len(words) == 3 and words[1] in ('sample', 'samples') and re.fullmatch(r'\d+', words[2]):
+                pos = int(words[2])
+            elif len(words) == 2:
+                secs = parse_time(words[1])
+                pos = None if secs is None or secs < 0 else secs * self.rate
             xlim = self.ax.get_xlim()
-            ylim = self.ax.get_ylim()
-
             width = xlim[1] - xlim[0]
-            height = ylim[1] - ylim[0]
-
-            changed = False
-
-            if event.key == 'right':
-                shift = width * 0.25
-                self.ax.set_xlim(xlim[0] + shift, xlim[1] + shift)
-                changed = True
-            elif event.key == 'left':
-                shift = width * 0.25
-                self.ax.set_xlim(xlim[0] - shift, xlim[1] - shift)
-                changed = True
-            elif event.key == 'up':
-                shift = height * 0.25
-                self.ax.set_ylim(ylim[0] + shift, ylim[1] + shift)
-                changed = True
-            elif event.key == 'down':
-                shift = height * 0.25
-                self.ax.set_ylim(ylim[0] - shift, ylim[1] - shift)
-                changed = True
-            elif event.key == 'pagedown': # Zoom In (0.5x width)
-                center = (xlim[0] + xlim[1]) / 2
-                new_width = width * 0.5
-                new_width = max(new_width, self.min_zoom_samples)
-                new_start = center - (new_width / 2)
-                # Clamp start
-                new_start = max(0, min(new_start, self.max_samples - new_width))
-                self.ax.set_xlim(new_start, new_start + new_width)
-                changed = True
-            elif event.key == 'pageup': # Zoom Out (2x width)
+            if pos is None or words[0] not in ('goto', 'width'):
+                print(f"Don't know how to '{text}'")
+            elif words[0] == 'goto':
+                self.set_cursor(pos)
+                self.update_view(self.cursor - width / 2, width)
+                self.sync_slider()
+            else:
                 center = (xlim[0] + xlim[1]) / 2
-                new_width = width * 2.0
-                new_width = min(new_width, self.max_samples)
-                new_start = center - (new_width / 2)
-                # Clamp start
-                new_start = max(0, min(new_start, self.max_samples - new_width))
-                self.ax.set_xlim(new_start, new_start + new_width)
-                changed = True
-
-            if changed:
-                # Reload data for new X view
-                # We need new xlim
-                new_xlim = self.ax.get_xlim()
-                self.get_chunk(new_xlim[0], new_xlim[1] - new_xlim[0])
-
-                # Sync slider silently
-                if hasattr(self, 'slider'):
-                    old_eventson = self.slider.eventson
-                    self.slider.eventson = False
-
-                    if self.x_mode == 'Time':
-                        s = new_xlim[0] / self.rate
-                        e = new_xlim[1] / self.rate
-                        self.slider.set_val((s, e))
-                        self.update_slider_text((s, e))
-                    else:
-                        self.slider.set_val((new_xlim[0], new_xlim[1]))
-                        self.update_slider_text((new_xlim[0], new_xlim[1]))
-
-                    self.slider.eventson = old_eventson
-
-                self.fig.canvas.draw_idle()
-
+                self.update_view(center - pos / 2, pos)
+                self.sync_slider()
         finally:
-             self.navigating = False
+            self.command.set_val("")
+            self.running_command = False
 
-        # Space Bar (Reset Zoom) - Only reset Y axis to fit visible data (Auto-scale)
-        if event.key == ' ':
+    def navigate(self, change, amount):
+        """Change the view (with the xlim callback held off), then load the data for it."""
+        self.navigating = True
+        try:
+            change(amount)
             xlim = self.ax.get_xlim()
-            width = xlim[1] - xlim[0]
-            self.update_view(xlim[0], width)
+            self.get_chunk(xlim[0], xlim[1] - xlim[0])
+            self.sync_slider()
+            self.fig.canvas.draw_idle()
+        finally:
+            self.navigating = False
+
+    def pan_x(self, fraction):
+        xlim = self.ax.get_xlim()
+        shift = (xlim[1] - xlim[0]) * fraction
+        self.ax.set_xlim(xlim[0] + shift, xlim[1] + shift)
+
+    def zoom_x(self, factor):
+        """Zoom around the middle of the view."""
+        xlim = self.ax.get_xlim()
+        center = (xlim[0] + xlim[1]) / 2
+        new_width = max(self.min_zoom_samples, min((xlim[1] - xlim[0]) * factor, self.max_samples))
+        new_start = max(0, min(center - new_width / 2, self.max_samples - new_width))
+        self.ax.set_xlim(new_start, new_start + new_width)
+
+    def pan_y(self, fraction):
+        ylim = self.ax.get_ylim()
+        shift = (ylim[1] - ylim[0]) * fraction
+        self.ax.set_ylim(ylim[0] + shift, ylim[1] + shift)
+        self.set_y_locked(True)
+
+    def zoom_y(self, factor):
+        """Vertical zoom around the middle."""
+        ylim = self.ax.get_ylim()
+        center = (ylim[0] + ylim[1]) / 2
+        half = (ylim[1] - ylim[0]) * factor / 2
+        self.ax.set_ylim(center - half, center + half)
+        self.set_y_locked(True)
+
+    def toggle_y_lock(self):
+        self.set_y_locked(not self.y_locked)
+        self.fig.canvas.draw_idle()
+
+    def mark_in(self):
+        """In point at the cursor."""
+        if self.cursor is not None:
+            self.snap_selection(self.cursor, self.sel_out)
+
+    def mark_out(self):
+        """Out point just after the cursor, so the cursor's sample is in."""
+        if self.cursor is not None:
+            self.snap_selection(self.sel_in, self.cursor + 1)
+
+    def auto_y(self):
+        """Back to auto-scaling the Y axis to fit the visible data."""
+        self.set_y_locked(False)
+        xlim = self.ax.get_xlim()
+        self.update_view(xlim[0], xlim[1] - xlim[0])
 
     def on_select(self, eclick, erelease):
         """Handle rectangle selection."""
@@ -571,11 +2855,12 @@ class WaveformVisualizer:
 
              self.ax.set_xlim(start_sample, start_sample + new_width)
 
-             # Y Axis Logic
+             # Y Axis Logic: keep what was selected until 'a'
              y_min = min(y1, y2)
              y_max = max(y1, y2)
 
              self.ax.set_ylim(y_min, y_max)
+             self.set_y_locked(True)
 
              self.fig.canvas.draw_idle()
 
@@ -600,9 +2885,42 @@ class WaveformVisualizer:
 
 if __name__ == "__main__":
     parser = argparse.ArgumentParser(description="Linux Audio Waveform Visualizer 2026 (mmap)")
-    parser.add_argument('files', nargs='+', help="Input .bin files (int32)")
-    parser.add_argument('--rate', type=int, default=48000, help="Sample rate (Hz)")
+    parser.add_argument('files', nargs='*', help="Input raw files (int32 unless --format says otherwise), '-' for stdin")
+    parser.add_argument('--rate', type=int, help="Sample rate (Hz), default from the sidecar file or 48000")
     parser.add_argument('--min-zoom-samples', type=int, default=100, help="Minimum samples to show when zoomed in")
+    parser.add_argument('--format', choices=SAMPLE_FORMATS.keys(), help="Raw sample format, default from the sidecar file or s32le")
+    parser.add_argument('--channels', type=int, help="Interleaved channels, default from each file's sidecar or 1")
+    parser.add_argument('--session', help="Session file to restore (and save to when done), files and all")
+    parser.add_argument('--follow', action='store_true', help="Keep updating as the files grow (like 'tail -f')")
     args = parser.parse_args()
 
-    app = WaveformVisualizer(args.files, args.rate, args.min_zoom_samples)
+    # A session has the files, and how they were read
+    session = read_session(args.session) if args.session else {}
+    if not args.files:
+        args.files = session.get('files', [])
+        if not args.files:
+            parser.error("no files, and no session to take them from")
+    for arg in ('rate', 'format', 'channels'):
+        if getattr(args, arg) is None:
+            setattr(args, arg, session.get(arg))
+
+    # All the files get shown at the same rate and format, so the
+    # first one's sidecar decides unless the command line does, and
+    # the config file only has defaults for when neither says
+    config = read_config()
+    meta = read_sidecar(args.files[0])
+    if args.rate is None:
+        args.rate = int(meta.get('rate', config.get('rate', 48000)))
+    if args.format is None:
+        args.format = meta.get('format', config.get('format', 's32le'))
+    if args.format not in SAMPLE_FORMATS:
+        print(f"Unsupported sample format {args.format}, using s32le")
+        args.format = 's32le'
+
+    if args.channels is not None and args.channels < 1:
+        parser.error("--channels must be at least 1")
+    if args.files.count('-') > 1:
+        parser.error("stdin can only be read once")
+
+    app = WaveformVisualizer(args.files, args.rate, args.min_zoom_samples, args.follow, args.format, args.channels,
+                             config, args.session, session)
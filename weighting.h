@@ -0,0 +1,124 @@
+//
+// A- and C-weighting filters (IEC 61672), and the K-weighting
+// used for loudness measurement (ITU-R BS.1770)
+//
+// These are just the analog weighting curves turned into a
+// cascade of biquads with the bilinear transform, normalized
+// to 0dB at 1kHz. The transform squishes the top end, so the
+// 12.2kHz poles make the high frequencies roll off a bit early
+// at 48kHz. Below 10kHz or so it's within a fraction of a dB,
+// which is plenty for looking at noise captures.
+//
+// They are also exposed as "helper" effects, so you can do
+// things like
+//
+//	./convert aweight input.raw aweighted.raw
+//
+#define WEIGHT_F1 20.598997
+#define WEIGHT_F2 107.65265
+#define WEIGHT_F3 737.86223
+#define WEIGHT_F4 12194.217
+
+struct weighting {
+	int nr;
+	struct biquad bq[3];
+};
+
+static inline void weighting_normalize(struct weighting *w)
+{
+	float gain = 1;
+
+	for (int i = 0; i < w->nr; i++)
+		gain *= biquad_response(&w->bq[i].coeff, 1000);
+
+	// Put the whole gain correction in the first stage
+	w->bq[0].coeff.b0 /= gain;
+	w->bq[0].coeff.b1 /= gain;
+	w->bq[0].coeff.b2 /= gain;
+}
+
+//
+//                  s^4
+//   ---------------------------------
+//   (s+w1)^2 (s+w2) (s+w3) (s+w4)^2
+//
+static inline void weighting_a(struct weighting *w)
+{
+	double w1 = 2*M_PI*WEIGHT_F1, w2 = 2*M_PI*WEIGHT_F2;
+	double w3 = 2*M_PI*WEIGHT_F3, w4 = 2*M_PI*WEIGHT_F4;
+
+	w->nr = 3;
+	_biquad_bilinear(&w->bq[0].coeff,
+		(double[3]) { 1, 0, 0 }, (double[3]) { 1, 2*w1, w1*w1 });
+	_biquad_bilinear(&w->bq[1].coeff,
+		(double[3]) { 1, 0, 0 }, (double[3]) { 1, w2+w3, w2*w3 });
+	_biquad_bilinear(&w->bq[2].coeff,
+		(double[3]) { 0, 0, 1 }, (double[3]) { 1, 2*w4, w4*w4 });
+	weighting_normalize(w);
+}
+
+//
+//           s^2
+//   -------------------
+//   (s+w1)^2 (s+w4)^2
+//
+static inline void weighting_c(struct weighting *w)
+{
+	double w1 = 2*M_PI*WEIGHT_F1, w4 = 2*M_PI*WEIGHT_F4;
+
+	w->nr = 2;
+	_biquad_bilinear(&w->bq[0].coeff,
+		(double[3]) { 1, 0, 0 }, (double[3]) { 1, 2*w1, w1*w1 });
+	_biquad_bilinear(&w->bq[1].coeff,
+		(double[3]) { 0, 0, 1 }, (double[3]) { 1, 2*w4, w4*w4 });
+	weighting_normalize(w);
+}
+
+//
+// K-weighting is a high shelf (+4dB above ~1.5kHz, roughly the
+// effect of the head) followed by a highpass at ~38Hz. The spec
+// gives the coefficients for 48kHz directly, so use those rather
+// than trying to come up with an analog prototype. Note that this
+// is *not* normalized to 0dB at 1kHz.
+//
+static inline void weighting_k(struct weighting *w)
+{
+	w->nr = 2;
+	w->bq[0].coeff = (struct biquad_coeff) {
+		1.53512485958697f, -2.69169618940638f, 1.19839281085285f,
+		-1.69065929318241f, 0.73248077421585f };
+	w->bq[1].coeff = (struct biquad_coeff) {
+		1, -2, 1,
+		-1.99004745483398f, 0.99007225036621f };
+}
+
+static inline float weighting_step(struct weighting *w, float in)
+{
+	for (int i = 0; i < w->nr; i++)
+		in = biquad_step(&w->bq[i], in);
+	return in;
+}
+
+static inline float weighting_response(struct weighting *w, float f)
+{
+	float gain = 1;
+
+	for (int i = 0; i < w->nr; i++)
+		gain *= biquad_response(&w->bq[i].coeff, f);
+	return gain;
+}
+
+//
+// The helper effects. Note that 'init' gets called for
+// every block, so it only re-does the coefficients and
+// leaves the filter state alone.
+//
+static struct weighting aweight, cweight;
+
+static inline void aweight_describe(float pot[4]) { fprintf(stderr, "\n"); }
+static inline void aweight_init(float pot[4]) { weighting_a(&aweight); }
+static inline float aweight_step(float in) { return weighting_step(&aweight, in); }
+
+static inline void cweight_describe(float pot[4]) { fprintf(stderr, "\n"); }
+static inline void cweight_init(float pot[4]) { weighting_c(&cweight); }
+static inline float cweight_step(float in) { return weighting_step(&cweight, in); }